@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{Context, Result};
+use lending_dapp::MintExtensions;
+use serde::Deserialize;
+
+/// Asset file read by `init-bank`:
+///
+/// ```toml
+/// [[assets]]
+/// ticker_symbol = "USDC"
+/// mint = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+/// oracle_feed_id = "0xeaa020c61cc479712813461ce153894a96a6c00b21ed0cfc2798d1f9a9e9c94a"
+/// decimals = 6
+/// liquidation_threshold = 0.8
+/// max_ltv = 0.75
+/// ```
+///
+/// `token_program` defaults to the SPL Token program. Mints with a freeze
+/// authority or issuer-controlled Token-2022 extensions are rejected unless
+/// the asset allowlists them:
+///
+/// ```toml
+/// [assets.allowed_mint_extensions]
+/// freeze_authority = true
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct AssetFile {
+    pub assets: Vec<AssetConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssetConfig {
+    pub ticker_symbol: String,
+    pub mint: String,
+    pub oracle_feed_id: String,
+    pub decimals: u8,
+    pub liquidation_threshold: f64,
+    pub max_ltv: f64,
+    pub token_program: Option<String>,
+    #[serde(default)]
+    pub allowed_mint_extensions: AllowedMintExtensions,
+}
+
+/// Mirrors `MintExtensions`; fields left out are not allowed.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AllowedMintExtensions {
+    pub freeze_authority: bool,
+    pub permanent_delegate: bool,
+    pub transfer_hook: bool,
+    pub non_transferable: bool,
+    pub default_account_state: bool,
+}
+
+impl From<&AllowedMintExtensions> for MintExtensions {
+    fn from(allowed: &AllowedMintExtensions) -> Self {
+        MintExtensions {
+            freeze_authority: allowed.freeze_authority,
+            permanent_delegate: allowed.permanent_delegate,
+            transfer_hook: allowed.transfer_hook,
+            non_transferable: allowed.non_transferable,
+            default_account_state: allowed.default_account_state,
+        }
+    }
+}
+
+impl AssetFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("parsing {}", path.display()))
+    }
+}
+
+impl AssetConfig {
+    pub fn mint(&self) -> Result<Pubkey> {
+        self.mint
+            .parse()
+            .with_context(|| format!("invalid mint for {}", self.ticker_symbol))
+    }
+
+    pub fn token_program(&self) -> Result<Pubkey> {
+        match &self.token_program {
+            Some(token_program) => token_program
+                .parse()
+                .with_context(|| format!("invalid token program for {}", self.ticker_symbol)),
+            None => Ok(anchor_spl::token::ID),
+        }
+    }
+}
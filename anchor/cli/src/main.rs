@@ -0,0 +1,539 @@
+mod assets;
+mod keypair;
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_client::solana_sdk::signature::{Keypair, Signer};
+use anchor_client::{Client, Cluster, Program};
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Parser, Subcommand};
+use lending_client::instructions::{self as ix, BankKeys};
+use lending_client::math::{borrowed_amount, deposited_amount, portfolio_health};
+use lending_client::pda::{find_bank_address, find_market_address, find_price_feed_address};
+use lending_client::PROGRAM_ID;
+use lending_dapp::{Bank, BankConfigParams, Market, MintExtensions, User, SHARE_PRICE_SCALE};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::assets::{AssetConfig, AssetFile};
+use crate::keypair::load_keypair;
+
+#[derive(Parser)]
+#[command(name = "lending-cli", about = "Operator tool for the lending program")]
+struct Cli {
+    /// RPC URL or cluster moniker (localnet, devnet, mainnet).
+    #[arg(
+        long,
+        short,
+        env = "LENDING_RPC_URL",
+        default_value = "http://127.0.0.1:8899"
+    )]
+    url: Cluster,
+    /// Keypair paying for and signing admin transactions.
+    #[arg(
+        long,
+        short,
+        env = "LENDING_KEYPAIR",
+        default_value = "~/.config/solana/id.json"
+    )]
+    keypair: String,
+    /// Pyth push oracle shard the bank price feeds are read from.
+    #[arg(long, default_value_t = 0)]
+    price_feed_shard: u16,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the market account with the default asset registry.
+    InitMarket,
+    /// Create the bank of every asset in the file. Each asset must already be
+    /// in the market registry with the same feed and decimals, see
+    /// `set-supported-asset`.
+    InitBank {
+        /// TOML file listing the assets, see `assets.rs` for the format.
+        #[arg(long)]
+        assets: PathBuf,
+        /// Only create banks for these tickers.
+        #[arg(long)]
+        only: Vec<String>,
+    },
+    /// Add an asset to the market registry, or replace its feed and decimals.
+    SetSupportedAsset {
+        #[arg(long)]
+        ticker: String,
+        /// Pyth feed id, as hex.
+        #[arg(long)]
+        oracle_feed_id: String,
+        #[arg(long)]
+        decimals: u8,
+    },
+    /// Remove an asset from the market registry.
+    RemoveSupportedAsset {
+        #[arg(long)]
+        ticker: String,
+    },
+    /// Update a bank's risk parameters, liquidation terms, flash loan fee and
+    /// isolation settings.
+    UpdateConfig {
+        #[arg(long)]
+        mint: Pubkey,
+        #[arg(long)]
+        liquidation_threshold: Option<f64>,
+        #[arg(long)]
+        max_ltv: Option<f64>,
+        /// Weight applied to this asset's debt when checking new borrows.
+        #[arg(long)]
+        liability_weight_init: Option<f64>,
+        /// Weight applied to this asset's debt when checking for liquidation.
+        #[arg(long)]
+        liability_weight_maint: Option<f64>,
+        #[arg(long)]
+        flash_loan_fee_bps: Option<u16>,
+        #[arg(long)]
+        close_factor_bps: Option<u64>,
+        #[arg(long)]
+        liquidation_bonus_bps: Option<u64>,
+        /// Only allow this bank's collateral on its own, against stablecoins.
+        #[arg(long)]
+        isolated: Option<bool>,
+        /// Cap on debt against the bank's isolated collateral, in cents.
+        #[arg(long)]
+        debt_ceiling: Option<u64>,
+        /// Allow borrowing this stablecoin against isolated collateral.
+        #[arg(long)]
+        borrowable_in_isolation: Option<bool>,
+        /// E-mode category the bank belongs to, 0 for none.
+        #[arg(long)]
+        emode_category: Option<u8>,
+        /// Count deposits towards borrowing power.
+        #[arg(long)]
+        can_be_collateral: Option<bool>,
+        /// Allow borrowing the bank's asset.
+        #[arg(long)]
+        borrowing_enabled: Option<bool>,
+    },
+    /// Create or update an e-mode category for correlated assets.
+    SetEmodeCategory {
+        #[arg(long)]
+        id: u8,
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        max_ltv: f64,
+        #[arg(long)]
+        liquidation_threshold: f64,
+    },
+    /// Remove an e-mode category from the market.
+    RemoveEmodeCategory {
+        #[arg(long)]
+        id: u8,
+    },
+    /// Stop deposits, borrows and flash loans on a bank.
+    Pause {
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Reopen a paused bank.
+    Unpause {
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Create the bank's receipt mint so deposits can be taken as
+    /// transferable receipt tokens.
+    EnableReceipts {
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Show a bank's totals, utilization, rates and share prices.
+    Bank {
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// List every user with an open position.
+    Positions {
+        /// Only show positions in the bank for this mint.
+        #[arg(long)]
+        mint: Option<Pubkey>,
+    },
+    /// Value a user's positions at the current oracle prices.
+    Health {
+        /// Wallet that owns the user account.
+        #[arg(long)]
+        owner: Pubkey,
+    },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    // Inspection commands only read accounts, so they don't need a wallet.
+    let payer = match cli.command {
+        Command::Bank { .. } | Command::Positions { .. } | Command::Health { .. } => Keypair::new(),
+        _ => load_keypair(&cli.keypair)?,
+    };
+    let payer = Arc::new(payer);
+    let client = Client::new_with_options(
+        cli.url.clone(),
+        payer.clone(),
+        CommitmentConfig::confirmed(),
+    );
+    let program = client.program(PROGRAM_ID)?;
+    let authority = payer.pubkey();
+
+    match cli.command {
+        Command::InitMarket => send(&program, ix::init_market(authority)),
+        Command::InitBank { assets, only } => init_banks(&program, authority, &assets, &only),
+        Command::SetSupportedAsset {
+            ticker,
+            oracle_feed_id,
+            decimals,
+        } => send(
+            &program,
+            ix::set_supported_asset(authority, ticker, oracle_feed_id, decimals),
+        ),
+        Command::RemoveSupportedAsset { ticker } => {
+            send(&program, ix::remove_supported_asset(authority, ticker))
+        }
+        Command::UpdateConfig {
+            mint,
+            liquidation_threshold,
+            max_ltv,
+            liability_weight_init,
+            liability_weight_maint,
+            flash_loan_fee_bps,
+            close_factor_bps,
+            liquidation_bonus_bps,
+            isolated,
+            debt_ceiling,
+            borrowable_in_isolation,
+            emode_category,
+            can_be_collateral,
+            borrowing_enabled,
+        } => {
+            let params = BankConfigParams {
+                liquidation_threshold,
+                max_ltv,
+                liability_weight_init,
+                liability_weight_maint,
+                flash_loan_fee_bps,
+                close_factor_bps,
+                liquidation_bonus_bps,
+                isolated,
+                debt_ceiling,
+                borrowable_in_isolation,
+                emode_category,
+                can_be_collateral,
+                borrowing_enabled,
+                ..Default::default()
+            };
+            send(&program, ix::update_bank_config(authority, mint, params))
+        }
+        Command::SetEmodeCategory {
+            id,
+            name,
+            max_ltv,
+            liquidation_threshold,
+        } => send(
+            &program,
+            ix::set_emode_category(authority, id, name, max_ltv, liquidation_threshold),
+        ),
+        Command::RemoveEmodeCategory { id } => {
+            send(&program, ix::remove_emode_category(authority, id))
+        }
+        Command::Pause { mint } => set_paused(&program, authority, mint, true),
+        Command::Unpause { mint } => set_paused(&program, authority, mint, false),
+        Command::EnableReceipts { mint } => {
+            let token_program = program.rpc().get_account(&mint)?.owner;
+            let keys = BankKeys::new(mint, token_program);
+            send(&program, ix::init_receipt_mint(authority, keys))
+        }
+        Command::Bank { mint } => show_bank(&program, mint),
+        Command::Positions { mint } => show_positions(&program, mint),
+        Command::Health { owner } => show_health(&program, owner, cli.price_feed_shard),
+    }
+}
+
+fn send(program: &Program<Arc<Keypair>>, instruction: Instruction) -> Result<()> {
+    let signature = program.request().instruction(instruction).send()?;
+    println!("{signature}");
+    Ok(())
+}
+
+fn init_banks(
+    program: &Program<Arc<Keypair>>,
+    authority: Pubkey,
+    path: &Path,
+    only: &[String],
+) -> Result<()> {
+    let file = AssetFile::load(path)?;
+    let market: Market = program
+        .account(find_market_address().0)
+        .context("market not found, run init-market first")?;
+    let assets: Vec<_> = file
+        .assets
+        .iter()
+        .filter(|asset| only.is_empty() || only.contains(&asset.ticker_symbol))
+        .collect();
+    // Registry edits change how existing banks are valued, so they are never
+    // made on the side of creating a bank.
+    for asset in &assets {
+        check_registered(&market, asset)?;
+    }
+    for asset in assets {
+        let keys = BankKeys::new(asset.mint()?, asset.token_program()?);
+        let signature = program
+            .request()
+            .instruction(ix::init_bank(
+                authority,
+                keys,
+                asset.liquidation_threshold,
+                asset.max_ltv,
+                asset.oracle_feed_id.clone(),
+                asset.ticker_symbol.clone(),
+                (&asset.allowed_mint_extensions).into(),
+            ))
+            .send()
+            .with_context(|| format!("creating bank for {}", asset.ticker_symbol))?;
+        println!(
+            "{}: bank {} ({signature})",
+            asset.ticker_symbol,
+            keys.bank()
+        );
+    }
+    Ok(())
+}
+
+/// Fails unless the market registry lists `asset` with the feed and decimals
+/// the asset file gives it.
+fn check_registered(market: &Market, asset: &AssetConfig) -> Result<()> {
+    let ticker = &asset.ticker_symbol;
+    let supported = market.get_supported_asset(ticker).ok_or_else(|| {
+        anyhow!("{ticker} is not in the market registry, add it with set-supported-asset first")
+    })?;
+    let feed_id = hex_feed_id(&supported.oracle_feed_id);
+    if feed_id != normalize_hex(&asset.oracle_feed_id) || supported.decimals != asset.decimals {
+        bail!(
+            "{ticker} is registered with feed {feed_id} and {} decimals but the asset file has \
+             feed {} and {} decimals; update the registry with set-supported-asset if the \
+             file is right",
+            supported.decimals,
+            asset.oracle_feed_id,
+            asset.decimals,
+        );
+    }
+    Ok(())
+}
+
+fn set_paused(
+    program: &Program<Arc<Keypair>>,
+    authority: Pubkey,
+    mint: Pubkey,
+    paused: bool,
+) -> Result<()> {
+    let params = BankConfigParams {
+        paused: Some(paused),
+        ..Default::default()
+    };
+    send(program, ix::update_bank_config(authority, mint, params))
+}
+
+/// Formats isolated debt, counted in cents, as dollars.
+fn usd_cents(amount: u64) -> String {
+    format!("${}.{:02}", amount / 100, amount % 100)
+}
+
+/// Comma-separated names of the issuer-controlled features a bank accepted.
+fn mint_extensions(extensions: &MintExtensions) -> String {
+    let names: Vec<&str> = [
+        (extensions.freeze_authority, "freeze authority"),
+        (extensions.permanent_delegate, "permanent delegate"),
+        (extensions.transfer_hook, "transfer hook"),
+        (extensions.non_transferable, "non-transferable"),
+        (extensions.default_account_state, "default account state"),
+    ]
+    .into_iter()
+    .filter_map(|(used, name)| used.then_some(name))
+    .collect();
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+fn show_bank(program: &Program<Arc<Keypair>>, mint: Pubkey) -> Result<()> {
+    let address = find_bank_address(&mint).0;
+    let bank: Bank = program.account(address)?;
+    let utilization = if bank.total_deposits == 0 {
+        0.0
+    } else {
+        bank.total_borrowed as f64 / bank.total_deposits as f64
+    };
+    println!("bank                   {address}");
+    println!("ticker                 {}", bank.config.ticker_symbol);
+    println!("mint                   {}", bank.token_mint_address);
+    println!("authority              {}", bank.authority);
+    println!("paused                 {}", bank.config.paused);
+    println!(
+        "mint extensions        {}",
+        mint_extensions(&bank.config.mint_extensions)
+    );
+    if bank.receipt_mint == Pubkey::default() {
+        println!("receipt mint           none");
+    } else {
+        println!("receipt mint           {}", bank.receipt_mint);
+    }
+    println!("total deposits         {}", bank.total_deposits);
+    println!("total borrowed         {}", bank.total_borrowed);
+    println!("utilization            {:.2}%", utilization * 100.0);
+    println!("borrow rate            {}", bank.interest_rate);
+    println!(
+        "supply rate            {}",
+        bank.interest_rate * utilization
+    );
+    println!(
+        "deposit share price    {}",
+        share_price(bank.deposit_share_price())
+    );
+    println!(
+        "borrow share price     {}",
+        share_price(bank.borrow_share_price())
+    );
+    println!("liquidation threshold  {}", bank.liquidation_threshold);
+    println!("max ltv                {}", bank.max_ltv);
+    println!(
+        "liability weights      {} init, {} maint",
+        bank.liability_weight_init, bank.liability_weight_maint
+    );
+    println!("close factor           {} bps", bank.close_factor);
+    println!("liquidation bonus      {} bps", bank.liquidation_bonus);
+    println!(
+        "flash loan fee         {} bps",
+        bank.config.flash_loan_fee_bps
+    );
+    if bank.config.isolated {
+        println!(
+            "isolated debt          {} / {}",
+            usd_cents(bank.isolated_debt),
+            usd_cents(bank.config.debt_ceiling)
+        );
+    }
+    if bank.config.borrowable_in_isolation {
+        println!("borrowable in isolation");
+    }
+    if !bank.config.can_be_collateral {
+        println!("not collateral");
+    }
+    if !bank.config.borrowing_enabled {
+        println!("borrowing disabled");
+    }
+    if bank.config.emode_category != 0 {
+        println!("e-mode category        {}", bank.config.emode_category);
+    }
+    println!(
+        "oracle feed            {}",
+        hex_feed_id(&bank.config.oracle_feed_id)
+    );
+    Ok(())
+}
+
+fn show_positions(program: &Program<Arc<Keypair>>, mint: Option<Pubkey>) -> Result<()> {
+    let bank_filter = mint.map(|mint| find_bank_address(&mint).0);
+    let users: Vec<(Pubkey, User)> = program.accounts(vec![])?;
+    let mut banks = BankCache::default();
+    for (address, user) in users {
+        for balance in &user.balances {
+            if balance.bank_address == Pubkey::default()
+                || bank_filter.is_some_and(|bank| bank != balance.bank_address)
+                || (balance.deposited_shares == 0 && balance.borrowed_shares == 0)
+            {
+                continue;
+            }
+            let bank = banks.get(program, balance.bank_address)?;
+            println!(
+                "{} user={} {:<5} deposited={} borrowed={}",
+                user.owner,
+                address,
+                bank.config.ticker_symbol,
+                deposited_amount(bank, balance.deposited_shares)?,
+                borrowed_amount(bank, balance.borrowed_shares)?,
+            );
+        }
+    }
+    Ok(())
+}
+
+fn show_health(program: &Program<Arc<Keypair>>, owner: Pubkey, shard: u16) -> Result<()> {
+    let address = lending_client::pda::find_user_address(&owner).0;
+    let user: User = program.account(address)?;
+    let mut banks = Vec::new();
+    for balance in &user.balances {
+        if balance.bank_address == Pubkey::default() {
+            continue;
+        }
+        let bank: Bank = program.account(balance.bank_address)?;
+        let feed = find_price_feed_address(shard, &bank.config.oracle_feed_id).0;
+        let price: PriceUpdateV2 = program
+            .account(feed)
+            .with_context(|| format!("price feed {feed} for {}", bank.config.ticker_symbol))?;
+        println!(
+            "{:<5} deposited={} borrowed={} price={}e{}",
+            bank.config.ticker_symbol,
+            deposited_amount(&bank, balance.deposited_shares)?,
+            borrowed_amount(&bank, balance.borrowed_shares)?,
+            price.price_message.price,
+            price.price_message.exponent,
+        );
+        banks.push((balance.bank_address, bank, price.price_message.price));
+    }
+    let market: Market = program.account(find_market_address().0)?;
+    let emode = market.user_emode(&user);
+    if let Some(category) = emode {
+        println!("e-mode                 {}", category.name);
+    }
+    let health = portfolio_health(&user, &banks, emode)?;
+    println!("collateral value       {}", health.collateral_value);
+    println!(
+        "borrowing power        {}",
+        health.weighted_collateral_value
+    );
+    println!(
+        "liquidation value      {}",
+        health.liquidation_collateral_value
+    );
+    println!("debt value             {}", health.debt_value);
+    println!("weighted debt          {}", health.weighted_debt_value);
+    println!("liquidation debt       {}", health.liquidation_debt_value);
+    println!("health factor          {}", health.health_factor());
+    Ok(())
+}
+
+#[derive(Default)]
+struct BankCache(HashMap<Pubkey, Bank>);
+
+impl BankCache {
+    fn get(&mut self, program: &Program<Arc<Keypair>>, address: Pubkey) -> Result<&Bank> {
+        match self.0.entry(address) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(program.account(address)?)),
+        }
+    }
+}
+
+fn share_price(price: u128) -> f64 {
+    price as f64 / SHARE_PRICE_SCALE as f64
+}
+
+fn hex_feed_id(feed_id: &[u8; 32]) -> String {
+    feed_id.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn normalize_hex(feed_id: &str) -> String {
+    feed_id.trim_start_matches("0x").to_lowercase()
+}
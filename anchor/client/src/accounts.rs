@@ -0,0 +1,15 @@
+use anchor_lang::{AccountDeserialize, Result};
+use lending_dapp::{Bank, Market, User};
+
+/// Deserializes raw account data, checking the account discriminator.
+pub fn deserialize_bank(mut data: &[u8]) -> Result<Bank> {
+    Bank::try_deserialize(&mut data)
+}
+
+pub fn deserialize_user(mut data: &[u8]) -> Result<User> {
+    User::try_deserialize(&mut data)
+}
+
+pub fn deserialize_market(mut data: &[u8]) -> Result<Market> {
+    Market::try_deserialize(&mut data)
+}
@@ -0,0 +1,12 @@
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+
+/// Decodes the data of one of the program's `emit_cpi!` inner instructions
+/// as an event of type `T`. Returns `None` for other events and for data
+/// that is not an event.
+pub fn decode_event<T: AnchorDeserialize + Discriminator>(data: &[u8]) -> Option<T> {
+    let mut data = data
+        .strip_prefix(EVENT_IX_TAG_LE)?
+        .strip_prefix(T::DISCRIMINATOR)?;
+    T::deserialize(&mut data).ok()
+}
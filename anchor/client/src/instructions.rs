@@ -0,0 +1,937 @@
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::{instruction::Instruction, system_program, sysvar};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token;
+use lending_dapp::{accounts, instruction, BankConfigParams, MintExtensions};
+
+use crate::pda::{
+    find_bank_address, find_event_authority_address, find_extra_account_metas_address,
+    find_market_address, find_program_data_address, find_receipt_mint_address,
+    find_treasury_address, find_user_address, find_wrapped_sol_address, get_user_token_address,
+};
+use crate::PROGRAM_ID;
+
+/// A bank together with the mint and token program it was created for.
+#[derive(Clone, Copy, Debug)]
+pub struct BankKeys {
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+}
+
+impl BankKeys {
+    pub fn new(mint: Pubkey, token_program: Pubkey) -> Self {
+        Self {
+            mint,
+            token_program,
+        }
+    }
+
+    pub fn bank(&self) -> Pubkey {
+        find_bank_address(&self.mint).0
+    }
+
+    pub fn treasury(&self) -> Pubkey {
+        find_treasury_address(&self.mint).0
+    }
+
+    pub fn user_token_account(&self, owner: &Pubkey) -> Pubkey {
+        get_user_token_address(owner, &self.mint, &self.token_program)
+    }
+
+    pub fn receipt_mint(&self) -> Pubkey {
+        find_receipt_mint_address(&self.mint).0
+    }
+
+    pub fn user_receipt_account(&self, owner: &Pubkey) -> Pubkey {
+        get_user_token_address(owner, &self.receipt_mint(), &self.token_program)
+    }
+}
+
+/// A bank with the Pyth price update account used to value it.
+#[derive(Clone, Copy, Debug)]
+pub struct PricedBank {
+    pub keys: BankKeys,
+    pub price_update: Pubkey,
+}
+
+impl std::ops::Deref for PricedBank {
+    type Target = BankKeys;
+
+    fn deref(&self) -> &BankKeys {
+        &self.keys
+    }
+}
+
+/// A `[bank, price_update]` pair passed as remaining accounts to the
+/// instructions that value the whole portfolio.
+#[derive(Clone, Copy, Debug)]
+pub struct HealthAccount {
+    pub bank: Pubkey,
+    pub price_update: Pubkey,
+}
+
+fn health_metas(health_accounts: &[HealthAccount]) -> Vec<AccountMeta> {
+    health_accounts
+        .iter()
+        .flat_map(|entry| {
+            [
+                AccountMeta::new_readonly(entry.bank, false),
+                AccountMeta::new_readonly(entry.price_update, false),
+            ]
+        })
+        .collect()
+}
+
+/// Remaining accounts that let the program move a mint with a transfer hook:
+/// the hook program, its extra-account-metas account and `extra_accounts`,
+/// the accounts that list resolves to. Append them to any instruction
+/// transferring the mint; after the health accounts where there are some.
+pub fn transfer_hook_metas(
+    mint: &Pubkey,
+    hook_program: &Pubkey,
+    extra_accounts: &[AccountMeta],
+) -> Vec<AccountMeta> {
+    let mut metas = vec![
+        AccountMeta::new_readonly(*hook_program, false),
+        AccountMeta::new_readonly(
+            find_extra_account_metas_address(mint, hook_program).0,
+            false,
+        ),
+    ];
+    metas.extend_from_slice(extra_accounts);
+    metas
+}
+
+/// Turns the swap program's own instruction into remaining accounts. The
+/// user signs the outer transaction, so signer flags are dropped here.
+fn swap_metas(swap: &Instruction) -> Vec<AccountMeta> {
+    swap.accounts
+        .iter()
+        .map(|meta| AccountMeta {
+            pubkey: meta.pubkey,
+            is_signer: false,
+            is_writable: meta.is_writable,
+        })
+        .collect()
+}
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+fn event_authority() -> Pubkey {
+    find_event_authority_address().0
+}
+
+pub fn init_market(owner: Pubkey) -> Instruction {
+    build(
+        accounts::InitMarket {
+            signer: owner,
+            program_data: find_program_data_address().0,
+            market: find_market_address().0,
+            system_program: system_program::ID,
+        },
+        instruction::InitMarket {},
+    )
+}
+
+fn update_market(owner: Pubkey) -> accounts::UpdateMarket {
+    accounts::UpdateMarket {
+        signer: owner,
+        market: find_market_address().0,
+        event_authority: event_authority(),
+        program: PROGRAM_ID,
+    }
+}
+
+pub fn set_supported_asset(
+    owner: Pubkey,
+    ticker_symbol: String,
+    oracle_key: String,
+    decimals: u8,
+) -> Instruction {
+    build(
+        update_market(owner),
+        instruction::SetSupportedAsset {
+            ticker_symbol,
+            oracle_key,
+            decimals,
+        },
+    )
+}
+
+pub fn remove_supported_asset(owner: Pubkey, ticker_symbol: String) -> Instruction {
+    build(
+        update_market(owner),
+        instruction::RemoveSupportedAsset { ticker_symbol },
+    )
+}
+
+pub fn add_swap_program(owner: Pubkey, program_id: Pubkey) -> Instruction {
+    build(
+        update_market(owner),
+        instruction::AddSwapProgram { program_id },
+    )
+}
+
+pub fn remove_swap_program(owner: Pubkey, program_id: Pubkey) -> Instruction {
+    build(
+        update_market(owner),
+        instruction::RemoveSwapProgram { program_id },
+    )
+}
+
+pub fn set_emode_category(
+    owner: Pubkey,
+    id: u8,
+    name: String,
+    max_ltv: f64,
+    liquidation_threshold: f64,
+) -> Instruction {
+    build(
+        update_market(owner),
+        instruction::SetEmodeCategory {
+            id,
+            name,
+            max_ltv,
+            liquidation_threshold,
+        },
+    )
+}
+
+pub fn remove_emode_category(owner: Pubkey, id: u8) -> Instruction {
+    build(
+        update_market(owner),
+        instruction::RemoveEmodeCategory { id },
+    )
+}
+
+pub fn init_bank(
+    authority: Pubkey,
+    bank: BankKeys,
+    liquidation_threshold: f64,
+    max_ltv: f64,
+    oracle_key: String,
+    ticker_symbol: String,
+    allowed_mint_extensions: MintExtensions,
+) -> Instruction {
+    build(
+        accounts::InitBank {
+            signer: authority,
+            market: find_market_address().0,
+            mint: bank.mint,
+            bank: bank.bank(),
+            bank_token_account: bank.treasury(),
+            token_program: bank.token_program,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::InitBank {
+            liquidation_threshold,
+            max_ltv,
+            oracle_key,
+            ticker_symbol,
+            allowed_mint_extensions,
+        },
+    )
+}
+
+pub fn update_bank_config(
+    authority: Pubkey,
+    mint: Pubkey,
+    params: BankConfigParams,
+) -> Instruction {
+    build(
+        accounts::UpdateBankConfig {
+            signer: authority,
+            market: find_market_address().0,
+            mint,
+            bank: find_bank_address(&mint).0,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::UpdateBankConfig { params },
+    )
+}
+
+pub fn init_user(owner: Pubkey) -> Instruction {
+    build(
+        accounts::InitUser {
+            signer: owner,
+            user_account: find_user_address(&owner).0,
+            system_program: system_program::ID,
+        },
+        instruction::InitUser {},
+    )
+}
+
+pub fn close_bank(authority: Pubkey, bank: BankKeys) -> Instruction {
+    build(
+        accounts::CloseBank {
+            signer: authority,
+            mint: bank.mint,
+            bank: bank.bank(),
+            bank_token_account: bank.treasury(),
+            user_account: find_user_address(&authority).0,
+            user_token_account: bank.user_token_account(&authority),
+            token_program: bank.token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+        },
+        instruction::CloseBank {},
+    )
+}
+
+/// `health_accounts`, when not empty, lists every bank the user has a
+/// position in, this one included, to value the position for the event.
+pub fn deposit(
+    owner: Pubkey,
+    bank: BankKeys,
+    amount: u64,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let mut ix = build(
+        accounts::Deposit {
+            signer: owner,
+            market: find_market_address().0,
+            mint: bank.mint,
+            bank: bank.bank(),
+            bank_token_account: bank.treasury(),
+            user_account: find_user_address(&owner).0,
+            user_token_account: bank.user_token_account(&owner),
+            token_program: bank.token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::Deposit { amount },
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+/// `health_accounts` lists every bank the user has a position in, this one
+/// included; only needed while the user has debt.
+pub fn withdraw(
+    owner: Pubkey,
+    bank: BankKeys,
+    amount: u64,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let mut ix = build(
+        accounts::Withdraw {
+            signer: owner,
+            market: find_market_address().0,
+            mint: bank.mint,
+            bank: bank.bank(),
+            bank_token_account: bank.treasury(),
+            user_account: find_user_address(&owner).0,
+            user_token_account: bank.user_token_account(&owner),
+            token_program: bank.token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::Withdraw { amount },
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+/// `health_accounts` lists every bank the user has a position in besides
+/// `collateral` and `borrowed`.
+pub fn borrow(
+    owner: Pubkey,
+    collateral: PricedBank,
+    borrowed: PricedBank,
+    amount: u64,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let mut ix = build(
+        accounts::Borrow {
+            signer: owner,
+            market: find_market_address().0,
+            collateral_mint: collateral.mint,
+            borrow_mint: borrowed.mint,
+            borrow_bank: borrowed.bank(),
+            bank_token_account: borrowed.treasury(),
+            user_account: find_user_address(&owner).0,
+            collateral_bank: collateral.bank(),
+            user_token_account: borrowed.user_token_account(&owner),
+            collateral_price_update: collateral.price_update,
+            borrow_price_update: borrowed.price_update,
+            token_program: borrowed.token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::Borrow { amount },
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+/// `isolated_bank` is the bank of the user's isolated collateral, if the
+/// user is in isolation mode. `health_accounts`, when not empty, lists every
+/// bank the user has a position in, this one included, to value the position
+/// for the event.
+pub fn repay(
+    owner: Pubkey,
+    bank: BankKeys,
+    amount: u64,
+    isolated_bank: Option<Pubkey>,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let mut ix = build(
+        accounts::Repay {
+            signer: owner,
+            market: find_market_address().0,
+            mint: bank.mint,
+            bank: bank.bank(),
+            bank_token_account: bank.treasury(),
+            user_account: find_user_address(&owner).0,
+            user_token_account: bank.user_token_account(&owner),
+            isolated_bank,
+            token_program: bank.token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::Repay { amount },
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+/// `bank` is the wrapped SOL bank; the signer pays in lamports.
+/// `health_accounts`, when not empty, lists every bank the user has a
+/// position in, this one included, to value the position for the event.
+pub fn deposit_sol(
+    owner: Pubkey,
+    bank: BankKeys,
+    amount: u64,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let mut ix = build(
+        accounts::DepositSol {
+            signer: owner,
+            market: find_market_address().0,
+            mint: bank.mint,
+            bank: bank.bank(),
+            bank_token_account: bank.treasury(),
+            user_account: find_user_address(&owner).0,
+            wrapped_sol_account: find_wrapped_sol_address(&owner).0,
+            token_program: bank.token_program,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::DepositSol { amount },
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+/// `bank` is the wrapped SOL bank; the signer receives lamports.
+/// `health_accounts` lists every bank the user has a position in, this one
+/// included; only needed while the user has debt.
+pub fn withdraw_sol(
+    owner: Pubkey,
+    bank: BankKeys,
+    amount: u64,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let mut ix = build(
+        accounts::WithdrawSol {
+            signer: owner,
+            market: find_market_address().0,
+            mint: bank.mint,
+            bank: bank.bank(),
+            bank_token_account: bank.treasury(),
+            user_account: find_user_address(&owner).0,
+            wrapped_sol_account: find_wrapped_sol_address(&owner).0,
+            token_program: bank.token_program,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::WithdrawSol { amount },
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+/// `borrowed` is the wrapped SOL bank; the signer receives lamports.
+/// `health_accounts` lists every bank the user has a position in besides
+/// `collateral` and `borrowed`.
+pub fn borrow_sol(
+    owner: Pubkey,
+    collateral: PricedBank,
+    borrowed: PricedBank,
+    amount: u64,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let mut ix = build(
+        accounts::BorrowSol {
+            signer: owner,
+            market: find_market_address().0,
+            collateral_mint: collateral.mint,
+            borrow_mint: borrowed.mint,
+            borrow_bank: borrowed.bank(),
+            bank_token_account: borrowed.treasury(),
+            user_account: find_user_address(&owner).0,
+            collateral_bank: collateral.bank(),
+            wrapped_sol_account: find_wrapped_sol_address(&owner).0,
+            collateral_price_update: collateral.price_update,
+            borrow_price_update: borrowed.price_update,
+            token_program: borrowed.token_program,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::BorrowSol { amount },
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+/// `health_accounts` lists every bank the user has a position in; zero
+/// `emode_category` leaves e-mode.
+pub fn set_user_emode(
+    owner: Pubkey,
+    emode_category: u8,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let mut ix = build(
+        accounts::SetUserEmode {
+            signer: owner,
+            market: find_market_address().0,
+            user_account: find_user_address(&owner).0,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::SetUserEmode { emode_category },
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+/// `health_accounts` lists every bank the user has a position in; they are
+/// only checked when excluding the deposit from collateral.
+pub fn set_collateral_enabled(
+    owner: Pubkey,
+    bank: Pubkey,
+    enabled: bool,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let mut ix = build(
+        accounts::SetCollateralEnabled {
+            signer: owner,
+            market: find_market_address().0,
+            bank,
+            user_account: find_user_address(&owner).0,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::SetCollateralEnabled { enabled },
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+/// `borrower` is the wallet owning the position; `health_accounts` lists
+/// every other bank it has a position in.
+pub fn liquidate(
+    liquidator: Pubkey,
+    borrower: Pubkey,
+    collateral: PricedBank,
+    borrowed: PricedBank,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let mut ix = build(
+        accounts::Liquidate {
+            liquidator,
+            market: find_market_address().0,
+            borrower_account: find_user_address(&borrower).0,
+            collateral_price_update: collateral.price_update,
+            borrowed_price_update: borrowed.price_update,
+            collateral_mint: collateral.mint,
+            borrowed_mint: borrowed.mint,
+            collateral_bank: collateral.bank(),
+            borrowed_bank: borrowed.bank(),
+            collateral_bank_token_account: collateral.treasury(),
+            borrowed_bank_token_account: borrowed.treasury(),
+            liquidator_collateral_token_account: collateral.user_token_account(&liquidator),
+            liquidator_borrowed_token_account: borrowed.user_token_account(&liquidator),
+            token_program: collateral.token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::Liquidate {},
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+/// `swap` is the swap program's instruction moving `collateral_amount` of
+/// collateral out of the user's wallet into the borrowed asset.
+/// `health_accounts` lists every other bank the user has a position in.
+pub fn repay_with_collateral(
+    owner: Pubkey,
+    collateral: PricedBank,
+    borrowed: PricedBank,
+    collateral_amount: u64,
+    min_out: u64,
+    swap: &Instruction,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let swap_accounts = swap_metas(swap);
+    let mut ix = build(
+        accounts::RepayWithCollateral {
+            signer: owner,
+            market: find_market_address().0,
+            collateral_mint: collateral.mint,
+            borrow_mint: borrowed.mint,
+            collateral_bank: collateral.bank(),
+            borrow_bank: borrowed.bank(),
+            collateral_bank_token_account: collateral.treasury(),
+            borrow_bank_token_account: borrowed.treasury(),
+            user_account: find_user_address(&owner).0,
+            user_collateral_token_account: collateral.user_token_account(&owner),
+            user_borrow_token_account: borrowed.user_token_account(&owner),
+            collateral_price_update: collateral.price_update,
+            borrow_price_update: borrowed.price_update,
+            swap_program: swap.program_id,
+            token_program: collateral.token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::RepayWithCollateral {
+            collateral_amount,
+            min_out,
+            swap_data: swap.data.clone(),
+            swap_accounts_len: swap_accounts.len() as u8,
+        },
+    );
+    ix.accounts.extend(swap_accounts);
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+/// `swap` is the swap program's instruction turning the flash borrowed
+/// debt asset into collateral in the user's wallet. `health_accounts` lists
+/// every other bank the user has a position in.
+#[allow(clippy::too_many_arguments)]
+pub fn leverage(
+    owner: Pubkey,
+    collateral: PricedBank,
+    borrowed: PricedBank,
+    collateral_amount: u64,
+    target_leverage_bps: u64,
+    max_slippage_bps: u16,
+    swap: &Instruction,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let swap_accounts = swap_metas(swap);
+    let mut ix = build(
+        accounts::Leverage {
+            signer: owner,
+            market: find_market_address().0,
+            collateral_mint: collateral.mint,
+            borrow_mint: borrowed.mint,
+            collateral_bank: collateral.bank(),
+            borrow_bank: borrowed.bank(),
+            collateral_bank_token_account: collateral.treasury(),
+            borrow_bank_token_account: borrowed.treasury(),
+            user_account: find_user_address(&owner).0,
+            user_collateral_token_account: collateral.user_token_account(&owner),
+            user_borrow_token_account: borrowed.user_token_account(&owner),
+            collateral_price_update: collateral.price_update,
+            borrow_price_update: borrowed.price_update,
+            swap_program: swap.program_id,
+            token_program: collateral.token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::Leverage {
+            collateral_amount,
+            target_leverage_bps,
+            max_slippage_bps,
+            swap_data: swap.data.clone(),
+            swap_accounts_len: swap_accounts.len() as u8,
+        },
+    );
+    ix.accounts.extend(swap_accounts);
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+/// `health_accounts` lists every other bank the user has a position in.
+pub fn swap_collateral(
+    owner: Pubkey,
+    source: PricedBank,
+    destination: PricedBank,
+    amount: u64,
+    min_out: u64,
+    swap: &Instruction,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let swap_accounts = swap_metas(swap);
+    let mut ix = build(
+        accounts::SwapCollateral {
+            signer: owner,
+            market: find_market_address().0,
+            source_mint: source.mint,
+            destination_mint: destination.mint,
+            source_bank: source.bank(),
+            destination_bank: destination.bank(),
+            source_bank_token_account: source.treasury(),
+            destination_bank_token_account: destination.treasury(),
+            user_account: find_user_address(&owner).0,
+            user_source_token_account: source.user_token_account(&owner),
+            user_destination_token_account: destination.user_token_account(&owner),
+            source_price_update: source.price_update,
+            destination_price_update: destination.price_update,
+            swap_program: swap.program_id,
+            token_program: source.token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::SwapCollateral {
+            amount,
+            min_out,
+            swap_data: swap.data.clone(),
+            swap_accounts_len: swap_accounts.len() as u8,
+        },
+    );
+    ix.accounts.extend(swap_accounts);
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+/// Must sit at the top level of a transaction that also contains the
+/// matching `flash_repay`.
+pub fn flash_borrow(owner: Pubkey, bank: BankKeys, amount: u64) -> Instruction {
+    build(
+        accounts::FlashBorrow {
+            signer: owner,
+            mint: bank.mint,
+            bank: bank.bank(),
+            bank_token_account: bank.treasury(),
+            user_token_account: bank.user_token_account(&owner),
+            instructions: sysvar::instructions::ID,
+            token_program: bank.token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::FlashBorrow { amount },
+    )
+}
+
+pub fn flash_repay(owner: Pubkey, bank: BankKeys, amount: u64) -> Instruction {
+    build(
+        accounts::FlashRepay {
+            signer: owner,
+            mint: bank.mint,
+            bank: bank.bank(),
+            bank_token_account: bank.treasury(),
+            user_token_account: bank.user_token_account(&owner),
+            token_program: bank.token_program,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::FlashRepay { amount },
+    )
+}
+
+pub fn init_receipt_mint(authority: Pubkey, bank: BankKeys) -> Instruction {
+    build(
+        accounts::InitReceiptMint {
+            signer: authority,
+            mint: bank.mint,
+            bank: bank.bank(),
+            receipt_mint: bank.receipt_mint(),
+            token_program: bank.token_program,
+            system_program: system_program::ID,
+        },
+        instruction::InitReceiptMint {},
+    )
+}
+
+pub fn mint_receipt(owner: Pubkey, bank: BankKeys, amount: u64) -> Instruction {
+    build(
+        accounts::MintReceipt {
+            signer: owner,
+            mint: bank.mint,
+            bank: bank.bank(),
+            bank_token_account: bank.treasury(),
+            receipt_mint: bank.receipt_mint(),
+            user_token_account: bank.user_token_account(&owner),
+            user_receipt_account: bank.user_receipt_account(&owner),
+            token_program: bank.token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::MintReceipt { amount },
+    )
+}
+
+pub fn redeem(owner: Pubkey, bank: BankKeys, shares: u64) -> Instruction {
+    build(
+        accounts::Redeem {
+            signer: owner,
+            mint: bank.mint,
+            bank: bank.bank(),
+            bank_token_account: bank.treasury(),
+            receipt_mint: bank.receipt_mint(),
+            user_receipt_account: bank.user_receipt_account(&owner),
+            user_token_account: bank.user_token_account(&owner),
+            token_program: bank.token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::Redeem { shares },
+    )
+}
+
+pub fn deposit_receipt_as_collateral(owner: Pubkey, bank: BankKeys, shares: u64) -> Instruction {
+    build(
+        accounts::DepositReceiptAsCollateral {
+            signer: owner,
+            mint: bank.mint,
+            bank: bank.bank(),
+            receipt_mint: bank.receipt_mint(),
+            user_receipt_account: bank.user_receipt_account(&owner),
+            user_account: find_user_address(&owner).0,
+            token_program: bank.token_program,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::DepositReceiptAsCollateral { shares },
+    )
+}
+
+/// `health_accounts` lists every bank the user has a position in, `bank`
+/// included. They are only needed while the user has debt.
+pub fn withdraw_collateral_as_receipt(
+    owner: Pubkey,
+    bank: BankKeys,
+    shares: u64,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let mut ix = build(
+        accounts::WithdrawCollateralAsReceipt {
+            signer: owner,
+            market: find_market_address().0,
+            mint: bank.mint,
+            bank: bank.bank(),
+            receipt_mint: bank.receipt_mint(),
+            user_receipt_account: bank.user_receipt_account(&owner),
+            user_account: find_user_address(&owner).0,
+            token_program: bank.token_program,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::WithdrawCollateralAsReceipt { shares },
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+pub fn refresh_bank(bank: Pubkey) -> Instruction {
+    build(
+        accounts::RefreshBank {
+            bank,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        },
+        instruction::RefreshBank {},
+    )
+}
+
+pub fn get_user_health(owner: Pubkey, health_accounts: &[HealthAccount]) -> Instruction {
+    let mut ix = build(
+        accounts::GetUserHealth {
+            market: find_market_address().0,
+            user_account: find_user_address(&owner).0,
+        },
+        instruction::GetUserHealth {},
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+fn user_bank_limit(
+    owner: Pubkey,
+    mint: Pubkey,
+    price_update: Pubkey,
+) -> accounts::GetUserBankLimit {
+    accounts::GetUserBankLimit {
+        market: find_market_address().0,
+        user_account: find_user_address(&owner).0,
+        bank: find_bank_address(&mint).0,
+        price_update,
+    }
+}
+
+pub fn get_max_borrowable(
+    owner: Pubkey,
+    mint: Pubkey,
+    price_update: Pubkey,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let mut ix = build(
+        user_bank_limit(owner, mint, price_update),
+        instruction::GetMaxBorrowable {},
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+pub fn get_max_withdrawable(
+    owner: Pubkey,
+    mint: Pubkey,
+    price_update: Pubkey,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let mut ix = build(
+        user_bank_limit(owner, mint, price_update),
+        instruction::GetMaxWithdrawable {},
+    );
+    ix.accounts.extend(health_metas(health_accounts));
+    ix
+}
+
+pub fn get_bank_rates(mint: Pubkey) -> Instruction {
+    build(
+        accounts::GetBankRates {
+            bank: find_bank_address(&mint).0,
+        },
+        instruction::GetBankRates {},
+    )
+}
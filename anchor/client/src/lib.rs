@@ -0,0 +1,11 @@
+//! Client-side helpers for the lending program: PDA derivation, instruction
+//! builders, account and event deserializers and off-chain copies of the
+//! risk math.
+
+pub mod accounts;
+pub mod events;
+pub mod instructions;
+pub mod math;
+pub mod pda;
+
+pub use lending_dapp::{self as program, ID as PROGRAM_ID};
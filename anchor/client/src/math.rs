@@ -0,0 +1,40 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Result;
+use lending_dapp::{
+    calculate_portfolio_health, Bank, EmodeCategory, HealthBank, PortfolioHealth, User,
+};
+
+pub use lending_dapp::{
+    calculate_accrued_interest, calculate_flash_loan_fee, calculate_health_factor,
+    calculate_liquidation_amounts, calculate_token_amount, calculate_usd_value, Rounding,
+    ShareChange,
+};
+
+/// Tokens backing `shares` deposit shares, rounded down.
+pub fn deposited_amount(bank: &Bank, shares: u64) -> Result<u64> {
+    bank.deposit_shares_to_amount(shares, Rounding::Down)
+}
+
+/// Debt owed for `shares` borrow shares, rounded up.
+pub fn borrowed_amount(bank: &Bank, shares: u64) -> Result<u64> {
+    bank.borrow_shares_to_amount(shares, Rounding::Up)
+}
+
+/// Values a user's portfolio with the same code the program runs on-chain.
+/// `banks` holds `(bank address, bank, oracle price)` for each position and
+/// `emode` is the category the user opted into, see `Market::user_emode`.
+pub fn portfolio_health(
+    user: &User,
+    banks: &[(Pubkey, Bank, i64)],
+    emode: Option<&EmodeCategory>,
+) -> Result<PortfolioHealth> {
+    let banks: Vec<HealthBank> = banks
+        .iter()
+        .map(|(key, bank, price)| HealthBank {
+            key: *key,
+            bank: bank.clone(),
+            price: *price,
+        })
+        .collect();
+    calculate_portfolio_health(user, &banks, emode)
+}
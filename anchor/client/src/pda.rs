@@ -0,0 +1,57 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use pyth_solana_receiver_sdk::PYTH_PUSH_ORACLE_ID;
+
+use crate::PROGRAM_ID;
+
+pub fn find_market_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"market"], &PROGRAM_ID)
+}
+
+/// ProgramData account holding the lending program's upgrade authority.
+pub fn find_program_data_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PROGRAM_ID.as_ref()], &bpf_loader_upgradeable::ID)
+}
+
+pub fn find_bank_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref()], &PROGRAM_ID)
+}
+
+pub fn find_treasury_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"treasury", mint.as_ref()], &PROGRAM_ID)
+}
+
+pub fn find_receipt_mint_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"receipt", mint.as_ref()], &PROGRAM_ID)
+}
+
+pub fn find_user_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[owner.as_ref()], &PROGRAM_ID)
+}
+
+/// Temporary wSOL account the native SOL instructions open and close again.
+pub fn find_wrapped_sol_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"wrapped_sol", owner.as_ref()], &PROGRAM_ID)
+}
+
+/// Account listing the extra accounts `hook_program` needs on every transfer
+/// of `mint`, at the address the transfer hook interface defines.
+pub fn find_extra_account_metas_address(mint: &Pubkey, hook_program: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"extra-account-metas", mint.as_ref()], hook_program)
+}
+
+/// Signer PDA used by `emit_cpi!`.
+pub fn find_event_authority_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"__event_authority"], &PROGRAM_ID)
+}
+
+pub fn get_user_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, mint, token_program)
+}
+
+/// Price feed account maintained by the Pyth push oracle for `feed_id`.
+/// Sponsored feeds live on shard 0.
+pub fn find_price_feed_address(shard_id: u16, feed_id: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[&shard_id.to_le_bytes(), feed_id], &PYTH_PUSH_ORACLE_ID)
+}
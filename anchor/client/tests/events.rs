@@ -0,0 +1,37 @@
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Event;
+use lending_client::events::decode_event;
+use lending_dapp::events::{InterestAccrued, Repay};
+
+fn interest_accrued() -> InterestAccrued {
+    InterestAccrued {
+        bank: Pubkey::new_unique(),
+        interest: 1_234,
+        total_borrowed: 1_000_000,
+        borrow_share_price: 1_001_234,
+        timestamp: 1_700_000_000,
+    }
+}
+
+/// The data of the inner instruction `emit_cpi!` issues for `event`.
+fn emitted(event: &impl Event) -> Vec<u8> {
+    [EVENT_IX_TAG_LE, &event.data()].concat()
+}
+
+#[test]
+fn decodes_the_emitted_event() {
+    let event = interest_accrued();
+    let decoded: InterestAccrued = decode_event(&emitted(&event)).unwrap();
+    assert_eq!(decoded.bank, event.bank);
+    assert_eq!(decoded.interest, 1_234);
+    assert_eq!(decoded.timestamp, 1_700_000_000);
+}
+
+#[test]
+fn skips_other_events_and_plain_instructions() {
+    let data = emitted(&interest_accrued());
+    assert!(decode_event::<Repay>(&data).is_none());
+    // Without the event tag the data is an ordinary instruction.
+    assert!(decode_event::<InterestAccrued>(&data[EVENT_IX_TAG_LE.len()..]).is_none());
+}
@@ -0,0 +1,107 @@
+use anchor_lang::prelude::Pubkey;
+use lending_client::math::{
+    borrowed_amount, calculate_liquidation_amounts, deposited_amount, portfolio_health,
+};
+use lending_dapp::{
+    Balance, Bank, User, DEFAULT_CLOSE_FACTOR_BPS, DEFAULT_LIQUIDATION_BONUS_BPS,
+    MAX_TOKEN_BALANCES, VIRTUAL_DEPOSIT_OFFSET,
+};
+
+const USDC: u64 = 1_000_000;
+const SOL: u64 = 1_000_000_000;
+const USDC_PRICE: i64 = 100_000_000;
+
+fn bank(mint_decimals: u8) -> Bank {
+    let mut bank = Bank {
+        mint_decimals,
+        liquidation_threshold: 0.8,
+        max_ltv: 0.75,
+        liability_weight_init: 1.0,
+        liability_weight_maint: 1.0,
+        close_factor: DEFAULT_CLOSE_FACTOR_BPS,
+        liquidation_bonus: DEFAULT_LIQUIDATION_BONUS_BPS,
+        ..Default::default()
+    };
+    bank.config.can_be_collateral = true;
+    bank
+}
+
+#[test]
+fn deposits_round_down_and_debts_round_up() {
+    // Deposits have grown by a third, virtual offset included.
+    let mut usdc = bank(6);
+    usdc.total_deposits = 4 * USDC - VIRTUAL_DEPOSIT_OFFSET;
+    usdc.total_deposits_shares = 3 * USDC - VIRTUAL_DEPOSIT_OFFSET;
+    usdc.total_borrowed = 4 * USDC;
+    usdc.total_borrowed_shares = 3 * USDC;
+
+    assert_eq!(deposited_amount(&usdc, 3 * USDC).unwrap(), 4 * USDC);
+    assert_eq!(deposited_amount(&usdc, 1).unwrap(), 1);
+    assert_eq!(borrowed_amount(&usdc, 3 * USDC).unwrap(), 4 * USDC);
+    assert_eq!(borrowed_amount(&usdc, 1).unwrap(), 2);
+}
+
+#[test]
+fn portfolio_health_weighs_collateral_against_debt() {
+    let (sol_bank, usdc_bank) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sol = bank(9);
+    sol.total_deposits = SOL;
+    sol.total_deposits_shares = SOL;
+    let mut usdc = bank(6);
+    usdc.total_borrowed = 70 * USDC;
+    usdc.total_borrowed_shares = 70 * USDC;
+    let mut balances = [Balance::default(); MAX_TOKEN_BALANCES];
+    balances[0].bank_address = sol_bank;
+    balances[0].deposited_shares = SOL;
+    balances[1].bank_address = usdc_bank;
+    balances[1].borrowed_shares = 70 * USDC;
+    let user = User {
+        owner: Pubkey::new_unique(),
+        balances,
+        isolated_bank: Pubkey::default(),
+        emode_category: 0,
+    };
+
+    let banks = [
+        (sol_bank, sol.clone(), 100 * USDC_PRICE),
+        (usdc_bank, usdc.clone(), USDC_PRICE),
+    ];
+    let health = portfolio_health(&user, &banks, None).unwrap();
+    assert_eq!(health.collateral_value, 100 * USDC_PRICE as u128);
+    assert_eq!(health.weighted_collateral_value, 75 * USDC_PRICE as u128);
+    assert_eq!(health.debt_value, 70 * USDC_PRICE as u128);
+    assert!(health.is_healthy() && !health.is_liquidatable());
+
+    // At $80 the $64 the liquidation threshold leaves is below the debt.
+    let banks = [
+        (sol_bank, sol, 80 * USDC_PRICE),
+        (usdc_bank, usdc, USDC_PRICE),
+    ];
+    let health = portfolio_health(&user, &banks, None).unwrap();
+    assert!(health.is_liquidatable());
+}
+
+/// The sizes match what the `liquidate` program tests observe on-chain for
+/// the same position.
+#[test]
+fn liquidation_repays_the_close_factor_plus_bonus() {
+    let (sol, usdc) = (bank(9), bank(6));
+
+    // Half the 70 USDC debt, paid with 35 * 1.05 / 80 SOL.
+    let amounts =
+        calculate_liquidation_amounts(&sol, 80 * USDC_PRICE, SOL, &usdc, USDC_PRICE, 70 * USDC)
+            .unwrap();
+    assert_eq!(amounts, (35 * USDC, 459_375_000));
+}
+
+#[test]
+fn liquidation_shrinks_when_the_collateral_falls_short() {
+    let (sol, usdc) = (bank(9), bank(6));
+
+    // $36.75 of SOL is owed for half the debt but only $30 is left, so the
+    // repayment shrinks to $30 / 1.05 and all the collateral is seized.
+    let amounts =
+        calculate_liquidation_amounts(&sol, 30 * USDC_PRICE, SOL, &usdc, USDC_PRICE, 70 * USDC)
+            .unwrap();
+    assert_eq!(amounts, (28_571_428, SOL));
+}
@@ -0,0 +1,188 @@
+mod keypair;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anchor_client::solana_client::rpc_config::RpcTransactionConfig;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::signature::{Keypair, Signature};
+use anchor_client::{Client, Cluster, Program};
+use anchor_lang::prelude::Pubkey;
+use anyhow::{Context, Result};
+use clap::Parser;
+use lending_client::events::decode_event;
+use lending_client::instructions::refresh_bank;
+use lending_client::PROGRAM_ID;
+use lending_dapp::events::InterestAccrued;
+use lending_dapp::{Bank, SHARE_PRICE_SCALE};
+use solana_transaction_status_client_types::{
+    UiInnerInstructions, UiInstruction, UiTransactionEncoding,
+};
+
+use crate::keypair::load_keypair;
+
+#[derive(Parser)]
+#[command(
+    name = "lending-crank",
+    about = "Keeps interest accrued on every lending bank"
+)]
+struct Args {
+    /// RPC URL or cluster moniker (localnet, devnet, mainnet).
+    #[arg(
+        long,
+        short,
+        env = "LENDING_RPC_URL",
+        default_value = "http://127.0.0.1:8899"
+    )]
+    url: Cluster,
+    /// Wallet paying the transaction fees.
+    #[arg(
+        long,
+        short,
+        env = "LENDING_KEYPAIR",
+        default_value = "~/.config/solana/id.json"
+    )]
+    keypair: String,
+    /// Seconds between runs.
+    #[arg(long, default_value_t = 300)]
+    interval: u64,
+    /// Only refresh banks that have not accrued for at least this many seconds.
+    #[arg(long, default_value_t = 0)]
+    min_staleness: i64,
+    /// Refresh instructions packed into one transaction.
+    #[arg(long, default_value_t = 8)]
+    batch_size: usize,
+    /// Run once and exit.
+    #[arg(long)]
+    once: bool,
+}
+
+/// Interest accrued per bank since the crank started, in native units.
+#[derive(Default)]
+struct Totals {
+    runs: u64,
+    interest: HashMap<Pubkey, u128>,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let payer = Arc::new(load_keypair(&args.keypair)?);
+    let client = Client::new_with_options(
+        args.url.clone(),
+        payer.clone(),
+        CommitmentConfig::confirmed(),
+    );
+    let program = client.program(PROGRAM_ID)?;
+    let mut totals = Totals::default();
+
+    loop {
+        if let Err(err) = run(&program, &args, &mut totals) {
+            eprintln!("run failed: {err:#}");
+        }
+        if args.once {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(args.interval));
+    }
+}
+
+fn run(program: &Program<Arc<Keypair>>, args: &Args, totals: &mut Totals) -> Result<()> {
+    let started = Instant::now();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let stale: Vec<(Pubkey, Bank)> = program
+        .accounts::<Bank>(vec![])?
+        .into_iter()
+        .filter(|(_, bank)| {
+            bank.total_borrowed > 0 && now - bank.last_updated_borrow >= args.min_staleness
+        })
+        .collect();
+
+    let mut refreshed = 0;
+    let mut failed = 0;
+    let mut accrued = Vec::new();
+    for batch in stale.chunks(args.batch_size.max(1)) {
+        let mut request = program.request();
+        for (address, _) in batch {
+            request = request.instruction(refresh_bank(*address));
+        }
+        match request.send() {
+            Ok(signature) => {
+                refreshed += batch.len();
+                match interest_events(program, &signature) {
+                    Ok(events) => accrued.extend(events),
+                    Err(err) => eprintln!("reading the events of {signature}: {err:#}"),
+                }
+            }
+            Err(err) => {
+                eprintln!("refreshing {} banks: {err}", batch.len());
+                failed += batch.len();
+            }
+        }
+    }
+
+    for event in &accrued {
+        let Some((address, before)) = stale.iter().find(|(address, _)| *address == event.bank)
+        else {
+            continue;
+        };
+        *totals.interest.entry(*address).or_default() += event.interest as u128;
+        println!(
+            "{:<5} {address} interest={} total_borrowed={} borrow_share_price={:.9} idle={}s lifetime_interest={}",
+            before.config.ticker_symbol,
+            event.interest,
+            event.total_borrowed,
+            event.borrow_share_price as f64 / SHARE_PRICE_SCALE as f64,
+            event.timestamp - before.last_updated_borrow,
+            totals.interest[address],
+        );
+    }
+    totals.runs += 1;
+    println!(
+        "run {}: {} stale, {refreshed} refreshed, {failed} failed in {:.1}s",
+        totals.runs,
+        stale.len(),
+        started.elapsed().as_secs_f64(),
+    );
+    Ok(())
+}
+
+/// The `InterestAccrued` events `refresh_bank` emitted in the transaction
+/// `signature`. Banks with nothing to accrue emit none.
+fn interest_events(
+    program: &Program<Arc<Keypair>>,
+    signature: &Signature,
+) -> Result<Vec<InterestAccrued>> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+    let confirmed = program
+        .rpc()
+        .get_transaction_with_config(signature, config)?
+        .transaction;
+    let transaction = confirmed
+        .transaction
+        .decode()
+        .context("transaction does not decode")?;
+    let account_keys = transaction.message.static_account_keys();
+    let inner_instructions: Vec<UiInnerInstructions> = confirmed
+        .meta
+        .and_then(|meta| meta.inner_instructions.into())
+        .unwrap_or_default();
+    Ok(inner_instructions
+        .into_iter()
+        .flat_map(|inner| inner.instructions)
+        .filter_map(|instruction| match instruction {
+            UiInstruction::Compiled(instruction)
+                if account_keys.get(instruction.program_id_index as usize) == Some(&PROGRAM_ID) =>
+            {
+                bs58::decode(instruction.data).into_vec().ok()
+            }
+            _ => None,
+        })
+        .filter_map(|data| decode_event::<InterestAccrued>(&data))
+        .collect())
+}
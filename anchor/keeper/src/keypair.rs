@@ -0,0 +1,14 @@
+use std::path::PathBuf;
+
+use anchor_client::solana_sdk::signature::{read_keypair_file, Keypair};
+use anyhow::{anyhow, Context, Result};
+
+/// Reads the keypair file at `path`, expanding a leading `~/` to the home
+/// directory the way the Solana CLI does.
+pub fn load_keypair(path: &str) -> Result<Keypair> {
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(std::env::var("HOME").context("HOME is not set")?).join(rest),
+        None => PathBuf::from(path),
+    };
+    read_keypair_file(&path).map_err(|err| anyhow!("reading keypair {}: {err}", path.display()))
+}
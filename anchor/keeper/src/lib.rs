@@ -0,0 +1,7 @@
+//! Liquidation keeper for the lending program: scans borrowers, picks the
+//! positions to liquidate and builds the transactions that do it.
+
+pub mod liquidation;
+pub mod opportunity;
+pub mod scanner;
+pub mod swap;
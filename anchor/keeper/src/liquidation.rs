@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use lending_client::instructions as ix;
+use lending_client::math::{calculate_flash_loan_fee, calculate_token_amount, calculate_usd_value};
+
+use crate::opportunity::Opportunity;
+use crate::scanner::PricedBankState;
+use crate::swap::SwapRoute;
+
+/// The instructions of one liquidation transaction and what it earns.
+pub struct Liquidation {
+    pub instructions: Vec<Instruction>,
+    /// Profit left to the liquidator, in oracle units.
+    pub profit_value: u128,
+}
+
+/// Builds the transaction liquidating `opportunity`. Without a `route` the
+/// liquidator repays from its own balance. With one, the repayment is flash
+/// borrowed and the seized collateral sold through `route` to close the
+/// loan; `None` is returned when the sale would not cover it.
+pub fn build_liquidation(
+    liquidator: Pubkey,
+    banks: &HashMap<Pubkey, PricedBankState>,
+    route: Option<&dyn SwapRoute>,
+    opportunity: &Opportunity,
+) -> Result<Option<Liquidation>> {
+    let collateral = &banks[&opportunity.collateral_bank];
+    let borrowed = &banks[&opportunity.borrowed_bank];
+    let liquidate = ix::liquidate(
+        liquidator,
+        opportunity.borrower,
+        collateral.priced(),
+        borrowed.priced(),
+        &opportunity.health_accounts,
+    );
+    let Some(route) = route else {
+        return Ok(Some(Liquidation {
+            instructions: vec![liquidate],
+            profit_value: opportunity.profit_value,
+        }));
+    };
+
+    // The swap must return enough of the borrowed asset to close the flash
+    // loan, fee included; whatever is left over is the profit.
+    let fee = calculate_flash_loan_fee(
+        opportunity.repay_amount,
+        borrowed.bank.config.flash_loan_fee_bps,
+    )?;
+    let owed = opportunity.repay_amount + fee;
+    let seize_value = calculate_usd_value(
+        opportunity.seize_amount,
+        collateral.price,
+        collateral.bank.mint_decimals,
+    )?;
+    let amount_out =
+        calculate_token_amount(seize_value, borrowed.price, borrowed.bank.mint_decimals)?;
+    if amount_out < owed {
+        return Ok(None);
+    }
+    let instructions = vec![
+        ix::flash_borrow(liquidator, borrowed.keys(), opportunity.repay_amount),
+        liquidate,
+        route.swap_instruction(
+            liquidator,
+            &collateral.keys(),
+            &borrowed.keys(),
+            opportunity.seize_amount,
+            amount_out,
+        )?,
+        ix::flash_repay(liquidator, borrowed.keys(), opportunity.repay_amount),
+    ];
+    let profit_value = calculate_usd_value(
+        amount_out - owed,
+        borrowed.price,
+        borrowed.bank.mint_decimals,
+    )?;
+    Ok(Some(Liquidation {
+        instructions,
+        profit_value,
+    }))
+}
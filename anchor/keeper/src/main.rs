@@ -0,0 +1,209 @@
+mod keypair;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_client::solana_sdk::signature::Keypair;
+use anchor_client::{Client, Cluster, Program};
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use lending_client::pda::find_market_address;
+use lending_client::PROGRAM_ID;
+use lending_dapp::Market;
+use lending_keeper::liquidation::{build_liquidation, Liquidation};
+use lending_keeper::opportunity::{find_opportunity, Opportunity};
+use lending_keeper::scanner::{load_banks, load_users, PricedBankState};
+#[cfg(feature = "mock-swap")]
+use lending_keeper::swap::MockSwapRoute;
+use lending_keeper::swap::{SwapRoute, SWAP_ROUTES};
+
+use crate::keypair::load_keypair;
+
+#[derive(Parser)]
+#[command(
+    name = "lending-keeper",
+    about = "Liquidates unhealthy lending positions"
+)]
+struct Args {
+    /// RPC URL or cluster moniker (localnet, devnet, mainnet).
+    #[arg(
+        long,
+        short,
+        env = "LENDING_RPC_URL",
+        default_value = "http://127.0.0.1:8899"
+    )]
+    url: Cluster,
+    /// Liquidator wallet.
+    #[arg(
+        long,
+        short,
+        env = "LENDING_KEYPAIR",
+        default_value = "~/.config/solana/id.json"
+    )]
+    keypair: String,
+    /// Seconds between scans.
+    #[arg(long, default_value_t = 10)]
+    interval: u64,
+    /// Run a single scan and exit.
+    #[arg(long)]
+    once: bool,
+    /// Log opportunities without sending transactions.
+    #[arg(long)]
+    dry_run: bool,
+    /// Skip liquidations earning less than this, in USD.
+    #[arg(long, default_value_t = 0.0)]
+    min_profit_usd: f64,
+    /// Only scan users with a position in the bank for this mint.
+    #[arg(long)]
+    mint: Option<Pubkey>,
+    /// Pyth push oracle shard the bank price feeds are read from.
+    #[arg(long, default_value_t = 0)]
+    price_feed_shard: u16,
+    /// Use this price update account for a mint, as `MINT=ACCOUNT`.
+    #[arg(long = "price-update", value_parser = parse_pair)]
+    price_updates: Vec<(Pubkey, Pubkey)>,
+    /// Fund repayments with a flash loan and sell the seized collateral
+    /// through `--swap-route` instead of spending wallet balances.
+    #[arg(long, requires = "swap_route")]
+    flash_loan: bool,
+    /// Swap route selling seized collateral in flash loan liquidations:
+    /// `mock`, when built with the `mock-swap` feature.
+    #[arg(long)]
+    swap_route: Option<String>,
+    /// Mock swap pool vault for a mint, as `MINT=VAULT`.
+    #[cfg(feature = "mock-swap")]
+    #[arg(long = "mock-pool", value_parser = parse_pair)]
+    mock_pools: Vec<(Pubkey, Pubkey)>,
+}
+
+fn parse_pair(value: &str) -> Result<(Pubkey, Pubkey)> {
+    let (mint, account) = value
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected MINT=ACCOUNT"))?;
+    Ok((mint.parse()?, account.parse()?))
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let payer = Arc::new(load_keypair(&args.keypair)?);
+    let client = Client::new_with_options(
+        args.url.clone(),
+        payer.clone(),
+        CommitmentConfig::confirmed(),
+    );
+    let program = client.program(PROGRAM_ID)?;
+    let price_overrides: HashMap<Pubkey, Pubkey> = args.price_updates.iter().copied().collect();
+    let route = swap_route(&args)?;
+
+    loop {
+        if let Err(err) = scan(&program, &args, &price_overrides, route.as_deref()) {
+            eprintln!("scan failed: {err:#}");
+        }
+        if args.once {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(args.interval));
+    }
+}
+
+/// Builds the route flash loan liquidations sell through, named by
+/// `--swap-route` out of the ones this build includes.
+fn swap_route(args: &Args) -> Result<Option<Box<dyn SwapRoute>>> {
+    let Some(name) = args.swap_route.as_ref().filter(|_| args.flash_loan) else {
+        return Ok(None);
+    };
+    match name.as_str() {
+        #[cfg(feature = "mock-swap")]
+        "mock" => Ok(Some(Box::new(MockSwapRoute {
+            pools: args.mock_pools.iter().copied().collect(),
+        }) as Box<dyn SwapRoute>)),
+        _ => Err(anyhow!(
+            "unknown swap route {name}, this build supports: [{}]",
+            SWAP_ROUTES.join(", ")
+        )),
+    }
+}
+
+fn scan(
+    program: &Program<Arc<Keypair>>,
+    args: &Args,
+    price_overrides: &HashMap<Pubkey, Pubkey>,
+    route: Option<&dyn SwapRoute>,
+) -> Result<()> {
+    let banks = load_banks(program, args.price_feed_shard, price_overrides)?;
+    let market: Market = program.account(find_market_address().0)?;
+    let bank_filter = args
+        .mint
+        .map(|mint| lending_client::pda::find_bank_address(&mint).0);
+    let users = load_users(program, bank_filter)?;
+    for (address, user) in users {
+        let opportunity = match find_opportunity(&user, &banks, &market) {
+            Ok(Some(opportunity)) => opportunity,
+            Ok(None) => continue,
+            Err(err) => {
+                eprintln!("user {address}: {err}");
+                continue;
+            }
+        };
+        if let Err(err) = execute(program, args, &banks, route, &opportunity) {
+            eprintln!("liquidating {}: {err:#}", opportunity.borrower);
+        }
+    }
+    Ok(())
+}
+
+fn execute(
+    program: &Program<Arc<Keypair>>,
+    args: &Args,
+    banks: &HashMap<Pubkey, PricedBankState>,
+    route: Option<&dyn SwapRoute>,
+    opportunity: &Opportunity,
+) -> Result<()> {
+    let collateral = &banks[&opportunity.collateral_bank];
+    let borrowed = &banks[&opportunity.borrowed_bank];
+    let Some(Liquidation {
+        instructions,
+        profit_value,
+    }) = build_liquidation(program.payer(), banks, route, opportunity)?
+    else {
+        println!(
+            "borrower {}: seized collateral does not cover the flash loan, skipping",
+            opportunity.borrower
+        );
+        return Ok(());
+    };
+
+    let profit_usd = profit_value as f64 * 10f64.powi(borrowed.exponent);
+    println!(
+        "borrower {} health {:.4}: repay {} {} for {} {}, profit ${profit_usd:.2}",
+        opportunity.borrower,
+        opportunity.health_factor,
+        opportunity.repay_amount,
+        borrowed.bank.config.ticker_symbol,
+        opportunity.seize_amount,
+        collateral.bank.config.ticker_symbol,
+    );
+    if profit_usd < args.min_profit_usd {
+        println!("  below minimum profit, skipping");
+        return Ok(());
+    }
+    if args.dry_run {
+        return Ok(());
+    }
+    let signature = send(program, instructions)?;
+    println!("  liquidated: {signature}");
+    Ok(())
+}
+
+fn send(program: &Program<Arc<Keypair>>, instructions: Vec<Instruction>) -> Result<String> {
+    let mut request = program.request();
+    for instruction in instructions {
+        request = request.instruction(instruction);
+    }
+    Ok(request.send()?.to_string())
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use lending_client::instructions::{BankKeys, HealthAccount, PricedBank};
+use lending_client::math::{
+    borrowed_amount, calculate_liquidation_amounts, calculate_usd_value, deposited_amount,
+    portfolio_health,
+};
+use lending_dapp::{Market, User};
+
+use crate::scanner::PricedBankState;
+
+/// A liquidation the keeper can submit against one borrower.
+pub struct Opportunity {
+    pub borrower: Pubkey,
+    pub health_factor: f64,
+    pub collateral_bank: Pubkey,
+    pub borrowed_bank: Pubkey,
+    pub repay_amount: u64,
+    pub seize_amount: u64,
+    /// Value of the seized collateral minus the repaid debt, in oracle units.
+    pub profit_value: u128,
+    /// `[bank, price_update]` pairs for the borrower's other positions.
+    pub health_accounts: Vec<HealthAccount>,
+}
+
+impl PricedBankState {
+    pub fn keys(&self) -> BankKeys {
+        BankKeys::new(self.bank.token_mint_address, self.token_program)
+    }
+
+    pub fn priced(&self) -> PricedBank {
+        PricedBank {
+            keys: self.keys(),
+            price_update: self.price_update,
+        }
+    }
+}
+
+/// Values the user's portfolio and, when it is liquidatable, picks its
+/// largest debt and largest collateral position to liquidate against.
+/// Users with a position in a bank that could not be priced are skipped.
+pub fn find_opportunity(
+    user: &User,
+    banks: &HashMap<Pubkey, PricedBankState>,
+    market: &Market,
+) -> Result<Option<Opportunity>> {
+    let mut entries = Vec::new();
+    for balance in &user.balances {
+        if balance.deposited_shares == 0 && balance.borrowed_shares == 0 {
+            continue;
+        }
+        let Some(state) = banks.get(&balance.bank_address) else {
+            return Ok(None);
+        };
+        entries.push((balance.bank_address, state.bank.clone(), state.price));
+    }
+    let health = portfolio_health(user, &entries, market.user_emode(user))?;
+    if !health.is_liquidatable() {
+        return Ok(None);
+    }
+
+    let mut debt: Option<(Pubkey, u64, u128)> = None;
+    let mut collateral: Option<(Pubkey, u64, u128)> = None;
+    for balance in &user.balances {
+        let Some(state) = banks.get(&balance.bank_address) else {
+            continue;
+        };
+        let bank = &state.bank;
+        if balance.borrowed_shares > 0 {
+            let amount = borrowed_amount(bank, balance.borrowed_shares)?;
+            let value = calculate_usd_value(amount, state.price, bank.mint_decimals)?;
+            if debt.is_none_or(|(_, _, best)| value > best) {
+                debt = Some((balance.bank_address, amount, value));
+            }
+        }
+        // Deposits the owner excluded from collateral cannot be seized.
+        if balance.deposited_shares > 0 && !balance.collateral_disabled {
+            let amount = deposited_amount(bank, balance.deposited_shares)?;
+            let value = calculate_usd_value(amount, state.price, bank.mint_decimals)?;
+            if collateral.is_none_or(|(_, _, best)| value > best) {
+                collateral = Some((balance.bank_address, amount, value));
+            }
+        }
+    }
+    let (Some((borrowed_bank, debt, _)), Some((collateral_bank, collateral, _))) =
+        (debt, collateral)
+    else {
+        return Ok(None);
+    };
+    if borrowed_bank == collateral_bank {
+        return Ok(None);
+    }
+
+    let collateral_state = &banks[&collateral_bank];
+    let borrowed_state = &banks[&borrowed_bank];
+    let (repay_amount, seize_amount) = calculate_liquidation_amounts(
+        &collateral_state.bank,
+        collateral_state.price,
+        collateral,
+        &borrowed_state.bank,
+        borrowed_state.price,
+        debt,
+    )?;
+    if repay_amount == 0 || seize_amount == 0 {
+        return Ok(None);
+    }
+    let repay_value = calculate_usd_value(
+        repay_amount,
+        borrowed_state.price,
+        borrowed_state.bank.mint_decimals,
+    )?;
+    let seize_value = calculate_usd_value(
+        seize_amount,
+        collateral_state.price,
+        collateral_state.bank.mint_decimals,
+    )?;
+    let health_accounts = entries
+        .iter()
+        .filter(|(key, _, _)| *key != collateral_bank && *key != borrowed_bank)
+        .map(|(key, _, _)| HealthAccount {
+            bank: *key,
+            price_update: banks[key].price_update,
+        })
+        .collect();
+
+    Ok(Some(Opportunity {
+        borrower: user.owner,
+        health_factor: health.health_factor(),
+        collateral_bank,
+        borrowed_bank,
+        repay_amount,
+        seize_amount,
+        profit_value: seize_value.saturating_sub(repay_value),
+        health_accounts,
+    }))
+}
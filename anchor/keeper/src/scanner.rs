@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anchor_client::solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use anchor_client::solana_sdk::signature::Keypair;
+use anchor_client::Program;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator, Space};
+use anyhow::Result;
+use lending_client::pda::find_price_feed_address;
+use lending_dapp::{Balance, Bank, User, MAX_TOKEN_BALANCES};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+/// A bank and the oracle price it is valued at for this scan.
+pub struct PricedBankState {
+    pub bank: Bank,
+    pub token_program: Pubkey,
+    pub price_update: Pubkey,
+    pub price: i64,
+    pub exponent: i32,
+}
+
+/// Loads every bank of the program together with its latest price. Banks
+/// whose price account cannot be read are skipped with a warning.
+///
+/// `price_overrides` maps a mint to the price update account to use instead
+/// of the Pyth push feed, e.g. a mocked account on a local validator.
+pub fn load_banks(
+    program: &Program<Arc<Keypair>>,
+    shard_id: u16,
+    price_overrides: &HashMap<Pubkey, Pubkey>,
+) -> Result<HashMap<Pubkey, PricedBankState>> {
+    let mut banks = HashMap::new();
+    for (address, bank) in program.accounts::<Bank>(vec![])? {
+        let price_update = match price_overrides.get(&bank.token_mint_address) {
+            Some(price_update) => *price_update,
+            None => find_price_feed_address(shard_id, &bank.config.oracle_feed_id).0,
+        };
+        let price = match program.rpc().get_account(&price_update) {
+            Ok(account) => read_price(&account.data),
+            Err(err) => Err(err.into()),
+        };
+        let (price, exponent) = match price {
+            Ok(price) => price,
+            Err(err) => {
+                eprintln!(
+                    "skipping bank {address} ({}): price {price_update}: {err}",
+                    bank.config.ticker_symbol
+                );
+                continue;
+            }
+        };
+        let token_program = program.rpc().get_account(&bank.token_mint_address)?.owner;
+        banks.insert(
+            address,
+            PricedBankState {
+                bank,
+                token_program,
+                price_update,
+                price,
+                exponent,
+            },
+        );
+    }
+    Ok(banks)
+}
+
+/// Reads the latest price and its exponent out of the data of a Pyth price
+/// update account.
+pub fn read_price(mut data: &[u8]) -> Result<(i64, i32)> {
+    let update = PriceUpdateV2::try_deserialize(&mut data)?;
+    Ok((update.price_message.price, update.price_message.exponent))
+}
+
+/// Fetches user accounts. With `bank` set, only users holding a balance in
+/// that bank are returned, matched with one memcmp filter per balance slot.
+pub fn load_users(
+    program: &Program<Arc<Keypair>>,
+    bank: Option<Pubkey>,
+) -> Result<Vec<(Pubkey, User)>> {
+    let size = RpcFilterType::DataSize((User::DISCRIMINATOR.len() + User::INIT_SPACE) as u64);
+    let Some(bank) = bank else {
+        return Ok(program.accounts(vec![size])?);
+    };
+    let mut users = HashMap::new();
+    for slot in 0..MAX_TOKEN_BALANCES {
+        let offset = User::DISCRIMINATOR.len() + 32 + slot * Balance::INIT_SPACE;
+        let filter = RpcFilterType::Memcmp(Memcmp::new_base58_encoded(offset, bank.as_ref()));
+        users.extend(program.accounts::<User>(vec![size.clone(), filter])?);
+    }
+    Ok(users.into_iter().collect())
+}
@@ -0,0 +1,82 @@
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use lending_client::instructions::BankKeys;
+
+#[cfg(feature = "mock-swap")]
+pub use mock::MockSwapRoute;
+
+/// Names `--swap-route` accepts in this build.
+pub const SWAP_ROUTES: &[&str] = &[
+    #[cfg(feature = "mock-swap")]
+    "mock",
+];
+
+/// Builds the swap that sells seized collateral for the borrowed asset when
+/// a liquidation is funded with a flash loan.
+pub trait SwapRoute {
+    fn swap_instruction(
+        &self,
+        owner: Pubkey,
+        input: &BankKeys,
+        output: &BankKeys,
+        amount_in: u64,
+        amount_out: u64,
+    ) -> Result<Instruction>;
+}
+
+#[cfg(feature = "mock-swap")]
+mod mock {
+    use std::collections::HashMap;
+
+    use anchor_lang::{InstructionData, ToAccountMetas};
+    use anyhow::anyhow;
+
+    use super::*;
+
+    /// Routes through the mock swap program, whose pool pays out exactly the
+    /// requested amount. Used against a local validator.
+    pub struct MockSwapRoute {
+        /// Pool vault for each mint.
+        pub pools: HashMap<Pubkey, Pubkey>,
+    }
+
+    impl SwapRoute for MockSwapRoute {
+        fn swap_instruction(
+            &self,
+            owner: Pubkey,
+            input: &BankKeys,
+            output: &BankKeys,
+            amount_in: u64,
+            amount_out: u64,
+        ) -> Result<Instruction> {
+            let pool = |mint: &Pubkey| {
+                self.pools
+                    .get(mint)
+                    .copied()
+                    .ok_or_else(|| anyhow!("no mock pool configured for mint {mint}"))
+            };
+            let accounts = mock_swap::accounts::Swap {
+                user: owner,
+                pool_authority: Pubkey::find_program_address(&[b"pool"], &mock_swap::ID).0,
+                source_mint: input.mint,
+                destination_mint: output.mint,
+                user_source: input.user_token_account(&owner),
+                user_destination: output.user_token_account(&owner),
+                pool_source: pool(&input.mint)?,
+                pool_destination: pool(&output.mint)?,
+                source_token_program: input.token_program,
+                destination_token_program: output.token_program,
+            };
+            Ok(Instruction {
+                program_id: mock_swap::ID,
+                accounts: accounts.to_account_metas(None),
+                data: mock_swap::instruction::Swap {
+                    amount_in,
+                    amount_out,
+                }
+                .data(),
+            })
+        }
+    }
+}
@@ -0,0 +1,148 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountSerialize;
+use anchor_spl::token;
+use lending_client::pda::find_bank_address;
+use lending_dapp::{
+    Balance, Bank, Market, User, DEFAULT_CLOSE_FACTOR_BPS, DEFAULT_FLASH_LOAN_FEE_BPS,
+    DEFAULT_LIQUIDATION_BONUS_BPS, MAX_TOKEN_BALANCES,
+};
+use lending_keeper::scanner::{read_price, PricedBankState};
+use pyth_solana_receiver_sdk::price_update::{PriceFeedMessage, PriceUpdateV2, VerificationLevel};
+
+pub const USDC: u64 = 1_000_000;
+pub const SOL: u64 = 1_000_000_000;
+pub const PRICE_EXPONENT: i32 = -8;
+pub const SOL_PRICE: i64 = 100_00000000;
+pub const USDC_PRICE: i64 = 1_00000000;
+
+/// The data of a Pyth price update account quoting `price`.
+pub fn price_account(price: i64) -> Vec<u8> {
+    let update = PriceUpdateV2 {
+        write_authority: Pubkey::new_unique(),
+        verification_level: VerificationLevel::Full,
+        price_message: PriceFeedMessage {
+            feed_id: [0; 32],
+            price,
+            conf: 0,
+            exponent: PRICE_EXPONENT,
+            publish_time: 0,
+            prev_publish_time: 0,
+            ema_price: price,
+            ema_conf: 0,
+        },
+        posted_slot: 0,
+    };
+    let mut data = Vec::new();
+    update.try_serialize(&mut data).unwrap();
+    data
+}
+
+/// A freshly initialized bank for a new mint.
+pub fn bank(ticker: &str, mint_decimals: u8) -> Bank {
+    let mut bank = Bank {
+        token_mint_address: Pubkey::new_unique(),
+        mint_decimals,
+        liquidation_threshold: 0.8,
+        max_ltv: 0.75,
+        liability_weight_init: 1.0,
+        liability_weight_maint: 1.0,
+        close_factor: DEFAULT_CLOSE_FACTOR_BPS,
+        liquidation_bonus: DEFAULT_LIQUIDATION_BONUS_BPS,
+        ..Default::default()
+    };
+    bank.config.ticker_symbol = ticker.to_string();
+    bank.config.flash_loan_fee_bps = DEFAULT_FLASH_LOAN_FEE_BPS;
+    bank.config.can_be_collateral = true;
+    bank.config.borrowing_enabled = true;
+    bank
+}
+
+/// SOL, USDC and USDT banks priced from mocked price update accounts, keyed
+/// by bank address.
+pub struct Banks {
+    pub sol: Pubkey,
+    pub usdc: Pubkey,
+    pub usdt: Pubkey,
+    pub states: HashMap<Pubkey, PricedBankState>,
+}
+
+impl Banks {
+    pub fn new() -> Self {
+        let mut states = HashMap::new();
+        let mut add = |bank: Bank, price: i64| {
+            let address = find_bank_address(&bank.token_mint_address).0;
+            states.insert(address, priced(bank, price));
+            address
+        };
+        let sol = add(bank("SOL", 9), SOL_PRICE);
+        let usdc = add(bank("USDC", 6), USDC_PRICE);
+        let usdt = add(bank("USDT", 6), USDC_PRICE);
+        Self {
+            sol,
+            usdc,
+            usdt,
+            states,
+        }
+    }
+
+    /// A user holding `deposits` and owing `debts`, as `(bank, amount)`
+    /// pairs, booked into the banks at a share price of one.
+    pub fn user(&mut self, deposits: &[(Pubkey, u64)], debts: &[(Pubkey, u64)]) -> User {
+        let mut balances = [Balance::default(); MAX_TOKEN_BALANCES];
+        let mut slots = balances.iter_mut();
+        for &(address, amount) in deposits {
+            let bank = &mut self.states.get_mut(&address).unwrap().bank;
+            bank.total_deposits += amount;
+            bank.total_deposits_shares += amount;
+            let balance = slots.next().unwrap();
+            balance.bank_address = address;
+            balance.deposited = amount;
+            balance.deposited_shares = amount;
+        }
+        for &(address, amount) in debts {
+            let bank = &mut self.states.get_mut(&address).unwrap().bank;
+            bank.total_borrowed += amount;
+            bank.total_borrowed_shares += amount;
+            let balance = slots.next().unwrap();
+            balance.bank_address = address;
+            balance.borrowed = amount;
+            balance.borrowed_shares = amount;
+        }
+        User {
+            owner: Pubkey::new_unique(),
+            balances,
+            isolated_bank: Pubkey::default(),
+            emode_category: 0,
+        }
+    }
+
+    /// Quotes the bank at `address` from a new mocked price account.
+    pub fn set_price(&mut self, address: Pubkey, price: i64) {
+        let state = self.states.remove(&address).unwrap();
+        self.states.insert(address, priced(state.bank, price));
+    }
+}
+
+fn priced(bank: Bank, price: i64) -> PricedBankState {
+    let (price, exponent) = read_price(&price_account(price)).unwrap();
+    PricedBankState {
+        bank,
+        token_program: token::ID,
+        price_update: Pubkey::new_unique(),
+        price,
+        exponent,
+    }
+}
+
+pub fn market() -> Market {
+    Market {
+        owner: Pubkey::new_unique(),
+        supported_assets: Vec::new(),
+        swap_programs: Vec::new(),
+        emode_categories: Vec::new(),
+    }
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use common::*;
+use lending_client::instructions::{self as ix, BankKeys};
+use lending_keeper::liquidation::build_liquidation;
+use lending_keeper::opportunity::{find_opportunity, Opportunity};
+use lending_keeper::swap::SwapRoute;
+
+/// Records the swap it was asked for in the instruction data.
+struct TestRoute {
+    program_id: Pubkey,
+}
+
+impl SwapRoute for TestRoute {
+    fn swap_instruction(
+        &self,
+        _owner: Pubkey,
+        input: &BankKeys,
+        output: &BankKeys,
+        amount_in: u64,
+        amount_out: u64,
+    ) -> Result<Instruction> {
+        Ok(Instruction {
+            program_id: self.program_id,
+            accounts: Vec::new(),
+            data: [
+                input.mint.as_ref(),
+                output.mint.as_ref(),
+                &amount_in.to_le_bytes(),
+                &amount_out.to_le_bytes(),
+            ]
+            .concat(),
+        })
+    }
+}
+
+/// The liquidation of a borrower holding 1 SOL against 85 USDC of debt once
+/// SOL falls to $90.
+fn setup() -> (Banks, Opportunity) {
+    let mut banks = Banks::new();
+    let user = banks.user(&[(banks.sol, SOL)], &[(banks.usdc, 85 * USDC)]);
+    banks.set_price(banks.sol, 90 * USDC_PRICE);
+    let opportunity = find_opportunity(&user, &banks.states, &market())
+        .unwrap()
+        .unwrap();
+    (banks, opportunity)
+}
+
+#[test]
+fn liquidation_repays_from_the_wallet_without_a_route() {
+    let (banks, opportunity) = setup();
+    let liquidator = Pubkey::new_unique();
+
+    let liquidation = build_liquidation(liquidator, &banks.states, None, &opportunity)
+        .unwrap()
+        .unwrap();
+    let expected = ix::liquidate(
+        liquidator,
+        opportunity.borrower,
+        banks.states[&banks.sol].priced(),
+        banks.states[&banks.usdc].priced(),
+        &[],
+    );
+    assert_eq!(liquidation.instructions, vec![expected]);
+    assert_eq!(liquidation.profit_value, opportunity.profit_value);
+}
+
+#[test]
+fn flash_loan_liquidation_sells_the_seized_collateral() {
+    let (banks, opportunity) = setup();
+    let liquidator = Pubkey::new_unique();
+    let route = TestRoute {
+        program_id: Pubkey::new_unique(),
+    };
+
+    let liquidation = build_liquidation(liquidator, &banks.states, Some(&route), &opportunity)
+        .unwrap()
+        .unwrap();
+    let sol = &banks.states[&banks.sol];
+    let usdc = &banks.states[&banks.usdc];
+    let instructions = &liquidation.instructions;
+    assert_eq!(instructions.len(), 4);
+    assert_eq!(
+        instructions[0],
+        ix::flash_borrow(liquidator, usdc.keys(), opportunity.repay_amount)
+    );
+    assert_eq!(
+        instructions[1],
+        ix::liquidate(
+            liquidator,
+            opportunity.borrower,
+            sol.priced(),
+            usdc.priced(),
+            &[],
+        )
+    );
+    // The seized SOL sells for $44.62 of USDC.
+    let swap = route
+        .swap_instruction(
+            liquidator,
+            &sol.keys(),
+            &usdc.keys(),
+            opportunity.seize_amount,
+            44_624_999,
+        )
+        .unwrap();
+    assert_eq!(instructions[2], swap);
+    assert_eq!(
+        instructions[3],
+        ix::flash_repay(liquidator, usdc.keys(), opportunity.repay_amount)
+    );
+    // What is left after repaying 42.5 USDC and the 0.09% fee.
+    let fee = 38_250;
+    assert_eq!(
+        liquidation.profit_value,
+        ((44_624_999 - 42_500_000 - fee) * 100) as u128
+    );
+}
+
+#[test]
+fn flash_loan_liquidation_is_skipped_when_the_fee_eats_the_bonus() {
+    let (mut banks, opportunity) = setup();
+    let usdc = banks.states.get_mut(&banks.usdc).unwrap();
+    usdc.bank.config.flash_loan_fee_bps = 600;
+    let route = TestRoute {
+        program_id: Pubkey::new_unique(),
+    };
+
+    let liquidation = build_liquidation(
+        Pubkey::new_unique(),
+        &banks.states,
+        Some(&route),
+        &opportunity,
+    )
+    .unwrap();
+    assert!(liquidation.is_none());
+}
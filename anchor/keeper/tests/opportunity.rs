@@ -0,0 +1,85 @@
+mod common;
+
+use common::*;
+use lending_keeper::opportunity::find_opportunity;
+use lending_keeper::scanner::read_price;
+
+/// A borrower holding 1 SOL and 10 USDT against 85 USDC of debt: healthy at
+/// $100 SOL, liquidatable once SOL falls to $90.
+fn setup() -> (Banks, lending_dapp::User) {
+    let mut banks = Banks::new();
+    let user = banks.user(
+        &[(banks.sol, SOL), (banks.usdt, 10 * USDC)],
+        &[(banks.usdc, 85 * USDC)],
+    );
+    (banks, user)
+}
+
+#[test]
+fn healthy_borrowers_are_skipped() {
+    let (banks, user) = setup();
+    let opportunity = find_opportunity(&user, &banks.states, &market()).unwrap();
+    assert!(opportunity.is_none());
+}
+
+#[test]
+fn liquidates_the_largest_debt_against_the_largest_collateral() {
+    let (mut banks, user) = setup();
+    banks.set_price(banks.sol, 90 * USDC_PRICE);
+
+    let opportunity = find_opportunity(&user, &banks.states, &market())
+        .unwrap()
+        .unwrap();
+    assert_eq!(opportunity.borrower, user.owner);
+    assert!(opportunity.health_factor < 1.0);
+    assert_eq!(opportunity.collateral_bank, banks.sol);
+    assert_eq!(opportunity.borrowed_bank, banks.usdc);
+    // Half the debt, paid for with $44.625 of SOL at $90.
+    assert_eq!(opportunity.repay_amount, 42_500_000);
+    assert_eq!(opportunity.seize_amount, 495_833_333);
+    assert_eq!(opportunity.profit_value, 212_499_997);
+    // The USDT position is the only one not named by the liquidation.
+    assert_eq!(opportunity.health_accounts.len(), 1);
+    assert_eq!(opportunity.health_accounts[0].bank, banks.usdt);
+    assert_eq!(
+        opportunity.health_accounts[0].price_update,
+        banks.states[&banks.usdt].price_update
+    );
+}
+
+#[test]
+fn disabled_collateral_is_not_seized() {
+    let (mut banks, mut user) = setup();
+    user.balances[0].collateral_disabled = true;
+    banks.set_price(banks.sol, 90 * USDC_PRICE);
+
+    // The 10 USDT cannot cover half the debt plus the bonus, so the
+    // repayment shrinks to what they pay for.
+    let opportunity = find_opportunity(&user, &banks.states, &market())
+        .unwrap()
+        .unwrap();
+    assert_eq!(opportunity.collateral_bank, banks.usdt);
+    assert_eq!(opportunity.seize_amount, 10 * USDC);
+    assert_eq!(opportunity.repay_amount, 9_523_809);
+}
+
+#[test]
+fn borrowers_with_an_unpriced_bank_are_skipped() {
+    let (mut banks, user) = setup();
+    banks.set_price(banks.sol, 90 * USDC_PRICE);
+    banks.states.remove(&banks.usdt);
+
+    let opportunity = find_opportunity(&user, &banks.states, &market()).unwrap();
+    assert!(opportunity.is_none());
+}
+
+#[test]
+fn read_price_rejects_other_accounts() {
+    assert_eq!(
+        read_price(&price_account(SOL_PRICE)).unwrap(),
+        (SOL_PRICE, PRICE_EXPONENT)
+    );
+    let mut data = price_account(SOL_PRICE);
+    data[0] ^= 1;
+    assert!(read_price(&data).is_err());
+}
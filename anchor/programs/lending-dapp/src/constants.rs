@@ -6,3 +6,20 @@ pub const USDC_USD_FEED_ID: &str =
 pub const USDT_USD_FEED_ID: &str =
     "0x2b89b9dc8fdf9f34709a5b106b472f0f39bb6ca9ce04b0fd7f2e971688e2e53b";
 pub const MAX_AGE: u64 = 3600;
+pub const MAX_TICKER_LEN: usize = 5;
+pub const MAX_SUPPORTED_ASSETS: usize = 16;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+pub const DEFAULT_FLASH_LOAN_FEE_BPS: u16 = 9;
+pub const DEFAULT_CLOSE_FACTOR_BPS: u64 = 5_000;
+pub const DEFAULT_LIQUIDATION_BONUS_BPS: u64 = 500;
+pub const MAX_SWAP_PROGRAMS: usize = 4;
+pub const MAX_EMODE_CATEGORIES: usize = 8;
+pub const MAX_EMODE_NAME_LEN: usize = 32;
+pub const SHARE_PRICE_SCALE: u128 = 1_000_000_000_000_000_000;
+/// Virtual shares and assets added to both sides of every deposit share
+/// conversion, so a first depositor cannot inflate the share price cheaply.
+pub const VIRTUAL_DEPOSIT_OFFSET: u64 = 1_000_000;
+/// Decimals of `Bank::isolated_debt` and `BankConfig::debt_ceiling`. Only
+/// stablecoins can be borrowed against isolated collateral, so debt is
+/// counted in dollars at face value with this many decimals.
+pub const ISOLATED_DEBT_DECIMALS: u8 = 2;
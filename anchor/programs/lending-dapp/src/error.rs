@@ -21,5 +21,67 @@ pub enum ErrorCode {
     #[msg("DivisionByZero.")]
     DivisionByZero,
     #[msg("Invalid Ticker Supplied.")]
-    InvalidTicker
+    InvalidTicker,
+    #[msg("Oracle feed does not match the supported asset.")]
+    InvalidOracleFeed,
+    #[msg("Mint decimals do not match the supported asset.")]
+    InvalidMintDecimals,
+    #[msg("Supported asset list is full.")]
+    SupportedAssetsFull,
+    #[msg("A flash loan is already in progress for this bank.")]
+    FlashLoanInProgress,
+    #[msg("Flash loans cannot be taken through CPI.")]
+    FlashLoanCpiNotAllowed,
+    #[msg("No matching flash repay found in this transaction.")]
+    MissingFlashRepay,
+    #[msg("Flash repay does not match the outstanding flash loan.")]
+    InvalidFlashRepay,
+    #[msg("Invalid bank config.")]
+    InvalidBankConfig,
+    #[msg("Swap program is not whitelisted by the market.")]
+    SwapProgramNotAllowed,
+    #[msg("Swap program list is full.")]
+    SwapProgramsFull,
+    #[msg("Swap returned less than the minimum output.")]
+    SlippageExceeded,
+    #[msg("Position would be unhealthy after this action.")]
+    UnhealthyPosition,
+    #[msg("Invalid oracle price.")]
+    InvalidPrice,
+    #[msg("Invalid leverage or slippage parameters.")]
+    InvalidLeverage,
+    #[msg("Missing bank or price account for a position in the health check.")]
+    MissingHealthAccount,
+    #[msg("Bank is paused.")]
+    BankPaused,
+    #[msg("Borrower has no collateral or debt in these banks.")]
+    NothingToLiquidate,
+    #[msg("Amount is too small to mint or burn any shares.")]
+    ZeroShares,
+    #[msg("Bank does not issue deposit receipts.")]
+    ReceiptsDisabled,
+    #[msg("Mint uses a Token-2022 extension the bank does not support.")]
+    UnsupportedMintExtension,
+    #[msg("Mint uses an extension or freeze authority the market owner has not allowed.")]
+    MintExtensionNotAllowed,
+    #[msg("Native SOL instructions only work with the wrapped SOL bank.")]
+    NotNativeMint,
+    #[msg("Isolated collateral cannot be combined with other collateral or existing debt.")]
+    IsolatedCollateralMixed,
+    #[msg("Asset cannot be borrowed against isolated collateral.")]
+    NotBorrowableInIsolation,
+    #[msg("Borrow would exceed the isolated collateral's debt ceiling.")]
+    DebtCeilingExceeded,
+    #[msg("The user's isolated collateral bank must be passed.")]
+    MissingIsolatedBank,
+    #[msg("Invalid e-mode category.")]
+    InvalidEmodeCategory,
+    #[msg("E-mode category list is full.")]
+    EmodeCategoriesFull,
+    #[msg("Borrowing is disabled for this bank.")]
+    BorrowingDisabled,
+    #[msg("Deposit is excluded from collateral.")]
+    CollateralDisabled,
+    #[msg("Fewer remaining accounts than the swap instruction needs.")]
+    InvalidSwapAccounts,
 }
@@ -0,0 +1,215 @@
+use anchor_lang::prelude::*;
+
+use crate::MintExtensions;
+
+#[event]
+pub struct Deposit {
+    pub user: Pubkey,
+    pub bank: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub share_price: u128,
+    /// Oracle price of the bank's asset, when the instruction valued the
+    /// user's portfolio.
+    pub price: Option<i64>,
+    /// Health factor of the user's portfolio once the instruction settled,
+    /// valued alongside `price`.
+    pub health_factor: Option<f64>,
+}
+
+#[event]
+pub struct Withdraw {
+    pub user: Pubkey,
+    pub bank: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub share_price: u128,
+    /// Oracle price of the bank's asset, when the instruction valued the
+    /// user's portfolio.
+    pub price: Option<i64>,
+    /// Health factor of the user's portfolio once the instruction settled,
+    /// valued alongside `price`.
+    pub health_factor: Option<f64>,
+}
+
+#[event]
+pub struct Borrow {
+    pub user: Pubkey,
+    pub bank: Pubkey,
+    pub collateral_bank: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub share_price: u128,
+    pub collateral_price: i64,
+    pub borrow_price: i64,
+    pub health_factor: f64,
+}
+
+#[event]
+pub struct Repay {
+    pub user: Pubkey,
+    pub bank: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub share_price: u128,
+    /// Oracle price of the bank's asset, when the instruction valued the
+    /// user's portfolio.
+    pub price: Option<i64>,
+    /// Health factor of the user's portfolio once the instruction settled,
+    /// valued alongside `price`.
+    pub health_factor: Option<f64>,
+}
+
+#[event]
+pub struct Liquidate {
+    pub liquidator: Pubkey,
+    pub borrower: Pubkey,
+    pub collateral_bank: Pubkey,
+    pub borrowed_bank: Pubkey,
+    pub repaid_amount: u64,
+    pub seized_amount: u64,
+    pub collateral_price: i64,
+    pub borrowed_price: i64,
+    /// Health factor of the position once it was liquidated.
+    pub health_factor: f64,
+}
+
+#[event]
+pub struct BankInitialized {
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub ticker_symbol: String,
+    pub oracle_feed_id: [u8; 32],
+    pub liquidation_threshold: f64,
+    pub max_ltv: f64,
+    pub mint_extensions: MintExtensions,
+}
+
+#[event]
+pub struct BankConfigUpdated {
+    pub bank: Pubkey,
+    pub liquidation_threshold: f64,
+    pub max_ltv: f64,
+    pub liability_weight_init: f64,
+    pub liability_weight_maint: f64,
+    pub flash_loan_fee_bps: u16,
+    pub paused: bool,
+    pub close_factor_bps: u64,
+    pub liquidation_bonus_bps: u64,
+    pub isolated: bool,
+    pub debt_ceiling: u64,
+    pub borrowable_in_isolation: bool,
+    pub emode_category: u8,
+    pub can_be_collateral: bool,
+    pub borrowing_enabled: bool,
+}
+
+#[event]
+pub struct InterestAccrued {
+    pub bank: Pubkey,
+    pub interest: u64,
+    pub total_borrowed: u64,
+    pub borrow_share_price: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReceiptMinted {
+    pub user: Pubkey,
+    pub bank: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub share_price: u128,
+}
+
+#[event]
+pub struct ReceiptRedeemed {
+    pub user: Pubkey,
+    pub bank: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub share_price: u128,
+}
+
+/// Receipts burned and credited to the user's position as collateral.
+#[event]
+pub struct ReceiptCollateralized {
+    pub user: Pubkey,
+    pub bank: Pubkey,
+    pub shares: u64,
+}
+
+/// Deposit shares moved out of the user's position and minted as receipts.
+#[event]
+pub struct CollateralWithdrawnAsReceipt {
+    pub user: Pubkey,
+    pub bank: Pubkey,
+    pub shares: u64,
+}
+
+/// The user opted into e-mode `emode_category`, or out of e-mode when zero.
+#[event]
+pub struct UserEmodeSet {
+    pub user: Pubkey,
+    pub emode_category: u8,
+}
+
+/// The user included its deposit in `bank` in collateral, or excluded it.
+#[event]
+pub struct CollateralEnabledSet {
+    pub user: Pubkey,
+    pub bank: Pubkey,
+    pub enabled: bool,
+}
+
+/// `amount` was lent out of `bank` until the matching flash repay.
+#[event]
+pub struct FlashBorrow {
+    pub user: Pubkey,
+    pub bank: Pubkey,
+    pub amount: u64,
+}
+
+/// A flash loan of `amount` was returned to `bank` together with `fee`.
+#[event]
+pub struct FlashRepay {
+    pub user: Pubkey,
+    pub bank: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+/// The market registry now lists `ticker_symbol` with this feed and decimals.
+#[event]
+pub struct SupportedAssetSet {
+    pub ticker_symbol: String,
+    pub oracle_feed_id: [u8; 32],
+    pub decimals: u8,
+}
+
+#[event]
+pub struct SupportedAssetRemoved {
+    pub ticker_symbol: String,
+}
+
+/// E-mode category `id` was created or replaced.
+#[event]
+pub struct EmodeCategorySet {
+    pub id: u8,
+    pub name: String,
+    pub max_ltv: f64,
+    pub liquidation_threshold: f64,
+}
+
+#[event]
+pub struct EmodeCategoryRemoved {
+    pub id: u8,
+}
+
+/// `program_id` was added to the swap program whitelist, or removed from it.
+#[event]
+pub struct SwapProgramSet {
+    pub program_id: Pubkey,
+    pub allowed: bool,
+}
@@ -1,16 +1,68 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
 
 use crate::error::ErrorCode;
-use crate::{user, Bank, User, ANCHOR_DISCRIMINATOR};
-use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token_interface::{
-    close_account, transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+use crate::{
+    events, Bank, EmodeCategory, Market, MintExtensions, SupportedAsset, User,
+    ANCHOR_DISCRIMINATOR, BPS_DENOMINATOR, DEFAULT_CLOSE_FACTOR_BPS, DEFAULT_FLASH_LOAN_FEE_BPS,
+    DEFAULT_LIQUIDATION_BONUS_BPS, MAX_EMODE_CATEGORIES, MAX_EMODE_NAME_LEN,
+    MAX_SUPPORTED_ASSETS, MAX_SWAP_PROGRAMS, MAX_TICKER_LEN,
+    SOL_USD_FEED_ID, USDC_USD_FEED_ID, USDT_USD_FEED_ID,
 };
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 use pyth_solana_receiver_sdk::price_update::get_feed_id_from_hex;
+
+use super::{check_mint_extensions, transfer_from_treasury};
+
+#[derive(Accounts)]
+pub struct InitMarket<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    /// Only the program's upgrade authority may create the market and
+    /// become its owner.
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID,
+        constraint = program_data.upgrade_authority_address == Some(signer.key()) @ ErrorCode::Unauthorized,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(
+        init,
+        payer = signer,
+        space = ANCHOR_DISCRIMINATOR + Market::INIT_SPACE,
+        seeds = [b"market"],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateMarket<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"market"],
+        bump,
+        constraint = market.owner == signer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub market: Account<'info, Market>,
+}
+
+#[event_cpi]
 #[derive(Accounts)]
 pub struct InitBank<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+        constraint = market.owner == signer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub market: Account<'info, Market>,
 
     pub mint: InterfaceAccount<'info, Mint>,
     #[account(
@@ -35,6 +87,45 @@ pub struct InitBank<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateBankConfig<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [mint.key().as_ref()],
+        bump,
+        constraint = bank.authority == signer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub bank: Account<'info, Bank>,
+}
+
+/// Fields left as `None` keep their current value.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct BankConfigParams {
+    pub liquidation_threshold: Option<f64>,
+    pub max_ltv: Option<f64>,
+    pub liability_weight_init: Option<f64>,
+    pub liability_weight_maint: Option<f64>,
+    pub flash_loan_fee_bps: Option<u16>,
+    pub paused: Option<bool>,
+    pub close_factor_bps: Option<u64>,
+    pub liquidation_bonus_bps: Option<u64>,
+    pub isolated: Option<bool>,
+    pub debt_ceiling: Option<u64>,
+    pub borrowable_in_isolation: Option<bool>,
+    /// Zero takes the bank out of e-mode.
+    pub emode_category: Option<u8>,
+    pub can_be_collateral: Option<bool>,
+    pub borrowing_enabled: Option<bool>,
+}
+
 #[derive(Accounts)]
 pub struct InitUser<'info> {
     #[account(mut)]
@@ -87,21 +178,360 @@ pub struct CloseBank<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
+pub fn process_init_market(context: Context<InitMarket>) -> Result<()> {
+    let market = &mut context.accounts.market;
+    market.owner = context.accounts.signer.key();
+    market.supported_assets = vec![
+        SupportedAsset {
+            ticker_symbol: "SOL".to_string(),
+            oracle_feed_id: get_feed_id_from_hex(SOL_USD_FEED_ID)?,
+            decimals: 9,
+        },
+        SupportedAsset {
+            ticker_symbol: "USDC".to_string(),
+            oracle_feed_id: get_feed_id_from_hex(USDC_USD_FEED_ID)?,
+            decimals: 6,
+        },
+        SupportedAsset {
+            ticker_symbol: "USDT".to_string(),
+            oracle_feed_id: get_feed_id_from_hex(USDT_USD_FEED_ID)?,
+            decimals: 6,
+        },
+    ];
+    Ok(())
+}
+
+pub fn process_set_supported_asset(
+    context: Context<UpdateMarket>,
+    ticker_symbol: String,
+    oracle_feed_id_hex: &str,
+    decimals: u8,
+) -> Result<()> {
+    require!(
+        !ticker_symbol.is_empty() && ticker_symbol.len() <= MAX_TICKER_LEN,
+        ErrorCode::InvalidTicker
+    );
+    let asset = SupportedAsset {
+        ticker_symbol,
+        oracle_feed_id: get_feed_id_from_hex(oracle_feed_id_hex)?,
+        decimals,
+    };
+    let event = events::SupportedAssetSet {
+        ticker_symbol: asset.ticker_symbol.clone(),
+        oracle_feed_id: asset.oracle_feed_id,
+        decimals: asset.decimals,
+    };
+    let market = &mut context.accounts.market;
+    match market
+        .supported_assets
+        .iter_mut()
+        .find(|existing| existing.ticker_symbol == asset.ticker_symbol)
+    {
+        Some(existing) => *existing = asset,
+        None => {
+            require!(
+                market.supported_assets.len() < MAX_SUPPORTED_ASSETS,
+                ErrorCode::SupportedAssetsFull
+            );
+            market.supported_assets.push(asset);
+        }
+    }
+    let ctx = &context;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn process_remove_supported_asset(
+    context: Context<UpdateMarket>,
+    ticker_symbol: String,
+) -> Result<()> {
+    let market = &mut context.accounts.market;
+    let index = market
+        .supported_assets
+        .iter()
+        .position(|asset| asset.ticker_symbol == ticker_symbol)
+        .ok_or(ErrorCode::InvalidTicker)?;
+    market.supported_assets.remove(index);
+    let ctx = &context;
+    emit_cpi!(events::SupportedAssetRemoved { ticker_symbol });
+    Ok(())
+}
+
+/// Creates or replaces e-mode category `id`. Its weights must be valid
+/// fractions with the LTV no higher than the liquidation threshold.
+pub fn process_set_emode_category(
+    context: Context<UpdateMarket>,
+    id: u8,
+    name: String,
+    max_ltv: f64,
+    liquidation_threshold: f64,
+) -> Result<()> {
+    require!(
+        id != 0
+            && name.len() <= MAX_EMODE_NAME_LEN
+            && max_ltv > 0.0
+            && max_ltv <= liquidation_threshold
+            && liquidation_threshold < 1.0,
+        ErrorCode::InvalidEmodeCategory
+    );
+    let category = EmodeCategory {
+        id,
+        name,
+        max_ltv,
+        liquidation_threshold,
+    };
+    let event = events::EmodeCategorySet {
+        id,
+        name: category.name.clone(),
+        max_ltv,
+        liquidation_threshold,
+    };
+    let market = &mut context.accounts.market;
+    match market
+        .emode_categories
+        .iter_mut()
+        .find(|existing| existing.id == id)
+    {
+        Some(existing) => *existing = category,
+        None => {
+            require!(
+                market.emode_categories.len() < MAX_EMODE_CATEGORIES,
+                ErrorCode::EmodeCategoriesFull
+            );
+            market.emode_categories.push(category);
+        }
+    }
+    let ctx = &context;
+    emit_cpi!(event);
+    Ok(())
+}
+
+/// Removes e-mode category `id`. Banks and users still pointing at it fall
+/// back to the banks' own weights.
+pub fn process_remove_emode_category(context: Context<UpdateMarket>, id: u8) -> Result<()> {
+    let market = &mut context.accounts.market;
+    let index = market
+        .emode_categories
+        .iter()
+        .position(|category| category.id == id)
+        .ok_or(ErrorCode::InvalidEmodeCategory)?;
+    market.emode_categories.remove(index);
+    let ctx = &context;
+    emit_cpi!(events::EmodeCategoryRemoved { id });
+    Ok(())
+}
+
+pub fn process_add_swap_program(context: Context<UpdateMarket>, program_id: Pubkey) -> Result<()> {
+    let market = &mut context.accounts.market;
+    if market.swap_programs.contains(&program_id) {
+        return Ok(());
+    }
+    require!(
+        market.swap_programs.len() < MAX_SWAP_PROGRAMS,
+        ErrorCode::SwapProgramsFull
+    );
+    market.swap_programs.push(program_id);
+    let ctx = &context;
+    emit_cpi!(events::SwapProgramSet {
+        program_id,
+        allowed: true,
+    });
+    Ok(())
+}
+
+pub fn process_remove_swap_program(
+    context: Context<UpdateMarket>,
+    program_id: Pubkey,
+) -> Result<()> {
+    let market = &mut context.accounts.market;
+    market
+        .swap_programs
+        .retain(|swap_program| *swap_program != program_id);
+    let ctx = &context;
+    emit_cpi!(events::SwapProgramSet {
+        program_id,
+        allowed: false,
+    });
+    Ok(())
+}
+
 pub fn process_init_bank(
     context: Context<InitBank>,
     liquidation_threshold: f64,
     max_ltv: f64,
     oracle_feed_id_hex: &str,
     ticker_symbol: String,
+    allowed_mint_extensions: MintExtensions,
 ) -> Result<()> {
+    require!(
+        !ticker_symbol.is_empty() && ticker_symbol.len() <= MAX_TICKER_LEN,
+        ErrorCode::InvalidTicker
+    );
+    let asset = context
+        .accounts
+        .market
+        .get_supported_asset(&ticker_symbol)
+        .ok_or(ErrorCode::InvalidTicker)?;
+    let oracle_feed_id = get_feed_id_from_hex(oracle_feed_id_hex)?;
+    require!(
+        asset.oracle_feed_id == oracle_feed_id,
+        ErrorCode::InvalidOracleFeed
+    );
+    require!(
+        asset.decimals == context.accounts.mint.decimals,
+        ErrorCode::InvalidMintDecimals
+    );
+    require!(
+        valid_collateral_weights(max_ltv, liquidation_threshold),
+        ErrorCode::InvalidBankConfig
+    );
+    let mint_extensions =
+        check_mint_extensions(&context.accounts.mint, &allowed_mint_extensions)?;
+
     let bank = &mut context.accounts.bank;
     bank.authority = context.accounts.signer.key();
     bank.token_mint_address = context.accounts.mint.key();
+    bank.mint_decimals = context.accounts.mint.decimals;
     bank.liquidation_threshold = liquidation_threshold;
     bank.max_ltv = max_ltv;
+    bank.liability_weight_init = 1.0;
+    bank.liability_weight_maint = 1.0;
     bank.interest_rate = 0.05;
-    bank.config.oracle_feed_id = get_feed_id_from_hex(oracle_feed_id_hex)?;
+    bank.close_factor = DEFAULT_CLOSE_FACTOR_BPS;
+    bank.liquidation_bonus = DEFAULT_LIQUIDATION_BONUS_BPS;
+    bank.config.oracle_feed_id = oracle_feed_id;
     bank.config.ticker_symbol = ticker_symbol;
+    bank.config.flash_loan_fee_bps = DEFAULT_FLASH_LOAN_FEE_BPS;
+    bank.config.mint_extensions = mint_extensions;
+    bank.config.can_be_collateral = true;
+    bank.config.borrowing_enabled = true;
+
+    let event = events::BankInitialized {
+        bank: bank.key(),
+        mint: bank.token_mint_address,
+        authority: bank.authority,
+        ticker_symbol: bank.config.ticker_symbol.clone(),
+        oracle_feed_id: bank.config.oracle_feed_id,
+        liquidation_threshold: bank.liquidation_threshold,
+        max_ltv: bank.max_ltv,
+        mint_extensions,
+    };
+    let ctx = &context;
+    emit_cpi!(event);
+    Ok(())
+}
+
+/// A bank's collateral must lend something and keep the liquidation
+/// threshold between the max LTV and the full deposit value.
+fn valid_collateral_weights(max_ltv: f64, liquidation_threshold: f64) -> bool {
+    0.0 < max_ltv && max_ltv <= liquidation_threshold && liquidation_threshold <= 1.0
+}
+
+pub fn process_update_bank_config(
+    context: Context<UpdateBankConfig>,
+    params: BankConfigParams,
+) -> Result<()> {
+    let bank = &mut context.accounts.bank;
+    if let Some(liquidation_threshold) = params.liquidation_threshold {
+        bank.liquidation_threshold = liquidation_threshold;
+    }
+    if let Some(max_ltv) = params.max_ltv {
+        bank.max_ltv = max_ltv;
+    }
+    if let Some(liability_weight_init) = params.liability_weight_init {
+        bank.liability_weight_init = liability_weight_init;
+    }
+    if let Some(liability_weight_maint) = params.liability_weight_maint {
+        bank.liability_weight_maint = liability_weight_maint;
+    }
+    require!(
+        valid_collateral_weights(bank.max_ltv, bank.liquidation_threshold),
+        ErrorCode::InvalidBankConfig
+    );
+    require!(
+        bank.liability_weight_maint >= 1.0
+            && bank.liability_weight_init >= bank.liability_weight_maint,
+        ErrorCode::InvalidBankConfig
+    );
+    if let Some(flash_loan_fee_bps) = params.flash_loan_fee_bps {
+        require!(
+            flash_loan_fee_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidBankConfig
+        );
+        bank.config.flash_loan_fee_bps = flash_loan_fee_bps;
+    }
+    if let Some(paused) = params.paused {
+        bank.config.paused = paused;
+    }
+    if let Some(close_factor_bps) = params.close_factor_bps {
+        require!(
+            close_factor_bps > 0 && close_factor_bps <= BPS_DENOMINATOR,
+            ErrorCode::InvalidBankConfig
+        );
+        bank.close_factor = close_factor_bps;
+    }
+    if let Some(liquidation_bonus_bps) = params.liquidation_bonus_bps {
+        require!(
+            liquidation_bonus_bps <= BPS_DENOMINATOR,
+            ErrorCode::InvalidBankConfig
+        );
+        bank.liquidation_bonus = liquidation_bonus_bps;
+    }
+    if let Some(isolated) = params.isolated {
+        // Existing positions were opened under the old rules and debt
+        // ceiling accounting, so only an empty bank can switch.
+        require!(
+            isolated == bank.config.isolated
+                || (bank.total_deposits == 0 && bank.total_borrowed == 0),
+            ErrorCode::InvalidBankConfig
+        );
+        bank.config.isolated = isolated;
+    }
+    if let Some(debt_ceiling) = params.debt_ceiling {
+        bank.config.debt_ceiling = debt_ceiling;
+    }
+    if let Some(borrowable_in_isolation) = params.borrowable_in_isolation {
+        bank.config.borrowable_in_isolation = borrowable_in_isolation;
+    }
+    if let Some(emode_category) = params.emode_category {
+        require!(
+            emode_category == 0
+                || context.accounts.market.get_emode_category(emode_category).is_some(),
+            ErrorCode::InvalidBankConfig
+        );
+        bank.config.emode_category = emode_category;
+    }
+    if let Some(can_be_collateral) = params.can_be_collateral {
+        bank.config.can_be_collateral = can_be_collateral;
+    }
+    if let Some(borrowing_enabled) = params.borrowing_enabled {
+        bank.config.borrowing_enabled = borrowing_enabled;
+    }
+    // An isolated bank lending against itself would book its own debt.
+    require!(
+        !(bank.config.isolated && bank.config.borrowable_in_isolation),
+        ErrorCode::InvalidBankConfig
+    );
+
+    let event = events::BankConfigUpdated {
+        bank: bank.key(),
+        liquidation_threshold: bank.liquidation_threshold,
+        max_ltv: bank.max_ltv,
+        liability_weight_init: bank.liability_weight_init,
+        liability_weight_maint: bank.liability_weight_maint,
+        flash_loan_fee_bps: bank.config.flash_loan_fee_bps,
+        paused: bank.config.paused,
+        close_factor_bps: bank.close_factor,
+        liquidation_bonus_bps: bank.liquidation_bonus,
+        isolated: bank.config.isolated,
+        debt_ceiling: bank.config.debt_ceiling,
+        borrowable_in_isolation: bank.config.borrowable_in_isolation,
+        emode_category: bank.config.emode_category,
+        can_be_collateral: bank.config.can_be_collateral,
+        borrowing_enabled: bank.config.borrowing_enabled,
+    };
+    let ctx = &context;
+    emit_cpi!(event);
     Ok(())
 }
 
@@ -110,38 +540,25 @@ pub fn process_init_user(context: Context<InitUser>) -> Result<()> {
     user_account.owner = context.accounts.signer.key();
     Ok(())
 }
-pub fn process_close_bank(context: Context<CloseBank>) -> Result<()> {
+pub fn process_close_bank<'info>(
+    context: Context<'_, '_, 'info, 'info, CloseBank<'info>>,
+) -> Result<()> {
     if context.accounts.bank.authority != context.accounts.signer.key() {
         return Err(ErrorCode::Unauthorized.into());
     }
     let user = &mut context.accounts.user_account;
     let balance = user.get_balance(&context.accounts.bank.key());
-    if !balance.is_none() {
-        let balance = balance.unwrap();
+    if let Some(balance) = balance {
         let amount_to_be_returned = balance.deposited;
         balance.clear();
-        let seeds = &[
-            b"treasury",
-            context.accounts.mint.to_account_info().key.as_ref(),
-            &[context.bumps.bank_token_account],
-        ];
-        let signer_seeds = [&seeds[..]];
-
-        let accounts = TransferChecked {
-            from: context.accounts.bank_token_account.to_account_info(),
-            to: context.accounts.user_token_account.to_account_info(),
-            mint: context.accounts.mint.to_account_info(),
-            authority: context.accounts.bank_token_account.to_account_info(),
-        };
-        let cpi_context = CpiContext::new_with_signer(
-            context.accounts.token_program.to_account_info(),
-            accounts,
-            &signer_seeds,
-        );
-        transfer_checked(
-            cpi_context,
+        transfer_from_treasury(
+            &context.accounts.bank_token_account,
+            context.bumps.bank_token_account,
+            &context.accounts.user_token_account,
             amount_to_be_returned,
-            context.accounts.mint.decimals,
+            &context.accounts.mint,
+            &context.accounts.token_program,
+            context.remaining_accounts,
         )?;
     }
 
@@ -1,28 +1,34 @@
-use core::borrow;
-
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
-use pyth_solana_receiver_sdk::price_update::{get_feed_id_from_hex, PriceUpdateV2};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
-use crate::{Bank, User, MAX_AGE};
+use crate::{events, Bank, EmodeCategory, Market, User};
 
 use crate::error::ErrorCode;
 
-use super::calculate_accrued_interest;
+use super::{calculate_portfolio_health, load_health_banks_with, transfer_from_treasury};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Borrow<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
     pub collateral_mint: InterfaceAccount<'info, Mint>,
     pub borrow_mint: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
         seeds = [borrow_mint.key().as_ref()],
         bump,
+        constraint = !borrow_bank.config.paused @ ErrorCode::BankPaused,
+        constraint = borrow_bank.config.borrowing_enabled @ ErrorCode::BorrowingDisabled,
     )]
     pub borrow_bank: Account<'info, Bank>,
     #[account(
@@ -58,91 +64,127 @@ pub struct Borrow<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
-pub fn process_borrow(context: Context<Borrow>, amount: u64) -> Result<()> {
-    let borrow_bank = &mut context.accounts.borrow_bank;
-    let user = &mut context.accounts.user_account;
-    let now = Clock::get()?;
-    let decimals = context.accounts.borrow_mint.decimals as u64;
-    
-    let time_elapsed: i64 = now.unix_timestamp - borrow_bank.last_updated_borrow;
-    if time_elapsed > 0 {
-        let interest = calculate_accrued_interest(
-            borrow_bank.total_borrowed,
-            borrow_bank.interest_rate,
-            borrow_bank.last_updated_borrow,
-        )?;
-        borrow_bank.total_borrowed = borrow_bank
-            .total_borrowed
-            .checked_add(interest)
-            .ok_or(ErrorCode::Overflow)?;
-        borrow_bank.last_updated_borrow = now.unix_timestamp;
-    }
+/// Remaining accounts are `[bank, price_update]` pairs for every other bank
+/// the user has a position in, followed by any transfer hook accounts.
+pub fn process_borrow<'info>(
+    context: Context<'_, '_, 'info, 'info, Borrow<'info>>,
+    amount: u64,
+) -> Result<()> {
+    let emode = context.accounts.market.user_emode(&context.accounts.user_account).cloned();
+    let (interest_event, borrow_event) = record_borrow(
+        &mut context.accounts.borrow_bank,
+        &mut context.accounts.collateral_bank,
+        &mut context.accounts.user_account,
+        emode.as_ref(),
+        &context.accounts.collateral_price_update,
+        &context.accounts.borrow_price_update,
+        context.remaining_accounts,
+        amount,
+    )?;
 
-    let collateral_bank = &context.accounts.collateral_bank;
-    let collateral_balance = user
-        .balances
-        .iter()
-        .find(|b| b.bank_address == collateral_bank.key())
-        .ok_or(ErrorCode::InsufficientFunds)?;
-    let collateral_feed_id = collateral_bank.config.oracle_feed_id;
-    let collateral_price = context.accounts.collateral_price_update.get_price_no_older_than(&now, MAX_AGE, &collateral_feed_id)?.price as u128;
-    let updated_collateral = calculate_accrued_interest(
-        collateral_balance.deposited,
-        collateral_bank.interest_rate,
-        collateral_balance.last_updated_deposit,
+    transfer_from_treasury(
+        &context.accounts.bank_token_account,
+        context.bumps.bank_token_account,
+        &context.accounts.user_token_account,
+        amount,
+        &context.accounts.borrow_mint,
+        &context.accounts.token_program,
+        context.remaining_accounts,
     )?;
-    let collateral_value_usd = updated_collateral as u128 * collateral_price;
 
-    let borrow_feed_id = borrow_bank.config.oracle_feed_id;
-    let borrow_price = context.accounts.borrow_price_update.get_price_no_older_than(&now, MAX_AGE, &borrow_feed_id)?.price as u128;
-    let borrow_value_usd = amount as u128 * borrow_price;
-    
-    let allowed_borrow = (collateral_value_usd * borrow_bank.max_ltv as u128) ;
-    
-    if borrow_value_usd > allowed_borrow {
-        return Err(ErrorCode::OverBorrowableAmount.into());
+    let ctx = &context;
+    if interest_event.interest > 0 {
+        emit_cpi!(interest_event);
     }
-    let share_price = if borrow_bank.total_borrowed_shares == 0 {
-        decimals 
-    } else {
-        ((borrow_bank.total_borrowed as u128 * decimals as u128) / borrow_bank.total_borrowed_shares as u128) as u64
-    };
+    emit_cpi!(borrow_event);
+    Ok(())
+}
 
-    let shares_to_mint = amount as u128 * decimals as u128 / share_price as u128;
-    let seeds = &[
-        b"treasury",
-        context.accounts.borrow_mint.to_account_info().key.as_ref(),
-        &[context.bumps.bank_token_account],
-    ];
-    let signer_seeds = [&seeds[..]];
-    let accounts = TransferChecked {
-        from: context.accounts.bank_token_account.to_account_info(),
-        to: context.accounts.user_token_account.to_account_info(),
-        mint: context.accounts.borrow_mint.to_account_info(),
-        authority: context.accounts.bank_token_account.to_account_info(),
-    };
-    let cpi_context = CpiContext::new_with_signer(
-        context.accounts.token_program.to_account_info(),
-        accounts,
-        &signer_seeds,
+/// Books a borrow of `amount` against the owner's deposit in `collateral_bank` and checks the
+/// whole portfolio stays within its borrowing power and the isolation rules. `emode` is the
+/// category the user opted into. `health_accounts` carry the `[bank, price_update]` pair of
+/// every other bank the user has a position in. The caller sends the tokens. Returns the
+/// events to emit.
+#[allow(clippy::too_many_arguments)]
+pub fn record_borrow<'info>(
+    borrow_bank: &mut Account<'info, Bank>,
+    collateral_bank: &mut Account<'info, Bank>,
+    user: &mut User,
+    emode: Option<&EmodeCategory>,
+    collateral_price_update: &PriceUpdateV2,
+    borrow_price_update: &PriceUpdateV2,
+    health_accounts: &'info [AccountInfo<'info>],
+    amount: u64,
+) -> Result<(events::InterestAccrued, events::Borrow)> {
+    require_keys_neq!(
+        collateral_bank.key(),
+        borrow_bank.key(),
+        ErrorCode::InvalidCollateralMint
+    );
+    let now = Clock::get()?;
+    let interest = borrow_bank.accrue(now.unix_timestamp)?;
+    if collateral_bank.config.isolated {
+        user.enter_collateral(&collateral_bank.key(), true)?;
+    }
+    // A user in isolation mode borrows against the isolated bank only, so
+    // every borrow counts towards its debt ceiling.
+    if user.isolated_bank != Pubkey::default() {
+        require_keys_eq!(
+            collateral_bank.key(),
+            user.isolated_bank,
+            ErrorCode::MissingIsolatedBank
+        );
+    }
+    require!(
+        user.get_balance(&collateral_bank.key()).is_some(),
+        ErrorCode::InsufficientFunds
     );
-    transfer_checked(cpi_context, amount, context.accounts.borrow_mint.decimals)?;
-
-    borrow_bank.total_borrowed = borrow_bank
-    .total_borrowed
-    .checked_add(amount)
-    .ok_or(ErrorCode::Overflow)?;
-
-borrow_bank.total_borrowed_shares = borrow_bank
-    .total_borrowed_shares
-    .checked_add(shares_to_mint as u64)
-    .ok_or(ErrorCode::Overflow)?;
-
 
+    let owner = user.owner;
+    let change = borrow_bank.borrow(amount)?;
+    collateral_bank.add_isolated_debt(borrow_bank, amount)?;
     let balance = user.get_balance_or_create(&borrow_bank.key())?;
-    balance.borrowed += amount;
-    balance.borrowed_shares += shares_to_mint as u64;
+    balance.borrowed = balance.borrowed.checked_add(amount).ok_or(ErrorCode::Overflow)?;
+    balance.borrowed_shares = balance
+        .borrowed_shares
+        .checked_add(change.shares)
+        .ok_or(ErrorCode::Overflow)?;
     balance.last_updated_borrow = now.unix_timestamp;
 
-    Ok(())
+    let banks = load_health_banks_with(
+        health_accounts,
+        &now,
+        &[
+            (&*collateral_bank, collateral_price_update),
+            (&*borrow_bank, borrow_price_update),
+        ],
+    )?;
+    let health = calculate_portfolio_health(user, &banks, emode)?;
+    require!(health.is_healthy(), ErrorCode::OverBorrowableAmount);
+    let price_of = |key: Pubkey| {
+        banks
+            .iter()
+            .find(|entry| entry.key == key)
+            .map_or(0, |entry| entry.price)
+    };
+
+    let interest_event = events::InterestAccrued {
+        bank: borrow_bank.key(),
+        interest,
+        total_borrowed: borrow_bank.total_borrowed,
+        borrow_share_price: borrow_bank.borrow_share_price(),
+        timestamp: now.unix_timestamp,
+    };
+    let borrow_event = events::Borrow {
+        user: owner,
+        bank: borrow_bank.key(),
+        collateral_bank: collateral_bank.key(),
+        amount,
+        shares: change.shares,
+        share_price: borrow_bank.borrow_share_price(),
+        collateral_price: price_of(collateral_bank.key()),
+        borrow_price: price_of(borrow_bank.key()),
+        health_factor: health.health_factor(),
+    };
+    Ok((interest_event, borrow_event))
 }
@@ -0,0 +1,58 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::{events, Bank, Market, User};
+
+use super::{calculate_portfolio_health, load_health_banks};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetCollateralEnabled<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    pub bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [signer.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, User>,
+}
+
+/// Includes the user's deposit in `bank` in collateral, or excludes it so it
+/// adds no borrowing power and cannot be seized in a liquidation. Excluding
+/// a deposit can only lower the position's health, so then the remaining
+/// accounts carry the `[bank, price_update]` pair of every bank the user has
+/// a position in and the position must stay healthy.
+pub fn process_set_collateral_enabled<'info>(
+    context: Context<'_, '_, 'info, 'info, SetCollateralEnabled<'info>>,
+    enabled: bool,
+) -> Result<()> {
+    let bank_key = context.accounts.bank.key();
+    let user = &mut context.accounts.user_account;
+    let balance = user
+        .get_balance(&bank_key)
+        .ok_or(ErrorCode::InsufficientFunds)?;
+    balance.collateral_disabled = !enabled;
+
+    if !enabled {
+        let clock = Clock::get()?;
+        let banks = load_health_banks(context.remaining_accounts, &clock)?;
+        let emode = context.accounts.market.user_emode(user);
+        let health = calculate_portfolio_health(user, &banks, emode)?;
+        require!(health.is_healthy(), ErrorCode::UnhealthyPosition);
+    }
+
+    let event = events::CollateralEnabledSet {
+        user: context.accounts.signer.key(),
+        bank: bank_key,
+        enabled,
+    };
+    let ctx = &context;
+    emit_cpi!(event);
+    Ok(())
+}
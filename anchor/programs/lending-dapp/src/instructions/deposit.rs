@@ -1,19 +1,26 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{associated_token::AssociatedToken, token_interface::{Mint, TokenAccount, TokenInterface}};
 use crate::error::ErrorCode;
-use crate::{Bank, User};
+use crate::{events, Bank, Market, User};
 
-use super::transfer_tokens;
+use super::{transfer_tokens, value_portfolio};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Deposit<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
     mint: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
         seeds = [mint.key().as_ref()],
         bump,
+        constraint = !bank.config.paused @ ErrorCode::BankPaused,
     )]
     pub bank: Account<'info, Bank>,
 
@@ -41,68 +48,82 @@ pub struct Deposit<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
-pub fn process_deposit(
-    context: Context<Deposit>,
+/// Remaining accounts may start with `[bank, price_update]` pairs for every bank the user
+/// has a position in, this one included, to report the position in the Deposit event,
+/// followed by any transfer hook accounts.
+pub fn process_deposit<'info>(
+    context: Context<'_, '_, 'info, 'info, Deposit<'info>>,
     amount: u64,
 ) -> Result<()> {
-    transfer_tokens(
+    let received = transfer_tokens(
         &context.accounts.user_token_account,
-        &context.accounts.bank_token_account,
+        &mut context.accounts.bank_token_account,
         &amount,
         &context.accounts.mint,
         &context.accounts.signer,
         &context.accounts.token_program,
+        context.remaining_accounts,
     )?;
 
-    let bank = &mut context.accounts.bank;
-    let user = &mut context.accounts.user_account;
-    let decimals = context.accounts.mint.decimals ;
-    
-    if bank.total_deposits == 0 {
-        bank.total_deposits = amount;
-        bank.total_deposits_shares = amount;
-        let balance = user
-        .get_balance_or_create(&bank.key())
-        .unwrap();
-        balance.bank_address = bank.key();
-        balance.change_deposited_shares(amount)?;
-        balance.deposited += amount;
+    let (interest_event, mut event) = record_deposit(
+        &mut context.accounts.bank,
+        &mut context.accounts.user_account,
+        received,
+    )?;
+    let user = &context.accounts.user_account;
+    if let Some((price, health)) = value_portfolio(
+        context.remaining_accounts,
+        &Clock::get()?,
+        &context.accounts.bank,
+        user,
+        context.accounts.market.user_emode(user),
+    )? {
+        event.price = Some(price);
+        event.health_factor = Some(health.health_factor());
+    }
+    let ctx = &context;
+    if interest_event.interest > 0 {
+        emit_cpi!(interest_event);
+    }
+    emit_cpi!(event);
+    Ok(())
+}
 
-        balance.last_updated_deposit = Clock::get()?.unix_timestamp;
-        return Ok(());
-    } 
-    let share_price = if bank.total_deposits_shares == 0 {
-        decimals as u64 // 1.0 in fixed point
-    } else {
-        bank.total_deposits
-            .checked_mul(decimals as u64)
-            .unwrap()
-            .checked_div(bank.total_deposits_shares)
-            .unwrap()
-    };
-    let shares_to_mint = amount
-        .checked_mul(decimals as u64)
-        .unwrap()
-        .checked_div(share_price)
-        .unwrap();
+/// Books `received` tokens that already sit in the treasury as a deposit by the owner of `user`.
+/// Returns the events the caller emits; the caller fills in the Deposit event's valuation.
+pub fn record_deposit(
+    bank: &mut Account<Bank>,
+    user: &mut User,
+    received: u64,
+) -> Result<(events::InterestAccrued, events::Deposit)> {
+    user.enter_collateral(&bank.key(), bank.config.isolated)?;
+    let now = Clock::get()?.unix_timestamp;
+    let interest = bank.accrue(now)?;
+    let change = bank.deposit(received)?;
 
-bank.total_deposits = bank
-    .total_deposits
-    .checked_add(amount)
-    .ok_or(ErrorCode::Overflow)?;
-bank.total_deposits_shares = bank
-    .total_deposits_shares
-    .checked_add(shares_to_mint)
-    .ok_or(ErrorCode::Overflow)?;
+    let balance = user.get_balance_or_create(&bank.key())?;
+    balance.bank_address = bank.key();
+    balance.change_deposited_shares(change.shares)?;
+    balance.deposited = balance.deposited.checked_add(received).ok_or(ErrorCode::Overflow)?;
 
-let balance = user
-    .get_balance_or_create(&bank.key())
-    .unwrap();
-balance.bank_address = bank.key();
-balance.change_deposited_shares(shares_to_mint)?;
-balance.deposited = balance.deposited.checked_add(amount).ok_or(ErrorCode::Overflow)?;
+    balance.last_updated_deposit = now;
 
-balance.last_updated_deposit = Clock::get()?.unix_timestamp;
-Ok(())
+    let interest_event = events::InterestAccrued {
+        bank: bank.key(),
+        interest,
+        total_borrowed: bank.total_borrowed,
+        borrow_share_price: bank.borrow_share_price(),
+        timestamp: now,
+    };
 
-}
\ No newline at end of file
+    let event = events::Deposit {
+        user: user.owner,
+        bank: bank.key(),
+        amount: received,
+        shares: change.shares,
+        share_price: bank.deposit_share_price(),
+        price: None,
+        health_factor: None,
+    };
+    Ok((interest_event, event))
+}
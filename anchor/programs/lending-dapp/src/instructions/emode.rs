@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::{events, Market, User};
+
+use super::{calculate_portfolio_health, load_health_banks};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetUserEmode<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [signer.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, User>,
+}
+
+/// Opts the user into e-mode `emode_category`, or out of e-mode with zero.
+/// Switching can lower the weights the position is valued with, so the
+/// remaining accounts carry the `[bank, price_update]` pair of every bank
+/// the user has a position in and the position must stay healthy.
+pub fn process_set_user_emode<'info>(
+    context: Context<'_, '_, 'info, 'info, SetUserEmode<'info>>,
+    emode_category: u8,
+) -> Result<()> {
+    let market = &context.accounts.market;
+    require!(
+        emode_category == 0 || market.get_emode_category(emode_category).is_some(),
+        ErrorCode::InvalidEmodeCategory
+    );
+    let user = &mut context.accounts.user_account;
+    user.emode_category = emode_category;
+
+    let clock = Clock::get()?;
+    let banks = load_health_banks(context.remaining_accounts, &clock)?;
+    let health = calculate_portfolio_health(user, &banks, market.user_emode(user))?;
+    require!(health.is_healthy(), ErrorCode::UnhealthyPosition);
+
+    let event = events::UserEmodeSet {
+        user: context.accounts.signer.key(),
+        emode_category,
+    };
+    let ctx = &context;
+    emit_cpi!(event);
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT};
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::Discriminator;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::error::ErrorCode;
+use crate::{events, Bank, BPS_DENOMINATOR};
+
+use super::{amount_with_transfer_fee, transfer_from_treasury, transfer_tokens};
+
+/// Index of `bank` in the account lists of `FlashBorrow` and `FlashRepay`.
+pub const FLASH_LOAN_BANK_INDEX: usize = 2;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FlashBorrow<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [mint.key().as_ref()],
+        bump,
+        constraint = !bank.config.paused @ ErrorCode::BankPaused,
+    )]
+    pub bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump,
+    )]
+    pub bank_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: address is checked against the instructions sysvar id
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FlashRepay<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [mint.key().as_ref()],
+        bump,
+    )]
+    pub bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump,
+    )]
+    pub bank_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+pub fn calculate_flash_loan_fee(amount: u64, fee_bps: u16) -> Result<u64> {
+    let fee = (amount as u128 * fee_bps as u128).div_ceil(BPS_DENOMINATOR as u128);
+    u64::try_from(fee).map_err(|_| ErrorCode::Overflow.into())
+}
+
+pub fn process_flash_borrow<'info>(
+    context: Context<'_, '_, 'info, 'info, FlashBorrow<'info>>,
+    amount: u64,
+) -> Result<()> {
+    require!(
+        get_stack_height() == TRANSACTION_LEVEL_STACK_HEIGHT,
+        ErrorCode::FlashLoanCpiNotAllowed
+    );
+    let bank = &mut context.accounts.bank;
+    require!(bank.flash_loan_amount == 0, ErrorCode::FlashLoanInProgress);
+    require!(amount > 0, ErrorCode::InvalidFlashRepay);
+
+    // The loan is only handed out if this same transaction later repays it
+    // through `flash_repay` against the same bank and for the same amount.
+    let instructions = context.accounts.instructions.to_account_info();
+    let current_index = load_current_index_checked(&instructions)? as usize;
+    let mut index = current_index + 1;
+    loop {
+        let instruction = load_instruction_at_checked(index, &instructions)
+            .map_err(|_| ErrorCode::MissingFlashRepay)?;
+        index += 1;
+        if instruction.program_id != crate::ID || instruction.data.len() < 8 {
+            continue;
+        }
+        let (discriminator, data) = instruction.data.split_at(8);
+        if discriminator != crate::instruction::FlashRepay::DISCRIMINATOR {
+            continue;
+        }
+        let repay_bank = instruction
+            .accounts
+            .get(FLASH_LOAN_BANK_INDEX)
+            .map(|meta| meta.pubkey);
+        if repay_bank != Some(bank.key()) {
+            continue;
+        }
+        let repay = crate::instruction::FlashRepay::try_from_slice(data)
+            .map_err(|_| ErrorCode::InvalidFlashRepay)?;
+        require!(repay.amount == amount, ErrorCode::InvalidFlashRepay);
+        break;
+    }
+
+    transfer_from_treasury(
+        &context.accounts.bank_token_account,
+        context.bumps.bank_token_account,
+        &context.accounts.user_token_account,
+        amount,
+        &context.accounts.mint,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
+
+    bank.flash_loan_amount = amount;
+    let event = events::FlashBorrow {
+        user: context.accounts.signer.key(),
+        bank: bank.key(),
+        amount,
+    };
+    let ctx = &context;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn process_flash_repay<'info>(
+    context: Context<'_, '_, 'info, 'info, FlashRepay<'info>>,
+    amount: u64,
+) -> Result<()> {
+    let bank = &mut context.accounts.bank;
+    require!(
+        bank.flash_loan_amount != 0 && bank.flash_loan_amount == amount,
+        ErrorCode::InvalidFlashRepay
+    );
+    let fee = calculate_flash_loan_fee(amount, bank.config.flash_loan_fee_bps)?;
+    let amount_with_fee = amount.checked_add(fee).ok_or(ErrorCode::Overflow)?;
+    // The borrower also covers any transfer fee, so the treasury is made
+    // whole before the flash loan fee is credited.
+    let received = transfer_tokens(
+        &context.accounts.user_token_account,
+        &mut context.accounts.bank_token_account,
+        &amount_with_transfer_fee(&context.accounts.mint, amount_with_fee)?,
+        &context.accounts.mint,
+        &context.accounts.signer,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
+    require!(received >= amount_with_fee, ErrorCode::InvalidFlashRepay);
+
+    // The fee accrues to depositors by growing the assets behind their shares.
+    bank.total_deposits = bank
+        .total_deposits
+        .checked_add(fee)
+        .ok_or(ErrorCode::Overflow)?;
+    bank.flash_loan_amount = 0;
+    let event = events::FlashRepay {
+        user: context.accounts.signer.key(),
+        bank: bank.key(),
+        amount,
+        fee,
+    };
+    let ctx = &context;
+    emit_cpi!(event);
+    Ok(())
+}
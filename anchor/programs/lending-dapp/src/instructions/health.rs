@@ -0,0 +1,236 @@
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::error::ErrorCode;
+use crate::{Bank, EmodeCategory, Rounding, User, BPS_DENOMINATOR, MAX_AGE};
+
+use super::calculate_usd_value;
+
+/// A bank snapshot and its current oracle price, as used by health checks.
+pub struct HealthBank {
+    pub key: Pubkey,
+    pub bank: Bank,
+    pub price: i64,
+}
+
+#[derive(Default)]
+pub struct PortfolioHealth {
+    pub collateral_value: u128,
+    /// Collateral value weighted by each bank's `max_ltv`, or by the
+    /// e-mode category's while it applies.
+    pub weighted_collateral_value: u128,
+    /// Collateral value weighted by each bank's `liquidation_threshold`, or
+    /// by the e-mode category's while it applies.
+    pub liquidation_collateral_value: u128,
+    pub debt_value: u128,
+    /// Debt value weighted by each bank's `liability_weight_init`.
+    pub weighted_debt_value: u128,
+    /// Debt value weighted by each bank's `liability_weight_maint`.
+    pub liquidation_debt_value: u128,
+}
+
+impl PortfolioHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.weighted_debt_value <= self.weighted_collateral_value
+    }
+
+    pub fn is_liquidatable(&self) -> bool {
+        self.liquidation_debt_value > self.liquidation_collateral_value
+    }
+
+    pub fn health_factor(&self) -> f64 {
+        calculate_health_factor(
+            self.liquidation_collateral_value,
+            self.liquidation_debt_value,
+        )
+    }
+}
+
+/// Ratio of weighted collateral to weighted debt; `f64::MAX` when there is
+/// no debt.
+pub fn calculate_health_factor(weighted_collateral_value: u128, weighted_debt_value: u128) -> f64 {
+    if weighted_debt_value == 0 {
+        return f64::MAX;
+    }
+    weighted_collateral_value as f64 / weighted_debt_value as f64
+}
+
+impl HealthBank {
+    pub fn new(
+        key: Pubkey,
+        bank: &Bank,
+        price_update: &PriceUpdateV2,
+        clock: &Clock,
+    ) -> Result<Self> {
+        let price = price_update
+            .get_price_no_older_than(clock, MAX_AGE, &bank.config.oracle_feed_id)?
+            .price;
+        Ok(Self {
+            key,
+            bank: bank.clone(),
+            price,
+        })
+    }
+}
+
+/// Loads the `[bank, price_update]` pairs leading the remaining accounts.
+/// The pairs end at the first account not owned by this program, so
+/// transfer hook accounts can follow them.
+pub fn load_health_banks<'info>(
+    accounts: &'info [AccountInfo<'info>],
+    clock: &Clock,
+) -> Result<Vec<HealthBank>> {
+    let bank_count = accounts
+        .iter()
+        .step_by(2)
+        .take_while(|account| *account.owner == crate::ID)
+        .count();
+    let pairs = accounts[..accounts.len().min(bank_count * 2)].chunks_exact(2);
+    require!(
+        pairs.remainder().is_empty(),
+        ErrorCode::MissingHealthAccount
+    );
+    pairs
+        .map(|pair| {
+            let bank = Account::<Bank>::try_from(&pair[0])?;
+            let price_update = Account::<PriceUpdateV2>::try_from(&pair[1])?;
+            HealthBank::new(pair[0].key(), &bank, &price_update, clock)
+        })
+        .collect()
+}
+
+/// `load_health_banks` for instructions that hold some of the user's banks
+/// as accounts. Those replace any pair passed for the same bank, so the
+/// check sees the state the instruction has just written.
+pub fn load_health_banks_with<'info>(
+    accounts: &'info [AccountInfo<'info>],
+    clock: &Clock,
+    loaded: &[(&Account<'info, Bank>, &PriceUpdateV2)],
+) -> Result<Vec<HealthBank>> {
+    let mut banks = load_health_banks(accounts, clock)?;
+    banks.retain(|entry| !loaded.iter().any(|(bank, _)| bank.key() == entry.key));
+    for (bank, price_update) in loaded {
+        banks.push(HealthBank::new(bank.key(), bank, price_update, clock)?);
+    }
+    Ok(banks)
+}
+
+/// Values `user` for instructions that only need the `[bank, price_update]`
+/// pairs to report the position, with `bank` in the state the instruction has
+/// just written. Returns the oracle price of `bank` and the portfolio health,
+/// or `None` when the remaining accounts carry no pairs.
+pub fn value_portfolio<'info>(
+    accounts: &'info [AccountInfo<'info>],
+    clock: &Clock,
+    bank: &Account<Bank>,
+    user: &User,
+    emode: Option<&EmodeCategory>,
+) -> Result<Option<(i64, PortfolioHealth)>> {
+    let mut banks = load_health_banks(accounts, clock)?;
+    if banks.is_empty() {
+        return Ok(None);
+    }
+    let entry = banks
+        .iter_mut()
+        .find(|entry| entry.key == bank.key())
+        .ok_or(ErrorCode::MissingHealthAccount)?;
+    entry.bank = (**bank).clone();
+    let price = entry.price;
+    let health = calculate_portfolio_health(user, &banks, emode)?;
+    Ok(Some((price, health)))
+}
+
+/// Returns `emode`, the category `user` opted into, when every bank the user
+/// has a position in belongs to it. `category_of` gives a bank's category,
+/// or `None` for a bank the caller has not loaded.
+pub fn active_emode<'a>(
+    emode: Option<&'a EmodeCategory>,
+    user: &User,
+    category_of: impl Fn(&Pubkey) -> Option<u8>,
+) -> Option<&'a EmodeCategory> {
+    let category = emode?;
+    user.balances
+        .iter()
+        .filter(|balance| balance.deposited_shares > 0 || balance.borrowed_shares > 0)
+        .all(|balance| category_of(&balance.bank_address) == Some(category.id))
+        .then_some(category)
+}
+
+/// E-mode category of the bank at `key` among `banks`.
+pub fn emode_category_of(banks: &[HealthBank], key: &Pubkey) -> Option<u8> {
+    banks
+        .iter()
+        .find(|entry| entry.key == *key)
+        .map(|entry| entry.bank.config.emode_category)
+}
+
+/// `(max_ltv, liquidation_threshold)` for collateral in `bank`: the active
+/// e-mode category's, otherwise the bank's own. Zero when the bank cannot be
+/// collateral or `enabled`, see `User::is_collateral`, is false.
+pub fn collateral_weights(bank: &Bank, enabled: bool, emode: Option<&EmodeCategory>) -> (f64, f64) {
+    if !enabled || !bank.config.can_be_collateral {
+        return (0.0, 0.0);
+    }
+    emode.map_or((bank.max_ltv, bank.liquidation_threshold), |category| {
+        (category.max_ltv, category.liquidation_threshold)
+    })
+}
+
+/// Values every open position of `user`. Each bank the user has a position in
+/// must be present in `banks`. `emode` is the category the user opted into,
+/// see `Market::user_emode`.
+pub fn calculate_portfolio_health(
+    user: &User,
+    banks: &[HealthBank],
+    emode: Option<&EmodeCategory>,
+) -> Result<PortfolioHealth> {
+    let emode = active_emode(emode, user, |key| emode_category_of(banks, key));
+    let mut health = PortfolioHealth::default();
+    for balance in user.balances.iter() {
+        if balance.deposited_shares == 0 && balance.borrowed_shares == 0 {
+            continue;
+        }
+        let entry = banks
+            .iter()
+            .find(|entry| entry.key == balance.bank_address)
+            .ok_or(ErrorCode::MissingHealthAccount)?;
+        let bank = &entry.bank;
+        if balance.deposited_shares > 0 {
+            let deposited =
+                bank.deposit_shares_to_amount(balance.deposited_shares, Rounding::Down)?;
+            let value = calculate_usd_value(deposited, entry.price, bank.mint_decimals)?;
+            let (max_ltv, liquidation_threshold) =
+                collateral_weights(bank, !balance.collateral_disabled, emode);
+            health.collateral_value += value;
+            health.weighted_collateral_value += weigh(value, max_ltv, Rounding::Down)?;
+            health.liquidation_collateral_value +=
+                weigh(value, liquidation_threshold, Rounding::Down)?;
+        }
+        if balance.borrowed_shares > 0 {
+            let borrowed = bank.borrow_shares_to_amount(balance.borrowed_shares, Rounding::Up)?;
+            let value = calculate_usd_value(borrowed, entry.price, bank.mint_decimals)?;
+            health.debt_value += value;
+            health.weighted_debt_value += weigh(value, bank.liability_weight_init, Rounding::Up)?;
+            health.liquidation_debt_value +=
+                weigh(value, bank.liability_weight_maint, Rounding::Up)?;
+        }
+    }
+    Ok(health)
+}
+
+/// A collateral or liability weight in basis points, to the nearest one, so
+/// values are weighed in integer math.
+pub fn weight_bps(weight: f64) -> u128 {
+    (weight * BPS_DENOMINATOR as f64).round() as u128
+}
+
+/// `value` times `weight`. Collateral rounds down and debt up.
+fn weigh(value: u128, weight: f64, rounding: Rounding) -> Result<u128> {
+    let product = value
+        .checked_mul(weight_bps(weight))
+        .ok_or(ErrorCode::Overflow)?;
+    Ok(match rounding {
+        Rounding::Down => product / BPS_DENOMINATOR as u128,
+        Rounding::Up => product.div_ceil(BPS_DENOMINATOR as u128),
+    })
+}
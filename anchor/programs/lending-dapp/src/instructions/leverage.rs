@@ -0,0 +1,232 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::error::ErrorCode;
+use crate::{Bank, Market, User, BPS_DENOMINATOR, MAX_AGE};
+
+use super::{
+    calculate_token_amount, calculate_usd_value, record_borrow, record_deposit, swap_via_cpi,
+    transfer_from_treasury, transfer_tokens,
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Leverage<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+    pub borrow_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [collateral_mint.key().as_ref()],
+        bump,
+        constraint = !collateral_bank.config.paused @ ErrorCode::BankPaused,
+    )]
+    pub collateral_bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [borrow_mint.key().as_ref()],
+        bump,
+        constraint = !borrow_bank.config.paused @ ErrorCode::BankPaused,
+        constraint = borrow_bank.config.borrowing_enabled @ ErrorCode::BorrowingDisabled,
+    )]
+    pub borrow_bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [b"treasury", collateral_mint.key().as_ref()],
+        bump,
+    )]
+    pub collateral_bank_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"treasury", borrow_mint.key().as_ref()],
+        bump,
+    )]
+    pub borrow_bank_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [signer.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, User>,
+    #[account(
+        mut,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_collateral_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = borrow_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_borrow_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub collateral_price_update: Account<'info, PriceUpdateV2>,
+    pub borrow_price_update: Account<'info, PriceUpdateV2>,
+    /// CHECK: checked against the market's swap program whitelist
+    #[account(
+        executable,
+        constraint = market.swap_programs.contains(&swap_program.key()) @ ErrorCode::SwapProgramNotAllowed,
+    )]
+    pub swap_program: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+/// Opens a leveraged position in one instruction: the debt asset is flash
+/// borrowed out of the borrow treasury, swapped into collateral through the
+/// whitelisted swap program, deposited together with the user's own
+/// `collateral_amount`, and the flash loan is then booked as the user's debt
+/// once the whole portfolio passes the health check.
+///
+/// The flash loan is never repaid within the instruction: it becomes regular
+/// debt that pays borrow interest, so no flash loan fee is charged.
+///
+/// `target_leverage_bps` is the total exposure relative to `collateral_amount`
+/// (e.g. 30_000 for 3x), and `max_slippage_bps` bounds the swap output against
+/// the oracle-implied amount.
+///
+/// Remaining accounts start with the `swap_accounts_len` accounts of the swap
+/// instruction, followed by `[bank, price_update]` pairs for every other bank
+/// the user has a position in.
+pub fn process_leverage<'info>(
+    context: Context<'_, '_, 'info, 'info, Leverage<'info>>,
+    collateral_amount: u64,
+    target_leverage_bps: u64,
+    max_slippage_bps: u16,
+    swap_data: Vec<u8>,
+    swap_accounts_len: u8,
+) -> Result<()> {
+    require!(
+        target_leverage_bps > BPS_DENOMINATOR && max_slippage_bps as u64 <= BPS_DENOMINATOR,
+        ErrorCode::InvalidLeverage
+    );
+    let now = Clock::get()?;
+    let collateral_bank_key = context.accounts.collateral_bank.key();
+    let borrow_bank_key = context.accounts.borrow_bank.key();
+    require_keys_neq!(
+        collateral_bank_key,
+        borrow_bank_key,
+        ErrorCode::InvalidCollateralMint
+    );
+    require!(
+        swap_accounts_len as usize <= context.remaining_accounts.len(),
+        ErrorCode::InvalidSwapAccounts
+    );
+    let (swap_accounts, health_accounts) = context
+        .remaining_accounts
+        .split_at(swap_accounts_len as usize);
+
+    let collateral_decimals = context.accounts.collateral_mint.decimals;
+    let borrow_decimals = context.accounts.borrow_mint.decimals;
+    let collateral_price = context
+        .accounts
+        .collateral_price_update
+        .get_price_no_older_than(
+            &now,
+            MAX_AGE,
+            &context.accounts.collateral_bank.config.oracle_feed_id,
+        )?
+        .price;
+    let borrow_price = context
+        .accounts
+        .borrow_price_update
+        .get_price_no_older_than(
+            &now,
+            MAX_AGE,
+            &context.accounts.borrow_bank.config.oracle_feed_id,
+        )?
+        .price;
+
+    // Size the debt so that total exposure reaches the target leverage, and
+    // derive the minimum swap output from the oracle prices.
+    let collateral_value =
+        calculate_usd_value(collateral_amount, collateral_price, collateral_decimals)?;
+    let debt_value = collateral_value
+        .checked_mul((target_leverage_bps - BPS_DENOMINATOR) as u128)
+        .ok_or(ErrorCode::Overflow)?
+        / BPS_DENOMINATOR as u128;
+    let borrow_amount = calculate_token_amount(debt_value, borrow_price, borrow_decimals)?;
+    require!(borrow_amount > 0, ErrorCode::InvalidLeverage);
+    let expected_out = calculate_token_amount(debt_value, collateral_price, collateral_decimals)?;
+    let min_out = (expected_out as u128 * (BPS_DENOMINATOR - max_slippage_bps as u64) as u128
+        / BPS_DENOMINATOR as u128) as u64;
+
+    transfer_from_treasury(
+        &context.accounts.borrow_bank_token_account,
+        context.bumps.borrow_bank_token_account,
+        &context.accounts.user_borrow_token_account,
+        borrow_amount,
+        &context.accounts.borrow_mint,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
+
+    let amount_out = swap_via_cpi(
+        &context.accounts.swap_program.to_account_info(),
+        swap_accounts,
+        swap_data,
+        &mut context.accounts.user_collateral_token_account,
+        min_out,
+    )?;
+
+    let deposit_amount = collateral_amount
+        .checked_add(amount_out)
+        .ok_or(ErrorCode::Overflow)?;
+    let received = transfer_tokens(
+        &context.accounts.user_collateral_token_account,
+        &mut context.accounts.collateral_bank_token_account,
+        &deposit_amount,
+        &context.accounts.collateral_mint,
+        &context.accounts.signer,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
+    let (collateral_interest_event, mut deposit_event) = record_deposit(
+        &mut context.accounts.collateral_bank,
+        &mut context.accounts.user_account,
+        received,
+    )?;
+
+    // Close the flash loan by booking it as regular debt.
+    let emode = context
+        .accounts
+        .market
+        .user_emode(&context.accounts.user_account)
+        .cloned();
+    let (borrow_interest_event, borrow_event) = record_borrow(
+        &mut context.accounts.borrow_bank,
+        &mut context.accounts.collateral_bank,
+        &mut context.accounts.user_account,
+        emode.as_ref(),
+        &context.accounts.collateral_price_update,
+        &context.accounts.borrow_price_update,
+        health_accounts,
+        borrow_amount,
+    )?;
+    deposit_event.price = Some(collateral_price);
+    deposit_event.health_factor = Some(borrow_event.health_factor);
+
+    let ctx = &context;
+    for interest_event in [collateral_interest_event, borrow_interest_event] {
+        if interest_event.interest > 0 {
+            emit_cpi!(interest_event);
+        }
+    }
+    emit_cpi!(deposit_event);
+    emit_cpi!(borrow_event);
+    Ok(())
+}
@@ -1,19 +1,31 @@
 use crate::error::ErrorCode;
-use crate::{Bank, User, MAX_AGE};
+use crate::{events, Bank, Market, Rounding, User, BPS_DENOMINATOR};
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
-use super::{calculate_accrued_interest, transfer_tokens};
+use super::{
+    amount_with_transfer_fee, calculate_portfolio_health, calculate_token_amount,
+    calculate_usd_value, load_health_banks, transfer_from_treasury, transfer_tokens, HealthBank,
+};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Liquidate<'info> {
     #[account(mut)]
     pub liquidator: Signer<'info>,
-    pub price_update: Account<'info, PriceUpdateV2>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub borrower_account: Account<'info, User>,
+    pub collateral_price_update: Account<'info, PriceUpdateV2>,
+    pub borrowed_price_update: Account<'info, PriceUpdateV2>,
     pub collateral_mint: InterfaceAccount<'info, Mint>,
     pub borrowed_mint: InterfaceAccount<'info, Mint>,
 
@@ -24,9 +36,9 @@ pub struct Liquidate<'info> {
     )]
     pub collateral_bank: Account<'info, Bank>,
     #[account(
-    mut,
-    seeds = [borrowed_mint.key().as_ref()],
-    bump,
+        mut,
+        seeds = [borrowed_mint.key().as_ref()],
+        bump,
     )]
     pub borrowed_bank: Account<'info, Bank>,
     #[account(
@@ -42,13 +54,8 @@ pub struct Liquidate<'info> {
     )]
     pub borrowed_bank_token_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
-        mut,
-        seeds = [liquidator.key().as_ref()],
-        bump,
-    )]
-    pub liquidator_account: Account<'info, User>,
-    #[account(
-        mut,
+        init_if_needed,
+        payer = liquidator,
         associated_token::mint = collateral_mint,
         associated_token::authority = liquidator,
         associated_token::token_program = token_program,
@@ -66,102 +73,215 @@ pub struct Liquidate<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
-pub fn process_liquidate(context: Context<Liquidate>) -> Result<()> {
-    let collateral_bank = &mut context.accounts.collateral_bank;
-    let borrowed_bank = &mut context.accounts.borrowed_bank;
-    let liquidator_account = &mut context.accounts.liquidator_account;
-    let price_update = &mut context.accounts.price_update;
-    let borrowed_price = price_update.get_price_no_older_than(
-        &Clock::get()?,
-        MAX_AGE,
-        &borrowed_bank.config.oracle_feed_id,
+/// Sizes a liquidation of a borrower owing `debt` in `borrowed_bank` and
+/// holding `collateral` in `collateral_bank`, returning
+/// `(repay_amount, seize_amount)`. The repayment is `close_factor` of the
+/// debt, shrunk when the collateral cannot cover it plus the bonus.
+pub fn calculate_liquidation_amounts(
+    collateral_bank: &Bank,
+    collateral_price: i64,
+    collateral: u64,
+    borrowed_bank: &Bank,
+    borrowed_price: i64,
+    debt: u64,
+) -> Result<(u64, u64)> {
+    let bonus_bps = BPS_DENOMINATOR + collateral_bank.liquidation_bonus;
+    let repay_amount =
+        (debt as u128 * borrowed_bank.close_factor as u128 / BPS_DENOMINATOR as u128) as u64;
+    let repay_value =
+        calculate_usd_value(repay_amount, borrowed_price, borrowed_bank.mint_decimals)?;
+    let seize_amount = calculate_token_amount(
+        repay_value * bonus_bps as u128 / BPS_DENOMINATOR as u128,
+        collateral_price,
+        collateral_bank.mint_decimals,
     )?;
-    let collateral_price = price_update.get_price_no_older_than(
-        &Clock::get()?,
-        MAX_AGE,
-        &collateral_bank.config.oracle_feed_id,
+    if seize_amount <= collateral {
+        return Ok((repay_amount, seize_amount));
+    }
+    let collateral_value =
+        calculate_usd_value(collateral, collateral_price, collateral_bank.mint_decimals)?;
+    let repay_amount = calculate_token_amount(
+        collateral_value * BPS_DENOMINATOR as u128 / bonus_bps as u128,
+        borrowed_price,
+        borrowed_bank.mint_decimals,
     )?;
-    let total_collateral: u64;
-    let total_borrowed: u64;
-    let collateral_mint = context.accounts.collateral_mint.to_account_info().key();
-    let borrowed_mint = context.accounts.borrowed_mint.to_account_info().key();
-    let liquidator_collat_balance: &mut crate::Balance =
-        liquidator_account.get_balance(&collateral_mint).unwrap();
+    Ok((repay_amount, collateral))
+}
+
+/// Repays up to `close_factor` of the borrower's debt in the borrowed bank
+/// and pays the liquidator the same value plus `liquidation_bonus` out of the
+/// borrower's collateral. Only positions whose debt exceeds their
+/// liquidation-threshold weighted collateral can be liquidated.
+///
+/// Remaining accounts are `[bank, price_update]` pairs for every other bank
+/// the borrower has a position in.
+pub fn process_liquidate<'info>(
+    context: Context<'_, '_, 'info, 'info, Liquidate<'info>>,
+) -> Result<()> {
+    let now = Clock::get()?;
+    let collateral_bank_key = context.accounts.collateral_bank.key();
+    let borrowed_bank_key = context.accounts.borrowed_bank.key();
+    require_keys_neq!(
+        collateral_bank_key,
+        borrowed_bank_key,
+        ErrorCode::InvalidCollateralMint
+    );
+    let interest = context.accounts.borrowed_bank.accrue(now.unix_timestamp)?;
+    let collateral_interest = context
+        .accounts
+        .collateral_bank
+        .accrue(now.unix_timestamp)?;
 
-    let new_collateral_amount = calculate_accrued_interest(
-        liquidator_collat_balance.deposited,
-        collateral_bank.interest_rate,
-        liquidator_collat_balance.last_updated_deposit,
+    let collateral_entry = HealthBank::new(
+        collateral_bank_key,
+        &context.accounts.collateral_bank,
+        &context.accounts.collateral_price_update,
+        &now,
+    )?;
+    let borrowed_entry = HealthBank::new(
+        borrowed_bank_key,
+        &context.accounts.borrowed_bank,
+        &context.accounts.borrowed_price_update,
+        &now,
+    )?;
+    let collateral_price = collateral_entry.price;
+    let borrowed_price = borrowed_entry.price;
+    let mut banks = load_health_banks(context.remaining_accounts, &now)?;
+    banks.retain(|entry| entry.key != collateral_bank_key && entry.key != borrowed_bank_key);
+    banks.push(collateral_entry);
+    banks.push(borrowed_entry);
+    let borrower = &context.accounts.borrower_account;
+    let health = calculate_portfolio_health(
+        borrower,
+        &banks,
+        context.accounts.market.user_emode(borrower),
     )?;
-    total_collateral = collateral_price.price as u64 * new_collateral_amount;
-    let liquidator_borrow_balance = liquidator_account.get_balance(&borrowed_mint).unwrap();
-    let new_borrowed_amount = calculate_accrued_interest(
-        liquidator_borrow_balance.borrowed,
-        borrowed_bank.interest_rate,
-        liquidator_borrow_balance.last_updated_borrow,
+    require!(health.is_liquidatable(), ErrorCode::NotUnderCollateralized);
+
+    let collateral_bank = &mut context.accounts.collateral_bank;
+    let borrowed_bank = &mut context.accounts.borrowed_bank;
+    let borrower = &mut context.accounts.borrower_account;
+    let borrowed_shares = borrower
+        .get_balance(&borrowed_bank_key)
+        .map_or(0, |balance| balance.borrowed_shares);
+    let deposited_shares = borrower
+        .get_balance(&collateral_bank_key)
+        .map_or(0, |balance| balance.deposited_shares);
+    require!(
+        borrowed_shares > 0 && deposited_shares > 0,
+        ErrorCode::NothingToLiquidate
+    );
+    require!(
+        borrower.is_collateral(&collateral_bank_key),
+        ErrorCode::CollateralDisabled
+    );
+    let debt = borrowed_bank.borrow_shares_to_amount(borrowed_shares, Rounding::Up)?;
+    let collateral = collateral_bank.deposit_shares_to_amount(deposited_shares, Rounding::Down)?;
+
+    let (repay_amount, seize_amount) = calculate_liquidation_amounts(
+        collateral_bank,
+        collateral_price,
+        collateral,
+        borrowed_bank,
+        borrowed_price,
+        debt,
     )?;
-    total_borrowed = borrowed_price.price as u64 * new_borrowed_amount;
+    require!(
+        repay_amount > 0 && seize_amount > 0,
+        ErrorCode::NothingToLiquidate
+    );
+
+    let repaid = borrowed_bank.repay(repay_amount, borrowed_shares)?;
+    let seized = collateral_bank.withdraw(seize_amount, deposited_shares)?;
+
+    let borrow_balance = borrower
+        .get_balance(&borrowed_bank_key)
+        .ok_or(ErrorCode::NothingToLiquidate)?;
+    borrow_balance.borrowed_shares -= repaid.shares;
+    borrow_balance.borrowed = borrow_balance.borrowed.saturating_sub(repay_amount);
+
+    let collateral_balance = borrower
+        .get_balance(&collateral_bank_key)
+        .ok_or(ErrorCode::NothingToLiquidate)?;
+    collateral_balance.deposited_shares -= seized.shares;
+    collateral_balance.deposited = collateral_balance.deposited.saturating_sub(seize_amount);
+    if borrower.isolated_bank == collateral_bank_key {
+        collateral_bank.remove_isolated_debt(borrowed_bank, repay_amount)?;
+        borrower.release_isolation();
+    }
 
-    let health_factor = total_collateral as f64 * collateral_bank.liquidation_threshold as f64
-        / total_borrowed as f64;
-    if health_factor >= 1.0 {
-        return Err(ErrorCode::NotUnderCollateralized.into());
+    // Report the health the liquidation left the borrower with.
+    for entry in banks.iter_mut() {
+        if entry.key == collateral_bank_key {
+            entry.bank = (**collateral_bank).clone();
+        } else if entry.key == borrowed_bank_key {
+            entry.bank = (**borrowed_bank).clone();
+        }
     }
-    let liquidation_amount = total_borrowed
-        .checked_mul(borrowed_bank.close_factor)
-        .unwrap();
-    transfer_tokens(
+    let health_factor = calculate_portfolio_health(
+        borrower,
+        &banks,
+        context.accounts.market.user_emode(borrower),
+    )?
+    .health_factor();
+
+    // The liquidator covers any transfer fee so the bank nets the full
+    // repayment the seizure was sized against.
+    let repay_with_fee = amount_with_transfer_fee(&context.accounts.borrowed_mint, repay_amount)?;
+    let received = transfer_tokens(
         &context.accounts.liquidator_borrowed_token_account,
-        &context.accounts.borrowed_bank_token_account,
-        &liquidation_amount,
+        &mut context.accounts.borrowed_bank_token_account,
+        &repay_with_fee,
         &context.accounts.borrowed_mint,
         &context.accounts.liquidator,
         &context.accounts.token_program,
+        context.remaining_accounts,
     )?;
-    let liquidator_returned_amount = liquidation_amount
-        .checked_mul(collateral_bank.liquidation_bonus)
-        .unwrap()
-        .checked_add(liquidation_amount)
-        .unwrap();
+    require!(received >= repay_amount, ErrorCode::InsufficientFunds);
 
-    let seeds = &[
-        b"treasury",
-        context
-            .accounts
-            .collateral_mint
-            .to_account_info()
-            .key
-            .as_ref(),
-        &[context.bumps.collateral_bank_token_account],
-    ];
-    let signer_seeds = [&seeds[..]];
-    let accounts = TransferChecked {
-        from: context
-            .accounts
-            .collateral_bank_token_account
-            .to_account_info(),
-        to: context
-            .accounts
-            .liquidator_collateral_token_account
-            .to_account_info(),
-        mint: context.accounts.collateral_mint.to_account_info(),
-        authority: context
-            .accounts
-            .collateral_bank_token_account
-            .to_account_info(),
-    };
-    let cpi_context = CpiContext::new_with_signer(
-        context
-            .accounts
-            .collateral_bank_token_account
-            .to_account_info(),
-        accounts,
-        &signer_seeds,
-    );
-    transfer_checked(
-        cpi_context,
-        liquidator_returned_amount,
-        context.accounts.collateral_mint.decimals,
+    transfer_from_treasury(
+        &context.accounts.collateral_bank_token_account,
+        context.bumps.collateral_bank_token_account,
+        &context.accounts.liquidator_collateral_token_account,
+        seize_amount,
+        &context.accounts.collateral_mint,
+        &context.accounts.token_program,
+        context.remaining_accounts,
     )?;
+
+    let collateral_bank = &context.accounts.collateral_bank;
+    let collateral_interest_event = events::InterestAccrued {
+        bank: collateral_bank_key,
+        interest: collateral_interest,
+        total_borrowed: collateral_bank.total_borrowed,
+        borrow_share_price: collateral_bank.borrow_share_price(),
+        timestamp: now.unix_timestamp,
+    };
+    let borrowed_bank = &context.accounts.borrowed_bank;
+    let interest_event = events::InterestAccrued {
+        bank: borrowed_bank_key,
+        interest,
+        total_borrowed: borrowed_bank.total_borrowed,
+        borrow_share_price: borrowed_bank.borrow_share_price(),
+        timestamp: now.unix_timestamp,
+    };
+    let event = events::Liquidate {
+        liquidator: context.accounts.liquidator.key(),
+        borrower: context.accounts.borrower_account.owner,
+        collateral_bank: collateral_bank_key,
+        borrowed_bank: borrowed_bank_key,
+        repaid_amount: repay_amount,
+        seized_amount: seize_amount,
+        collateral_price,
+        borrowed_price,
+        health_factor,
+    };
+    let ctx = &context;
+    for interest_event in [collateral_interest_event, interest_event] {
+        if interest_event.interest > 0 {
+            emit_cpi!(interest_event);
+        }
+    }
+    emit_cpi!(event);
     Ok(())
 }
@@ -7,6 +7,9 @@ pub use deposit::*;
 pub mod shared;
 pub use shared::*;
 
+pub mod health;
+pub use health::*;
+
 pub mod withdraw;
 pub use withdraw::*;
 
@@ -18,3 +21,33 @@ pub use repay::*;
 
 pub mod liquidate;
 pub use liquidate::*;
+
+pub mod flash_loan;
+pub use flash_loan::*;
+
+pub mod repay_with_collateral;
+pub use repay_with_collateral::*;
+
+pub mod leverage;
+pub use leverage::*;
+
+pub mod swap_collateral;
+pub use swap_collateral::*;
+
+pub mod view;
+pub use view::*;
+
+pub mod refresh;
+pub use refresh::*;
+
+pub mod receipt;
+pub use receipt::*;
+
+pub mod native_sol;
+pub use native_sol::*;
+
+pub mod emode;
+pub use emode::*;
+
+pub mod collateral;
+pub use collateral::*;
@@ -0,0 +1,332 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, System, Transfer};
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_interface::{
+    self, CloseAccount, Mint, SyncNative, TokenAccount, TokenInterface,
+};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::error::ErrorCode;
+use crate::{Bank, Market, User};
+
+use super::{
+    record_borrow, record_deposit, record_withdraw, transfer_from_treasury, transfer_tokens,
+    value_portfolio,
+};
+
+/// Native SOL counterparts of `deposit`, `withdraw` and `borrow`. Each one
+/// creates a temporary wSOL account at `[b"wrapped_sol", signer]`, moves the
+/// lamports through it and closes it again before returning, so the signer
+/// never holds wrapped SOL and gets the account rent back.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DepositSol<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    #[account(address = native_mint::ID @ ErrorCode::NotNativeMint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [mint.key().as_ref()],
+        bump,
+        constraint = !bank.config.paused @ ErrorCode::BankPaused,
+    )]
+    pub bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump,
+    )]
+    pub bank_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [signer.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, User>,
+    #[account(
+        init,
+        payer = signer,
+        seeds = [b"wrapped_sol", signer.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = signer,
+        token::token_program = token_program,
+    )]
+    pub wrapped_sol_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawSol<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    #[account(address = native_mint::ID @ ErrorCode::NotNativeMint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [mint.key().as_ref()],
+        bump,
+    )]
+    pub bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump,
+    )]
+    pub bank_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [signer.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, User>,
+    #[account(
+        init,
+        payer = signer,
+        seeds = [b"wrapped_sol", signer.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = signer,
+        token::token_program = token_program,
+    )]
+    pub wrapped_sol_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct BorrowSol<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = native_mint::ID @ ErrorCode::NotNativeMint)]
+    pub borrow_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [borrow_mint.key().as_ref()],
+        bump,
+        constraint = !borrow_bank.config.paused @ ErrorCode::BankPaused,
+        constraint = borrow_bank.config.borrowing_enabled @ ErrorCode::BorrowingDisabled,
+    )]
+    pub borrow_bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [b"treasury", borrow_mint.key().as_ref()],
+        bump,
+    )]
+    pub bank_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [signer.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, User>,
+    #[account(
+        mut,
+        seeds = [collateral_mint.key().as_ref()],
+        bump,
+    )]
+    pub collateral_bank: Account<'info, Bank>,
+    #[account(
+        init,
+        payer = signer,
+        seeds = [b"wrapped_sol", signer.key().as_ref()],
+        bump,
+        token::mint = borrow_mint,
+        token::authority = signer,
+        token::token_program = token_program,
+    )]
+    pub wrapped_sol_account: InterfaceAccount<'info, TokenAccount>,
+    pub collateral_price_update: Account<'info, PriceUpdateV2>,
+    pub borrow_price_update: Account<'info, PriceUpdateV2>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Remaining accounts may hold `[bank, price_update]` pairs for every bank the
+/// user has a position in, this one included, to report the position in the
+/// Deposit event.
+pub fn process_deposit_sol<'info>(
+    context: Context<'_, '_, 'info, 'info, DepositSol<'info>>,
+    amount: u64,
+) -> Result<()> {
+    system_program::transfer(
+        CpiContext::new(
+            context.accounts.system_program.to_account_info(),
+            Transfer {
+                from: context.accounts.signer.to_account_info(),
+                to: context.accounts.wrapped_sol_account.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+    token_interface::sync_native(CpiContext::new(
+        context.accounts.token_program.to_account_info(),
+        SyncNative {
+            account: context.accounts.wrapped_sol_account.to_account_info(),
+        },
+    ))?;
+    context.accounts.wrapped_sol_account.reload()?;
+
+    let received = transfer_tokens(
+        &context.accounts.wrapped_sol_account,
+        &mut context.accounts.bank_token_account,
+        &amount,
+        &context.accounts.mint,
+        &context.accounts.signer,
+        &context.accounts.token_program,
+        &[],
+    )?;
+    close_wrapped_sol(
+        &context.accounts.wrapped_sol_account,
+        &context.accounts.signer,
+        &context.accounts.token_program,
+    )?;
+
+    let (interest_event, mut event) = record_deposit(
+        &mut context.accounts.bank,
+        &mut context.accounts.user_account,
+        received,
+    )?;
+    let user = &context.accounts.user_account;
+    if let Some((price, health)) = value_portfolio(
+        context.remaining_accounts,
+        &Clock::get()?,
+        &context.accounts.bank,
+        user,
+        context.accounts.market.user_emode(user),
+    )? {
+        event.price = Some(price);
+        event.health_factor = Some(health.health_factor());
+    }
+    let ctx = &context;
+    if interest_event.interest > 0 {
+        emit_cpi!(interest_event);
+    }
+    emit_cpi!(event);
+    Ok(())
+}
+
+/// Remaining accounts are `[bank, price_update]` pairs for every bank the
+/// user has a position in, this one included, while the user has debt.
+pub fn process_withdraw_sol<'info>(
+    context: Context<'_, '_, 'info, 'info, WithdrawSol<'info>>,
+    amount: u64,
+) -> Result<()> {
+    let emode = context
+        .accounts
+        .market
+        .user_emode(&context.accounts.user_account)
+        .cloned();
+    let (interest_event, event) = record_withdraw(
+        &mut context.accounts.bank,
+        &mut context.accounts.user_account,
+        emode.as_ref(),
+        context.remaining_accounts,
+        amount,
+    )?;
+
+    transfer_from_treasury(
+        &context.accounts.bank_token_account,
+        context.bumps.bank_token_account,
+        &context.accounts.wrapped_sol_account,
+        amount,
+        &context.accounts.mint,
+        &context.accounts.token_program,
+        &[],
+    )?;
+    close_wrapped_sol(
+        &context.accounts.wrapped_sol_account,
+        &context.accounts.signer,
+        &context.accounts.token_program,
+    )?;
+
+    let ctx = &context;
+    if interest_event.interest > 0 {
+        emit_cpi!(interest_event);
+    }
+    emit_cpi!(event);
+    Ok(())
+}
+
+/// Remaining accounts are `[bank, price_update]` pairs for every other bank
+/// the user has a position in.
+pub fn process_borrow_sol<'info>(
+    context: Context<'_, '_, 'info, 'info, BorrowSol<'info>>,
+    amount: u64,
+) -> Result<()> {
+    let emode = context
+        .accounts
+        .market
+        .user_emode(&context.accounts.user_account)
+        .cloned();
+    let (interest_event, borrow_event) = record_borrow(
+        &mut context.accounts.borrow_bank,
+        &mut context.accounts.collateral_bank,
+        &mut context.accounts.user_account,
+        emode.as_ref(),
+        &context.accounts.collateral_price_update,
+        &context.accounts.borrow_price_update,
+        context.remaining_accounts,
+        amount,
+    )?;
+
+    transfer_from_treasury(
+        &context.accounts.bank_token_account,
+        context.bumps.bank_token_account,
+        &context.accounts.wrapped_sol_account,
+        amount,
+        &context.accounts.borrow_mint,
+        &context.accounts.token_program,
+        &[],
+    )?;
+    close_wrapped_sol(
+        &context.accounts.wrapped_sol_account,
+        &context.accounts.signer,
+        &context.accounts.token_program,
+    )?;
+
+    let ctx = &context;
+    if interest_event.interest > 0 {
+        emit_cpi!(interest_event);
+    }
+    emit_cpi!(borrow_event);
+    Ok(())
+}
+
+/// Closes the temporary wSOL account into the signer. Closing a native
+/// account releases its whole lamport balance, wrapped amount and rent alike,
+/// which is what unwraps the SOL.
+fn close_wrapped_sol<'info>(
+    wrapped_sol_account: &InterfaceAccount<'info, TokenAccount>,
+    signer: &Signer<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    token_interface::close_account(CpiContext::new(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: wrapped_sol_account.to_account_info(),
+            destination: signer.to_account_info(),
+            authority: signer.to_account_info(),
+        },
+    ))
+}
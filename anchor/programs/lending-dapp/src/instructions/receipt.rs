@@ -0,0 +1,441 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{burn, mint_to, Burn, Mint, MintTo, TokenAccount, TokenInterface},
+};
+
+use crate::error::ErrorCode;
+use crate::{events, Bank, Market, Rounding, User};
+
+use super::{transfer_from_treasury, transfer_tokens, value_portfolio};
+
+#[derive(Accounts)]
+pub struct InitReceiptMint<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [mint.key().as_ref()],
+        bump,
+        constraint = bank.authority == signer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub bank: Account<'info, Bank>,
+    #[account(
+        init,
+        payer = signer,
+        mint::decimals = mint.decimals,
+        mint::authority = receipt_mint,
+        mint::token_program = token_program,
+        seeds = [b"receipt", mint.key().as_ref()],
+        bump,
+    )]
+    pub receipt_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MintReceipt<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [mint.key().as_ref()],
+        bump,
+        constraint = !bank.config.paused @ ErrorCode::BankPaused,
+        constraint = bank.receipt_mint == receipt_mint.key() @ ErrorCode::ReceiptsDisabled,
+    )]
+    pub bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump,
+    )]
+    pub bank_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"receipt", mint.key().as_ref()],
+        bump,
+    )]
+    pub receipt_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = receipt_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_receipt_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Redeem<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [mint.key().as_ref()],
+        bump,
+        constraint = bank.receipt_mint == receipt_mint.key() @ ErrorCode::ReceiptsDisabled,
+    )]
+    pub bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump,
+    )]
+    pub bank_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"receipt", mint.key().as_ref()],
+        bump,
+    )]
+    pub receipt_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = receipt_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_receipt_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DepositReceiptAsCollateral<'info> {
+    pub signer: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        seeds = [mint.key().as_ref()],
+        bump,
+        constraint = bank.receipt_mint == receipt_mint.key() @ ErrorCode::ReceiptsDisabled,
+    )]
+    pub bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [b"receipt", mint.key().as_ref()],
+        bump,
+    )]
+    pub receipt_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = receipt_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_receipt_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [signer.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, User>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawCollateralAsReceipt<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        seeds = [mint.key().as_ref()],
+        bump,
+        constraint = !bank.config.paused @ ErrorCode::BankPaused,
+        constraint = bank.receipt_mint == receipt_mint.key() @ ErrorCode::ReceiptsDisabled,
+    )]
+    pub bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [b"receipt", mint.key().as_ref()],
+        bump,
+    )]
+    pub receipt_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = receipt_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_receipt_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [signer.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, User>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+/// Creates the bank's receipt mint. The mint is its own authority, so only
+/// this program can issue receipts, and it uses the underlying decimals so a
+/// fresh bank prices one receipt at one token.
+pub fn process_init_receipt_mint(context: Context<InitReceiptMint>) -> Result<()> {
+    context.accounts.bank.receipt_mint = context.accounts.receipt_mint.key();
+    Ok(())
+}
+
+/// Deposits `amount` tokens and mints the resulting shares as receipt tokens
+/// to the signer instead of crediting a `User` position.
+pub fn process_mint_receipt<'info>(
+    context: Context<'_, '_, 'info, 'info, MintReceipt<'info>>,
+    amount: u64,
+) -> Result<()> {
+    let received = transfer_tokens(
+        &context.accounts.user_token_account,
+        &mut context.accounts.bank_token_account,
+        &amount,
+        &context.accounts.mint,
+        &context.accounts.signer,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
+
+    let bank = &mut context.accounts.bank;
+    let now = Clock::get()?.unix_timestamp;
+    let interest = bank.accrue(now)?;
+    let change = bank.deposit(received)?;
+
+    mint_receipts(
+        &context.accounts.mint,
+        &context.accounts.receipt_mint,
+        context.bumps.receipt_mint,
+        &context.accounts.user_receipt_account,
+        &context.accounts.token_program,
+        change.shares,
+    )?;
+
+    let interest_event = events::InterestAccrued {
+        bank: bank.key(),
+        interest,
+        total_borrowed: bank.total_borrowed,
+        borrow_share_price: bank.borrow_share_price(),
+        timestamp: now,
+    };
+    let event = events::ReceiptMinted {
+        user: context.accounts.signer.key(),
+        bank: bank.key(),
+        amount: received,
+        shares: change.shares,
+        share_price: bank.deposit_share_price(),
+    };
+    let ctx = &context;
+    if interest > 0 {
+        emit_cpi!(interest_event);
+    }
+    emit_cpi!(event);
+    Ok(())
+}
+
+/// Burns `shares` receipt tokens and pays out the tokens they are worth,
+/// rounded down. Fails when that rounds to nothing.
+pub fn process_redeem<'info>(
+    context: Context<'_, '_, 'info, 'info, Redeem<'info>>,
+    shares: u64,
+) -> Result<()> {
+    require!(shares > 0, ErrorCode::ZeroShares);
+    let bank = &mut context.accounts.bank;
+    let now = Clock::get()?.unix_timestamp;
+    let interest = bank.accrue(now)?;
+    let amount = bank.deposit_shares_to_amount(shares, Rounding::Down)?;
+    require!(amount > 0, ErrorCode::ZeroShares);
+    let change = bank.withdraw(amount, shares)?;
+
+    let accounts = Burn {
+        mint: context.accounts.receipt_mint.to_account_info(),
+        from: context.accounts.user_receipt_account.to_account_info(),
+        authority: context.accounts.signer.to_account_info(),
+    };
+    let cpi_context = CpiContext::new(context.accounts.token_program.to_account_info(), accounts);
+    burn(cpi_context, change.shares)?;
+
+    transfer_from_treasury(
+        &context.accounts.bank_token_account,
+        context.bumps.bank_token_account,
+        &context.accounts.user_token_account,
+        amount,
+        &context.accounts.mint,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
+
+    let interest_event = events::InterestAccrued {
+        bank: bank.key(),
+        interest,
+        total_borrowed: bank.total_borrowed,
+        borrow_share_price: bank.borrow_share_price(),
+        timestamp: now,
+    };
+    let event = events::ReceiptRedeemed {
+        user: context.accounts.signer.key(),
+        bank: bank.key(),
+        amount,
+        shares: change.shares,
+        share_price: bank.deposit_share_price(),
+    };
+    let ctx = &context;
+    if interest > 0 {
+        emit_cpi!(interest_event);
+    }
+    emit_cpi!(event);
+    Ok(())
+}
+
+/// Burns `shares` receipt tokens and credits the same number of deposit
+/// shares to the signer's `User`, where they count as collateral. The bank
+/// totals do not change: the shares only move from receipts to a position.
+pub fn process_deposit_receipt_as_collateral(
+    context: Context<DepositReceiptAsCollateral>,
+    shares: u64,
+) -> Result<()> {
+    require!(shares > 0, ErrorCode::ZeroShares);
+    let accounts = Burn {
+        mint: context.accounts.receipt_mint.to_account_info(),
+        from: context.accounts.user_receipt_account.to_account_info(),
+        authority: context.accounts.signer.to_account_info(),
+    };
+    let cpi_context = CpiContext::new(context.accounts.token_program.to_account_info(), accounts);
+    burn(cpi_context, shares)?;
+
+    let bank = &context.accounts.bank;
+    let amount = bank.deposit_shares_to_amount(shares, Rounding::Down)?;
+    let user = &mut context.accounts.user_account;
+    user.enter_collateral(&bank.key(), bank.config.isolated)?;
+    let balance = user.get_balance_or_create(&bank.key())?;
+    balance.change_deposited_shares(shares)?;
+    balance.deposited = balance
+        .deposited
+        .checked_add(amount)
+        .ok_or(ErrorCode::Overflow)?;
+    balance.last_updated_deposit = Clock::get()?.unix_timestamp;
+
+    let event = events::ReceiptCollateralized {
+        user: context.accounts.signer.key(),
+        bank: bank.key(),
+        shares,
+    };
+    let ctx = &context;
+    emit_cpi!(event);
+    Ok(())
+}
+
+/// Moves `shares` deposit shares out of the signer's `User` position and
+/// mints them as receipt tokens, the inverse of
+/// `deposit_receipt_as_collateral`. The bank totals do not change. Remaining
+/// accounts are `[bank, price_update]` pairs for every bank the user has a
+/// position in, this one included; they are only needed while the user has
+/// debt, since the shares stop counting as collateral.
+pub fn process_withdraw_collateral_as_receipt<'info>(
+    context: Context<'_, '_, 'info, 'info, WithdrawCollateralAsReceipt<'info>>,
+    shares: u64,
+) -> Result<()> {
+    require!(shares > 0, ErrorCode::ZeroShares);
+    let emode = context
+        .accounts
+        .market
+        .user_emode(&context.accounts.user_account)
+        .cloned();
+    let bank = &context.accounts.bank;
+    let amount = bank.deposit_shares_to_amount(shares, Rounding::Down)?;
+    let user = &mut context.accounts.user_account;
+    let balance = user
+        .get_balance(&bank.key())
+        .ok_or(ErrorCode::InsufficientFunds)?;
+    balance.deposited_shares = balance
+        .deposited_shares
+        .checked_sub(shares)
+        .ok_or(ErrorCode::InsufficientFunds)?;
+    balance.deposited = balance.deposited.saturating_sub(amount);
+    user.release_isolation();
+
+    if user
+        .balances
+        .iter()
+        .any(|balance| balance.borrowed_shares > 0)
+    {
+        let (_, health) = value_portfolio(
+            context.remaining_accounts,
+            &Clock::get()?,
+            bank,
+            user,
+            emode.as_ref(),
+        )?
+        .ok_or(ErrorCode::MissingHealthAccount)?;
+        require!(health.is_healthy(), ErrorCode::UnhealthyPosition);
+    }
+
+    mint_receipts(
+        &context.accounts.mint,
+        &context.accounts.receipt_mint,
+        context.bumps.receipt_mint,
+        &context.accounts.user_receipt_account,
+        &context.accounts.token_program,
+        shares,
+    )?;
+
+    let event = events::CollateralWithdrawnAsReceipt {
+        user: context.accounts.signer.key(),
+        bank: bank.key(),
+        shares,
+    };
+    let ctx = &context;
+    emit_cpi!(event);
+    Ok(())
+}
+
+/// Mints `shares` receipt tokens to `to`, signing as the receipt mint PDA.
+fn mint_receipts<'info>(
+    mint: &InterfaceAccount<'info, Mint>,
+    receipt_mint: &InterfaceAccount<'info, Mint>,
+    receipt_mint_bump: u8,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+    shares: u64,
+) -> Result<()> {
+    let mint_key = mint.key();
+    let seeds = &[b"receipt", mint_key.as_ref(), &[receipt_mint_bump]];
+    let signer_seeds = [&seeds[..]];
+    let accounts = MintTo {
+        mint: receipt_mint.to_account_info(),
+        to: to.to_account_info(),
+        authority: receipt_mint.to_account_info(),
+    };
+    let cpi_context =
+        CpiContext::new_with_signer(token_program.to_account_info(), accounts, &signer_seeds);
+    mint_to(cpi_context, shares)
+}
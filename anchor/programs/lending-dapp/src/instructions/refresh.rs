@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::{events, Bank};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RefreshBank<'info> {
+    #[account(mut)]
+    pub bank: Account<'info, Bank>,
+}
+
+/// Accrues interest on the bank's borrows up to the current time. Anyone
+/// can call it, so idle banks can be kept fresh by a crank.
+pub fn process_refresh_bank(context: Context<RefreshBank>) -> Result<()> {
+    let now = Clock::get()?;
+    let bank = &mut context.accounts.bank;
+    let interest = bank.accrue(now.unix_timestamp)?;
+
+    let event = events::InterestAccrued {
+        bank: bank.key(),
+        interest,
+        total_borrowed: bank.total_borrowed,
+        borrow_share_price: bank.borrow_share_price(),
+        timestamp: now.unix_timestamp,
+    };
+    if interest > 0 {
+        let ctx = &context;
+        emit_cpi!(event);
+    }
+    Ok(())
+}
@@ -1,6 +1,4 @@
-use std::f64::consts::E;
-
-use crate::{Bank, User};
+use crate::{events, Bank, Market, User};
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
@@ -9,13 +7,18 @@ use anchor_spl::{
 
 use crate::error::ErrorCode;
 
-use super::calculate_accrued_interest;
-use super::transfer_tokens;
+use super::{transfer_tokens, value_portfolio};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Repay<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
     mint: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
@@ -42,40 +45,88 @@ pub struct Repay<'info> {
         associated_token::token_program = token_program,
     )]
     pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// The user's isolated collateral bank, whose debt ceiling the
+    /// repayment frees up. Required while the user is in isolation mode.
+    #[account(mut)]
+    pub isolated_bank: Option<Account<'info, Bank>>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
-pub fn process_repay(context: Context<Repay>, amount: u64) -> Result<()> {
+
+/// Remaining accounts may start with `[bank, price_update]` pairs for every bank the user
+/// has a position in, this one included, to report the position in the Repay event,
+/// followed by any transfer hook accounts.
+pub fn process_repay<'info>(
+    context: Context<'_, '_, 'info, 'info, Repay<'info>>,
+    amount: u64,
+) -> Result<()> {
+    // Only what reaches the treasury pays down the debt, which is less than
+    // `amount` for mints charging a transfer fee.
+    let received = transfer_tokens(
+        &context.accounts.user_token_account,
+        &mut context.accounts.bank_token_account,
+        &amount,
+        &context.accounts.mint,
+        &context.accounts.signer,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
+
     let user = &mut context.accounts.user_account;
     let bank_address = &context.accounts.bank.key();
     let bank = &mut context.accounts.bank;
 
-    let balance = user.get_balance(bank_address).unwrap();
-    let borrowed_value = balance.borrowed;
-    let last_updated_borrow = balance.last_updated_borrow;
+    let balance = user
+        .get_balance(bank_address)
+        .ok_or(ErrorCode::OverRepayableAmount)?;
+    let now = Clock::get()?.unix_timestamp;
+
+    let interest = bank.accrue(now)?;
 
-    bank.total_borrowed =
-        calculate_accrued_interest(bank.total_borrowed, bank.interest_rate, last_updated_borrow)?;
+    require!(balance.borrowed_shares > 0, ErrorCode::OverRepayableAmount);
+    let change = bank.repay(received, balance.borrowed_shares)?;
+    balance.borrowed = balance.borrowed.saturating_sub(received);
+    balance.borrowed_shares -= change.shares;
 
-    let value_per_share = bank.total_borrowed as f64 / bank.total_borrowed_shares as f64;
-    let amount_to_repay = borrowed_value as f64 / value_per_share as f64;
-    if amount as f64 > amount_to_repay {
-        return Err(ErrorCode::OverRepayableAmount.into());
+    if user.isolated_bank != Pubkey::default() {
+        let isolated_bank = context
+            .accounts
+            .isolated_bank
+            .as_mut()
+            .filter(|isolated_bank| isolated_bank.key() == user.isolated_bank)
+            .ok_or(ErrorCode::MissingIsolatedBank)?;
+        isolated_bank.remove_isolated_debt(bank, received)?;
+        user.release_isolation();
     }
-    transfer_tokens(
-        &context.accounts.user_token_account,
-        &context.accounts.bank_token_account,
-        &amount,
-        &context.accounts.mint,
-        &context.accounts.signer,
-        &context.accounts.token_program,
+
+    let interest_event = events::InterestAccrued {
+        bank: bank.key(),
+        interest,
+        total_borrowed: bank.total_borrowed,
+        borrow_share_price: bank.borrow_share_price(),
+        timestamp: now,
+    };
+    let valuation = value_portfolio(
+        context.remaining_accounts,
+        &Clock::get()?,
+        bank,
+        user,
+        context.accounts.market.user_emode(user),
     )?;
-    let borrow_ratio = amount as f64 / (bank.total_borrowed) as f64;
-    let user_shares = bank.total_borrowed_shares as f64 * borrow_ratio as f64;
-    balance.borrowed -= amount as u64;
-    balance.borrowed_shares -= user_shares as u64;
-    bank.total_borrowed -= amount as u64;
-    bank.total_borrowed_shares -= user_shares as u64;
+    let repay_event = events::Repay {
+        user: context.accounts.signer.key(),
+        bank: bank.key(),
+        amount: received,
+        shares: change.shares,
+        share_price: bank.borrow_share_price(),
+        price: valuation.as_ref().map(|(price, _)| *price),
+        health_factor: valuation.map(|(_, health)| health.health_factor()),
+    };
+    let ctx = &context;
+    if interest > 0 {
+        emit_cpi!(interest_event);
+    }
+    emit_cpi!(repay_event);
     Ok(())
 }
@@ -0,0 +1,262 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::error::ErrorCode;
+use crate::{events, Bank, Market, Rounding, User};
+
+use super::{
+    calculate_portfolio_health, load_health_banks_with, swap_via_cpi, transfer_from_treasury,
+    transfer_tokens,
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RepayWithCollateral<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+    pub borrow_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [collateral_mint.key().as_ref()],
+        bump,
+    )]
+    pub collateral_bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [borrow_mint.key().as_ref()],
+        bump,
+    )]
+    pub borrow_bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [b"treasury", collateral_mint.key().as_ref()],
+        bump,
+    )]
+    pub collateral_bank_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"treasury", borrow_mint.key().as_ref()],
+        bump,
+    )]
+    pub borrow_bank_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [signer.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, User>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_collateral_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = borrow_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_borrow_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub collateral_price_update: Account<'info, PriceUpdateV2>,
+    pub borrow_price_update: Account<'info, PriceUpdateV2>,
+    /// CHECK: checked against the market's swap program whitelist
+    #[account(
+        executable,
+        constraint = market.swap_programs.contains(&swap_program.key()) @ ErrorCode::SwapProgramNotAllowed,
+    )]
+    pub swap_program: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+/// Withdraws `collateral_amount` of collateral, swaps it into the borrowed
+/// asset through the whitelisted swap program and repays as much debt as the
+/// swap returned. Any output above the outstanding debt stays in the user's
+/// wallet. While any debt remains, the whole portfolio must stay healthy.
+///
+/// Remaining accounts start with the `swap_accounts_len` accounts of the swap
+/// instruction, followed by `[bank, price_update]` pairs for every other bank
+/// the user has a position in.
+pub fn process_repay_with_collateral<'info>(
+    context: Context<'_, '_, 'info, 'info, RepayWithCollateral<'info>>,
+    collateral_amount: u64,
+    min_out: u64,
+    swap_data: Vec<u8>,
+    swap_accounts_len: u8,
+) -> Result<()> {
+    let now = Clock::get()?;
+    let collateral_bank_key = context.accounts.collateral_bank.key();
+    let borrow_bank_key = context.accounts.borrow_bank.key();
+    require_keys_neq!(
+        collateral_bank_key,
+        borrow_bank_key,
+        ErrorCode::InvalidCollateralMint
+    );
+    require!(
+        swap_accounts_len as usize <= context.remaining_accounts.len(),
+        ErrorCode::InvalidSwapAccounts
+    );
+    let (swap_accounts, health_accounts) = context
+        .remaining_accounts
+        .split_at(swap_accounts_len as usize);
+
+    let borrow_bank = &mut context.accounts.borrow_bank;
+    let interest = borrow_bank.accrue(now.unix_timestamp)?;
+    let collateral_bank = &mut context.accounts.collateral_bank;
+    let collateral_interest = collateral_bank.accrue(now.unix_timestamp)?;
+
+    // Release the collateral into the user's wallet so the swap can spend it.
+    let user = &mut context.accounts.user_account;
+    let collateral_balance = user
+        .get_balance(&collateral_bank_key)
+        .ok_or(ErrorCode::InsufficientFunds)?;
+    let withdrawn =
+        collateral_bank.withdraw(collateral_amount, collateral_balance.deposited_shares)?;
+    collateral_balance.deposited_shares -= withdrawn.shares;
+    collateral_balance.deposited = collateral_balance
+        .deposited
+        .saturating_sub(collateral_amount);
+
+    transfer_from_treasury(
+        &context.accounts.collateral_bank_token_account,
+        context.bumps.collateral_bank_token_account,
+        &context.accounts.user_collateral_token_account,
+        collateral_amount,
+        &context.accounts.collateral_mint,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
+
+    let amount_out = swap_via_cpi(
+        &context.accounts.swap_program.to_account_info(),
+        swap_accounts,
+        swap_data,
+        &mut context.accounts.user_borrow_token_account,
+        min_out,
+    )?;
+
+    let borrow_bank = &mut context.accounts.borrow_bank;
+    let user = &mut context.accounts.user_account;
+    let borrow_balance = user
+        .get_balance(&borrow_bank_key)
+        .ok_or(ErrorCode::OverRepayableAmount)?;
+    require!(
+        borrow_balance.borrowed_shares > 0,
+        ErrorCode::OverRepayableAmount
+    );
+    let owed = borrow_bank.borrow_shares_to_amount(borrow_balance.borrowed_shares, Rounding::Up)?;
+    let received = transfer_tokens(
+        &context.accounts.user_borrow_token_account,
+        &mut context.accounts.borrow_bank_token_account,
+        &amount_out.min(owed),
+        &context.accounts.borrow_mint,
+        &context.accounts.signer,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
+    let repaid = borrow_bank.repay(received, borrow_balance.borrowed_shares)?;
+    borrow_balance.borrowed_shares -= repaid.shares;
+    borrow_balance.borrowed = borrow_balance.borrowed.saturating_sub(received);
+    if user.isolated_bank == collateral_bank_key {
+        context
+            .accounts
+            .collateral_bank
+            .remove_isolated_debt(borrow_bank, received)?;
+        user.release_isolation();
+    }
+
+    // The position is only valued, and the events only carry prices, while
+    // debt remains to be checked.
+    let user = &context.accounts.user_account;
+    let mut valuation = None;
+    if user
+        .balances
+        .iter()
+        .any(|balance| balance.borrowed_shares > 0)
+    {
+        let banks = load_health_banks_with(
+            health_accounts,
+            &now,
+            &[
+                (
+                    &context.accounts.collateral_bank,
+                    &context.accounts.collateral_price_update,
+                ),
+                (
+                    &context.accounts.borrow_bank,
+                    &context.accounts.borrow_price_update,
+                ),
+            ],
+        )?;
+        let health =
+            calculate_portfolio_health(user, &banks, context.accounts.market.user_emode(user))?;
+        require!(health.is_healthy(), ErrorCode::UnhealthyPosition);
+        valuation = Some((banks, health.health_factor()));
+    }
+    let price_of = |key: Pubkey| {
+        valuation
+            .as_ref()
+            .and_then(|(banks, _)| banks.iter().find(|entry| entry.key == key))
+            .map(|entry| entry.price)
+    };
+    let health_factor = valuation.as_ref().map(|(_, health_factor)| *health_factor);
+
+    let borrow_bank = &context.accounts.borrow_bank;
+    let collateral_bank = &context.accounts.collateral_bank;
+    let collateral_interest_event = events::InterestAccrued {
+        bank: collateral_bank_key,
+        interest: collateral_interest,
+        total_borrowed: collateral_bank.total_borrowed,
+        borrow_share_price: collateral_bank.borrow_share_price(),
+        timestamp: now.unix_timestamp,
+    };
+    let interest_event = events::InterestAccrued {
+        bank: borrow_bank_key,
+        interest,
+        total_borrowed: borrow_bank.total_borrowed,
+        borrow_share_price: borrow_bank.borrow_share_price(),
+        timestamp: now.unix_timestamp,
+    };
+    let withdraw_event = events::Withdraw {
+        user: context.accounts.signer.key(),
+        bank: collateral_bank_key,
+        amount: collateral_amount,
+        shares: withdrawn.shares,
+        share_price: collateral_bank.deposit_share_price(),
+        price: price_of(collateral_bank_key),
+        health_factor,
+    };
+    let repay_event = events::Repay {
+        user: context.accounts.signer.key(),
+        bank: borrow_bank_key,
+        amount: received,
+        shares: repaid.shares,
+        share_price: borrow_bank.borrow_share_price(),
+        price: price_of(borrow_bank_key),
+        health_factor,
+    };
+    let ctx = &context;
+    for interest_event in [collateral_interest_event, interest_event] {
+        if interest_event.interest > 0 {
+            emit_cpi!(interest_event);
+        }
+    }
+    emit_cpi!(withdraw_event);
+    emit_cpi!(repay_event);
+    Ok(())
+}
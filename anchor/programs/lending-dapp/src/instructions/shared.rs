@@ -1,39 +1,232 @@
-use std::f64::consts::E;
-
 use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{
-    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+use anchor_lang::solana_program::{instruction::Instruction, program::invoke};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType,
+        StateWithExtensions,
+    },
+    onchain::invoke_transfer_checked,
 };
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::error::ErrorCode;
+use crate::MintExtensions;
 
+/// Token-2022 mint extensions the bank accounting copes with and that give
+/// the issuer no power over the treasury. Transfer fees are handled by
+/// crediting what the treasury actually receives; metadata, groups and the
+/// close authority (a mint with supply cannot be closed) do not affect
+/// transfers at all.
+const SAFE_MINT_EXTENSIONS: [ExtensionType; 8] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+    ExtensionType::MintCloseAuthority,
+    ExtensionType::GroupPointer,
+    ExtensionType::TokenGroup,
+    ExtensionType::GroupMemberPointer,
+    ExtensionType::TokenGroupMember,
+];
+
+/// Moves `amount` from the signer into a bank treasury and returns what the
+/// treasury actually received. For Token-2022 mints charging a transfer fee
+/// this is less than `amount`, and only the received amount may be credited.
+/// `hook_accounts` are searched for the accounts a transfer hook on `mint`
+/// needs; see `transfer_checked_with_hook`.
 pub fn transfer_tokens<'info>(
     from: &InterfaceAccount<'info, TokenAccount>,
-    to: &InterfaceAccount<'info, TokenAccount>,
+    to: &mut InterfaceAccount<'info, TokenAccount>,
     amount: &u64,
     mint: &InterfaceAccount<'info, Mint>,
     authority: &Signer<'info>,
     token_program: &Interface<'info, TokenInterface>,
+    hook_accounts: &[AccountInfo<'info>],
+) -> Result<u64> {
+    let balance_before = to.amount;
+    transfer_checked_with_hook(
+        token_program,
+        from.to_account_info(),
+        mint,
+        to.to_account_info(),
+        authority.to_account_info(),
+        hook_accounts,
+        *amount,
+        &[],
+    )?;
+    to.reload()?;
+    to.amount
+        .checked_sub(balance_before)
+        .ok_or(ErrorCode::Overflow.into())
+}
+
+/// Pays `amount` out of the treasury of `mint`, which signs for itself with
+/// its PDA seeds. The recipient bears any transfer fee.
+pub fn transfer_from_treasury<'info>(
+    treasury: &InterfaceAccount<'info, TokenAccount>,
+    treasury_bump: u8,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    amount: u64,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    hook_accounts: &[AccountInfo<'info>],
 ) -> Result<()> {
-    let transfer_accounts_options = TransferChecked {
-        from: from.to_account_info(),
-        to: to.to_account_info(),
-        mint: mint.to_account_info(),
-        authority: authority.to_account_info(),
+    let mint_key = mint.key();
+    let seeds: &[&[u8]] = &[b"treasury", mint_key.as_ref(), &[treasury_bump]];
+    transfer_checked_with_hook(
+        token_program,
+        treasury.to_account_info(),
+        mint,
+        to.to_account_info(),
+        treasury.to_account_info(),
+        hook_accounts,
+        amount,
+        &[seeds],
+    )
+}
+
+/// `transfer_checked` that also works for Token-2022 mints with a transfer
+/// hook. The hook program, its extra-account-metas account and every account
+/// those metas resolve to are looked up by key in `hook_accounts`, which is
+/// normally the instruction's remaining accounts; other accounts in there are
+/// ignored. Mints without a hook only need the four transfer accounts.
+#[allow(clippy::too_many_arguments)]
+fn transfer_checked_with_hook<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    hook_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    invoke_transfer_checked(
+        token_program.key,
+        from,
+        mint.to_account_info(),
+        to,
+        authority,
+        hook_accounts,
+        amount,
+        mint.decimals,
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+/// Amount to send so that `amount` arrives after the mint's transfer fee.
+/// Mints without a transfer fee need exactly `amount`.
+pub fn amount_with_transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(amount);
+    }
+    let data = mint_info.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let Ok(config) = state.get_extension::<TransferFeeConfig>() else {
+        return Ok(amount);
+    };
+    let fee = config
+        .calculate_inverse_epoch_fee(Clock::get()?.epoch, amount)
+        .ok_or(ErrorCode::Overflow)?;
+    Ok(amount.checked_add(fee).ok_or(ErrorCode::Overflow)?)
+}
+
+/// Inspects `mint` and returns the issuer-controlled features it uses.
+/// Fails if any of them is missing from `allowed`, or if the mint carries a
+/// Token-2022 extension the bank accounting does not model at all, such as
+/// interest-bearing or confidential transfer mints.
+pub fn check_mint_extensions(
+    mint: &InterfaceAccount<Mint>,
+    allowed: &MintExtensions,
+) -> Result<MintExtensions> {
+    let mut used = MintExtensions {
+        freeze_authority: mint.freeze_authority.is_some(),
+        ..Default::default()
     };
-    let cpi_context = CpiContext::new(token_program.to_account_info(), transfer_accounts_options);
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner == spl_token_2022::ID {
+        let data = mint_info.try_borrow_data()?;
+        let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+        for extension in state.get_extension_types()? {
+            match extension {
+                ExtensionType::PermanentDelegate => used.permanent_delegate = true,
+                ExtensionType::TransferHook => used.transfer_hook = true,
+                ExtensionType::NonTransferable => used.non_transferable = true,
+                ExtensionType::DefaultAccountState => used.default_account_state = true,
+                extension => require!(
+                    SAFE_MINT_EXTENSIONS.contains(&extension),
+                    ErrorCode::UnsupportedMintExtension
+                ),
+            }
+        }
+    }
+
+    require!(
+        (!used.freeze_authority || allowed.freeze_authority)
+            && (!used.permanent_delegate || allowed.permanent_delegate)
+            && (!used.transfer_hook || allowed.transfer_hook)
+            && (!used.non_transferable || allowed.non_transferable)
+            && (!used.default_account_state || allowed.default_account_state),
+        ErrorCode::MintExtensionNotAllowed
+    );
+    Ok(used)
+}
 
-    transfer_checked(cpi_context, *amount, mint.decimals)
+/// USD value of `amount` base units at `price`, expressed in the price's own
+/// exponent. All supported Pyth USD feeds share the same exponent, so values
+/// from different banks can be compared directly.
+pub fn calculate_usd_value(amount: u64, price: i64, decimals: u8) -> Result<u128> {
+    require!(price > 0, ErrorCode::InvalidPrice);
+    let value = (amount as u128)
+        .checked_mul(price as u128)
+        .ok_or(ErrorCode::Overflow)?
+        / 10u128.pow(decimals as u32);
+    Ok(value)
 }
 
-pub fn calculate_accrued_interest(
-    deposited: u64,
-    interest_rate: f64,
-    last_updated: i64,
+/// Inverse of `calculate_usd_value`: base units of the asset worth `value`.
+pub fn calculate_token_amount(value: u128, price: i64, decimals: u8) -> Result<u64> {
+    require!(price > 0, ErrorCode::InvalidPrice);
+    let amount = value
+        .checked_mul(10u128.pow(decimals as u32))
+        .ok_or(ErrorCode::Overflow)?
+        / price as u128;
+    u64::try_from(amount).map_err(|_| ErrorCode::Overflow.into())
+}
+
+/// Invokes a whitelisted swap program with caller-supplied accounts and data
+/// and returns how much `destination` received, enforcing `min_out`.
+pub fn swap_via_cpi<'info>(
+    swap_program: &AccountInfo<'info>,
+    swap_accounts: &[AccountInfo<'info>],
+    swap_data: Vec<u8>,
+    destination: &mut InterfaceAccount<'info, TokenAccount>,
+    min_out: u64,
 ) -> Result<u64> {
-    let seconds_per_hour = 60.0 * 60.0;
-    let per_second_rate = (interest_rate) / seconds_per_hour;
-    let current_time = Clock::get()?.unix_timestamp;
-    let time_elapsed = current_time - last_updated;
-    let new_value = (deposited as f64 * E.powf(per_second_rate * time_elapsed as f64)) as u64;
+    let balance_before = destination.amount;
+    let instruction = Instruction {
+        program_id: swap_program.key(),
+        accounts: swap_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data: swap_data,
+    };
+    let mut account_infos = swap_accounts.to_vec();
+    account_infos.push(swap_program.clone());
+    invoke(&instruction, &account_infos)?;
 
-    Ok(new_value)
+    destination.reload()?;
+    let amount_out = destination
+        .amount
+        .checked_sub(balance_before)
+        .ok_or(ErrorCode::SlippageExceeded)?;
+    require!(amount_out >= min_out, ErrorCode::SlippageExceeded);
+    Ok(amount_out)
 }
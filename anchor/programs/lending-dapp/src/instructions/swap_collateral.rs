@@ -0,0 +1,216 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::error::ErrorCode;
+use crate::{events, Bank, Market, User};
+
+use super::{
+    calculate_portfolio_health, load_health_banks, record_deposit, swap_via_cpi,
+    transfer_from_treasury, transfer_tokens, HealthBank,
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SwapCollateral<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    pub source_mint: InterfaceAccount<'info, Mint>,
+    pub destination_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [source_mint.key().as_ref()],
+        bump,
+    )]
+    pub source_bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [destination_mint.key().as_ref()],
+        bump,
+        constraint = !destination_bank.config.paused @ ErrorCode::BankPaused,
+    )]
+    pub destination_bank: Account<'info, Bank>,
+    #[account(
+        mut,
+        seeds = [b"treasury", source_mint.key().as_ref()],
+        bump,
+    )]
+    pub source_bank_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"treasury", destination_mint.key().as_ref()],
+        bump,
+    )]
+    pub destination_bank_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [signer.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, User>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = source_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_source_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = destination_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program,
+    )]
+    pub user_destination_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub source_price_update: Account<'info, PriceUpdateV2>,
+    pub destination_price_update: Account<'info, PriceUpdateV2>,
+    /// CHECK: checked against the market's swap program whitelist
+    #[account(
+        executable,
+        constraint = market.swap_programs.contains(&swap_program.key()) @ ErrorCode::SwapProgramNotAllowed,
+    )]
+    pub swap_program: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+/// Moves `amount` of the user's deposit in the source bank into the
+/// destination bank by swapping it through the whitelisted swap program.
+///
+/// Remaining accounts start with the `swap_accounts_len` accounts of the swap
+/// instruction, followed by `[bank, price_update]` pairs for every other bank
+/// the user has a position in, so the portfolio health can be checked once
+/// the swap has settled.
+pub fn process_swap_collateral<'info>(
+    context: Context<'_, '_, 'info, 'info, SwapCollateral<'info>>,
+    amount: u64,
+    min_out: u64,
+    swap_data: Vec<u8>,
+    swap_accounts_len: u8,
+) -> Result<()> {
+    let now = Clock::get()?;
+    let source_bank_key = context.accounts.source_bank.key();
+    let destination_bank_key = context.accounts.destination_bank.key();
+    require_keys_neq!(
+        source_bank_key,
+        destination_bank_key,
+        ErrorCode::InvalidCollateralMint
+    );
+    require!(
+        swap_accounts_len as usize <= context.remaining_accounts.len(),
+        ErrorCode::InvalidSwapAccounts
+    );
+    let (swap_accounts, health_accounts) = context
+        .remaining_accounts
+        .split_at(swap_accounts_len as usize);
+
+    let source_bank = &mut context.accounts.source_bank;
+    let source_interest = source_bank.accrue(now.unix_timestamp)?;
+    let source_interest_event = events::InterestAccrued {
+        bank: source_bank_key,
+        interest: source_interest,
+        total_borrowed: source_bank.total_borrowed,
+        borrow_share_price: source_bank.borrow_share_price(),
+        timestamp: now.unix_timestamp,
+    };
+    let user = &mut context.accounts.user_account;
+    let source_balance = user
+        .get_balance(&source_bank_key)
+        .ok_or(ErrorCode::InsufficientFunds)?;
+    let withdrawn = source_bank.withdraw(amount, source_balance.deposited_shares)?;
+    source_balance.deposited_shares -= withdrawn.shares;
+    source_balance.deposited = source_balance.deposited.saturating_sub(amount);
+    user.release_isolation();
+
+    transfer_from_treasury(
+        &context.accounts.source_bank_token_account,
+        context.bumps.source_bank_token_account,
+        &context.accounts.user_source_token_account,
+        amount,
+        &context.accounts.source_mint,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
+
+    let amount_out = swap_via_cpi(
+        &context.accounts.swap_program.to_account_info(),
+        swap_accounts,
+        swap_data,
+        &mut context.accounts.user_destination_token_account,
+        min_out,
+    )?;
+    let received = transfer_tokens(
+        &context.accounts.user_destination_token_account,
+        &mut context.accounts.destination_bank_token_account,
+        &amount_out,
+        &context.accounts.destination_mint,
+        &context.accounts.signer,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
+
+    let (destination_interest_event, mut deposit_event) = record_deposit(
+        &mut context.accounts.destination_bank,
+        &mut context.accounts.user_account,
+        received,
+    )?;
+
+    let mut health_banks = load_health_banks(health_accounts, &now)?;
+    health_banks.retain(|entry| entry.key != source_bank_key && entry.key != destination_bank_key);
+    let source_entry = HealthBank::new(
+        source_bank_key,
+        &context.accounts.source_bank,
+        &context.accounts.source_price_update,
+        &now,
+    )?;
+    let destination_entry = HealthBank::new(
+        destination_bank_key,
+        &context.accounts.destination_bank,
+        &context.accounts.destination_price_update,
+        &now,
+    )?;
+    let source_price = source_entry.price;
+    let destination_price = destination_entry.price;
+    health_banks.push(source_entry);
+    health_banks.push(destination_entry);
+    let user = &context.accounts.user_account;
+    let health = calculate_portfolio_health(
+        user,
+        &health_banks,
+        context.accounts.market.user_emode(user),
+    )?;
+    require!(health.is_healthy(), ErrorCode::UnhealthyPosition);
+    let health_factor = health.health_factor();
+
+    let withdraw_event = events::Withdraw {
+        user: context.accounts.signer.key(),
+        bank: source_bank_key,
+        amount,
+        shares: withdrawn.shares,
+        share_price: context.accounts.source_bank.deposit_share_price(),
+        price: Some(source_price),
+        health_factor: Some(health_factor),
+    };
+    deposit_event.price = Some(destination_price);
+    deposit_event.health_factor = Some(health_factor);
+    let ctx = &context;
+    for interest_event in [source_interest_event, destination_interest_event] {
+        if interest_event.interest > 0 {
+            emit_cpi!(interest_event);
+        }
+    }
+    emit_cpi!(withdraw_event);
+    emit_cpi!(deposit_event);
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::error::ErrorCode;
+use crate::{Bank, Market, Rounding, User, BPS_DENOMINATOR};
+
+use super::{
+    active_emode, calculate_portfolio_health, calculate_token_amount, collateral_weights,
+    emode_category_of, load_health_banks, weight_bps, HealthBank, PortfolioHealth,
+};
+
+// View instructions only read state and hand their result back through
+// `set_return_data`, so clients can call them with `simulateTransaction`.
+// Positions are valued from the stored bank state without accruing interest.
+// Remaining accounts are `[bank, price_update]` pairs for every bank the user
+// has a position in.
+
+#[derive(Accounts)]
+pub struct GetUserHealth<'info> {
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    pub user_account: Account<'info, User>,
+}
+
+#[derive(Accounts)]
+pub struct GetUserBankLimit<'info> {
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
+    pub user_account: Account<'info, User>,
+    pub bank: Account<'info, Bank>,
+    pub price_update: Account<'info, PriceUpdateV2>,
+}
+
+#[derive(Accounts)]
+pub struct GetBankRates<'info> {
+    pub bank: Account<'info, Bank>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct UserHealth {
+    pub collateral_value: u128,
+    pub weighted_collateral_value: u128,
+    pub liquidation_collateral_value: u128,
+    pub debt_value: u128,
+    pub weighted_debt_value: u128,
+    pub liquidation_debt_value: u128,
+    pub health_factor: f64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct BankRates {
+    pub total_deposits: u64,
+    pub total_borrowed: u64,
+    pub utilization_rate: f64,
+    pub borrow_rate: f64,
+    pub supply_rate: f64,
+    pub deposit_share_price: u128,
+    pub borrow_share_price: u128,
+}
+
+pub fn process_get_user_health<'info>(
+    context: Context<'_, '_, 'info, 'info, GetUserHealth<'info>>,
+) -> Result<UserHealth> {
+    let clock = Clock::get()?;
+    let banks = load_health_banks(context.remaining_accounts, &clock)?;
+    let user = &context.accounts.user_account;
+    let health =
+        calculate_portfolio_health(user, &banks, context.accounts.market.user_emode(user))?;
+    Ok(UserHealth {
+        collateral_value: health.collateral_value,
+        weighted_collateral_value: health.weighted_collateral_value,
+        liquidation_collateral_value: health.liquidation_collateral_value,
+        debt_value: health.debt_value,
+        weighted_debt_value: health.weighted_debt_value,
+        liquidation_debt_value: health.liquidation_debt_value,
+        health_factor: health.health_factor(),
+    })
+}
+
+pub fn process_get_max_borrowable<'info>(
+    context: Context<'_, '_, 'info, 'info, GetUserBankLimit<'info>>,
+) -> Result<u64> {
+    let (health, price, _) = user_bank_health(&context)?;
+    let bank = &context.accounts.bank;
+    // Every unit borrowed uses `price * liability_weight_init` of borrowing
+    // power.
+    let headroom = health
+        .weighted_collateral_value
+        .saturating_sub(health.weighted_debt_value);
+    let by_health = calculate_token_amount(
+        unweigh(headroom, weight_bps(bank.liability_weight_init))?,
+        price,
+        bank.mint_decimals,
+    )?;
+    let liquidity = bank.total_deposits.saturating_sub(bank.total_borrowed);
+    Ok(by_health.min(liquidity))
+}
+
+pub fn process_get_max_withdrawable<'info>(
+    context: Context<'_, '_, 'info, 'info, GetUserBankLimit<'info>>,
+) -> Result<u64> {
+    let (health, price, max_ltv) = user_bank_health(&context)?;
+    let bank = &context.accounts.bank;
+    let deposited = match context
+        .accounts
+        .user_account
+        .balances
+        .iter()
+        .find(|balance| balance.bank_address == bank.key())
+    {
+        Some(balance) => bank.deposit_shares_to_amount(balance.deposited_shares, Rounding::Down)?,
+        None => 0,
+    };
+    let liquidity = bank.total_deposits.saturating_sub(bank.total_borrowed);
+    let withdrawable = deposited.min(liquidity);
+    let max_ltv_bps = weight_bps(max_ltv);
+    if health.weighted_debt_value == 0 || max_ltv_bps == 0 {
+        return Ok(withdrawable);
+    }
+    // Every unit withdrawn removes `price * max_ltv` of borrowing power.
+    let headroom = health
+        .weighted_collateral_value
+        .saturating_sub(health.weighted_debt_value);
+    let by_health =
+        calculate_token_amount(unweigh(headroom, max_ltv_bps)?, price, bank.mint_decimals)?;
+    Ok(by_health.min(withdrawable))
+}
+
+pub fn process_get_bank_rates(context: Context<GetBankRates>) -> Result<BankRates> {
+    let bank = &context.accounts.bank;
+    let utilization_rate = if bank.total_deposits == 0 {
+        0.0
+    } else {
+        bank.total_borrowed as f64 / bank.total_deposits as f64
+    };
+    Ok(BankRates {
+        total_deposits: bank.total_deposits,
+        total_borrowed: bank.total_borrowed,
+        utilization_rate,
+        borrow_rate: bank.interest_rate,
+        supply_rate: bank.interest_rate * utilization_rate,
+        deposit_share_price: bank.deposit_share_price(),
+        borrow_share_price: bank.borrow_share_price(),
+    })
+}
+
+/// The value that weighs `weighted_value` at `weight_bps`, rounded down.
+fn unweigh(weighted_value: u128, weight_bps: u128) -> Result<u128> {
+    let scaled = weighted_value
+        .checked_mul(BPS_DENOMINATOR as u128)
+        .ok_or(ErrorCode::Overflow)?;
+    Ok(scaled / weight_bps)
+}
+
+fn user_bank_health<'info>(
+    context: &Context<'_, '_, 'info, 'info, GetUserBankLimit<'info>>,
+) -> Result<(PortfolioHealth, i64, f64)> {
+    let clock = Clock::get()?;
+    let bank_key = context.accounts.bank.key();
+    let mut banks = load_health_banks(context.remaining_accounts, &clock)?;
+    banks.retain(|entry| entry.key != bank_key);
+    let entry = HealthBank::new(
+        bank_key,
+        &context.accounts.bank,
+        &context.accounts.price_update,
+        &clock,
+    )?;
+    let price = entry.price;
+    banks.push(entry);
+    let user = &context.accounts.user_account;
+    let emode = context.accounts.market.user_emode(user);
+    let health = calculate_portfolio_health(user, &banks, emode)?;
+    let active = active_emode(emode, user, |key| emode_category_of(&banks, key));
+    let (max_ltv, _) = collateral_weights(
+        &context.accounts.bank,
+        user.is_collateral(&bank_key),
+        active,
+    );
+    Ok((health, price, max_ltv))
+}
@@ -1,18 +1,24 @@
-use std::f64::consts::E;
-
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{Mint, TokenAccount, TokenInterface,TransferChecked, transfer_checked},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
-use crate::{Bank, User};
+use crate::{events, Bank, EmodeCategory, Market, User};
 use crate::error::ErrorCode;
 
+use super::{transfer_from_treasury, value_portfolio};
+
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Withdraw<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"market"],
+        bump,
+    )]
+    pub market: Account<'info, Market>,
 
     #[account(
         mut,
@@ -47,65 +53,87 @@ pub struct Withdraw<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
-pub fn process_withdraw(
-    context: Context<Withdraw>,
+/// Remaining accounts are `[bank, price_update]` pairs for every bank the user has a
+/// position in, this one included, followed by any transfer hook accounts. The pairs are
+/// only needed while the user has debt.
+pub fn process_withdraw<'info>(
+    context: Context<'_, '_, 'info, 'info, Withdraw<'info>>,
     amount: u64,
 ) -> Result<()> {
-    let user = &mut context.accounts.user_account;
-    let bank_address = &context.accounts.bank.key();
-    let bank = &mut context.accounts.bank;
-    let decimals = context.accounts.mint.decimals;
-    let balance = user
-        .get_balance(bank_address)
-        .unwrap();
-    let last_updated_deposit = balance.last_updated_deposit;
-    let deposited_value = balance.deposited;
-    let time_diff = last_updated_deposit - Clock::get()?.unix_timestamp;
-    
-    let share_price = bank.total_deposits
-    .checked_mul(decimals as u64)
-    .unwrap()
-    .checked_div(bank.total_deposits_shares)
-    .unwrap();
-
-    let shares_to_burn = amount
-        .checked_mul(decimals as u64)
-        .unwrap()
-        .checked_div(share_price as u64)
-        .unwrap();
-    require!(balance.deposited_shares >= shares_to_burn, ErrorCode::InsufficientFunds);
-
-    let seeds = &[
-        b"treasury",
-        context.accounts.mint.to_account_info().key.as_ref(),
-        &[context.bumps.bank_token_account],
-    ];
-    let signer_seeds = [&seeds[..]];
-    let accounts = TransferChecked {
-        from: context.accounts.bank_token_account.to_account_info(),
-        to: context.accounts.user_token_account.to_account_info(),
-        mint: context.accounts.mint.to_account_info(),
-        authority: context.accounts.bank_token_account.to_account_info(),
-    };
-    let cpi_context = CpiContext::new_with_signer(
-        context.accounts.token_program.to_account_info(),
-        accounts,
-        &signer_seeds,
-    );
-    transfer_checked(
-        cpi_context,
+    let emode = context.accounts.market.user_emode(&context.accounts.user_account).cloned();
+    let (interest_event, event) = record_withdraw(
+        &mut context.accounts.bank,
+        &mut context.accounts.user_account,
+        emode.as_ref(),
+        context.remaining_accounts,
         amount,
-        context.accounts.mint.decimals,
     )?;
 
+    transfer_from_treasury(
+        &context.accounts.bank_token_account,
+        context.bumps.bank_token_account,
+        &context.accounts.user_token_account,
+        amount,
+        &context.accounts.mint,
+        &context.accounts.token_program,
+        context.remaining_accounts,
+    )?;
 
-    
-    balance.deposited -= amount;
-    balance.deposited_shares -= shares_to_burn as u64;
-    
-
-    bank.total_deposits -= amount;
-    bank.total_deposits_shares -= shares_to_burn as u64;
+    let ctx = &context;
+    if interest_event.interest > 0 {
+        emit_cpi!(interest_event);
+    }
+    emit_cpi!(event);
     Ok(())
 }
 
+/// Burns the deposit shares backing `amount` from the owner of `user`. While the owner has
+/// debt the whole portfolio must stay healthy, valued through the `[bank, price_update]`
+/// pairs in `health_accounts`; `emode` is the category the owner opted into. That check also
+/// values the position for the Withdraw event. The caller sends the tokens. Returns the
+/// events the caller emits.
+pub fn record_withdraw<'info>(
+    bank: &mut Account<'info, Bank>,
+    user: &mut User,
+    emode: Option<&EmodeCategory>,
+    health_accounts: &'info [AccountInfo<'info>],
+    amount: u64,
+) -> Result<(events::InterestAccrued, events::Withdraw)> {
+    let owner = user.owner;
+    let balance = user
+        .get_balance(&bank.key())
+        .ok_or(ErrorCode::InsufficientFunds)?;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let interest = bank.accrue(now)?;
+    let change = bank.withdraw(amount, balance.deposited_shares)?;
+    balance.deposited = balance.deposited.saturating_sub(amount);
+    balance.deposited_shares -= change.shares;
+    user.release_isolation();
+
+    let mut valuation = None;
+    if user.balances.iter().any(|balance| balance.borrowed_shares > 0) {
+        let (price, health) = value_portfolio(health_accounts, &clock, bank, user, emode)?
+            .ok_or(ErrorCode::MissingHealthAccount)?;
+        require!(health.is_healthy(), ErrorCode::UnhealthyPosition);
+        valuation = Some((price, health.health_factor()));
+    }
+
+    let interest_event = events::InterestAccrued {
+        bank: bank.key(),
+        interest,
+        total_borrowed: bank.total_borrowed,
+        borrow_share_price: bank.borrow_share_price(),
+        timestamp: now,
+    };
+    let event = events::Withdraw {
+        user: owner,
+        bank: bank.key(),
+        amount,
+        shares: change.shares,
+        share_price: bank.deposit_share_price(),
+        price: valuation.map(|(price, _)| price),
+        health_factor: valuation.map(|(_, health_factor)| health_factor),
+    };
+    Ok((interest_event, event))
+}
@@ -2,6 +2,7 @@
 
 pub mod constants;
 pub mod error;
+pub mod events;
 pub mod instructions;
 pub mod state;
 
@@ -19,12 +20,59 @@ declare_id!("5hBoN7RxDkAgU5XEfNRF1PfETHseseCjRfFqmjBh4B6c");
 pub mod lending_dapp {
     use super::*;
 
+    pub fn init_market(context: Context<InitMarket>) -> Result<()> {
+        instructions::admin::process_init_market(context)
+    }
+    pub fn set_supported_asset(
+        context: Context<UpdateMarket>,
+        ticker_symbol: String,
+        oracle_key: String,
+        decimals: u8,
+    ) -> Result<()> {
+        instructions::admin::process_set_supported_asset(
+            context,
+            ticker_symbol,
+            oracle_key.as_str(),
+            decimals,
+        )
+    }
+    pub fn remove_supported_asset(
+        context: Context<UpdateMarket>,
+        ticker_symbol: String,
+    ) -> Result<()> {
+        instructions::admin::process_remove_supported_asset(context, ticker_symbol)
+    }
+    pub fn set_emode_category(
+        context: Context<UpdateMarket>,
+        id: u8,
+        name: String,
+        max_ltv: f64,
+        liquidation_threshold: f64,
+    ) -> Result<()> {
+        instructions::admin::process_set_emode_category(
+            context,
+            id,
+            name,
+            max_ltv,
+            liquidation_threshold,
+        )
+    }
+    pub fn remove_emode_category(context: Context<UpdateMarket>, id: u8) -> Result<()> {
+        instructions::admin::process_remove_emode_category(context, id)
+    }
+    pub fn add_swap_program(context: Context<UpdateMarket>, program_id: Pubkey) -> Result<()> {
+        instructions::admin::process_add_swap_program(context, program_id)
+    }
+    pub fn remove_swap_program(context: Context<UpdateMarket>, program_id: Pubkey) -> Result<()> {
+        instructions::admin::process_remove_swap_program(context, program_id)
+    }
     pub fn init_bank(
         context: Context<InitBank>,
         liquidation_threshold: f64,
         max_ltv: f64,
         oracle_key: String,
         ticker_symbol: String,
+        allowed_mint_extensions: MintExtensions,
     ) -> Result<()> {
         instructions::admin::process_init_bank(
             context,
@@ -32,27 +80,187 @@ pub mod lending_dapp {
             max_ltv,
             oracle_key.as_str(),
             ticker_symbol,
+            allowed_mint_extensions,
         )
     }
+    pub fn update_bank_config(
+        context: Context<UpdateBankConfig>,
+        params: BankConfigParams,
+    ) -> Result<()> {
+        instructions::admin::process_update_bank_config(context, params)
+    }
     pub fn init_user(context: Context<InitUser>) -> Result<()> {
         instructions::admin::process_init_user(context)
     }
-    pub fn close_bank(context: Context<CloseBank>) -> Result<()> {
+    pub fn close_bank<'info>(
+        context: Context<'_, '_, 'info, 'info, CloseBank<'info>>,
+    ) -> Result<()> {
         instructions::admin::process_close_bank(context)
     }
-    pub fn deposit(context: Context<Deposit>, amount: u64) -> Result<()> {
+    pub fn deposit<'info>(
+        context: Context<'_, '_, 'info, 'info, Deposit<'info>>,
+        amount: u64,
+    ) -> Result<()> {
         instructions::deposit::process_deposit(context, amount)
     }
-    pub fn withdraw(context: Context<Withdraw>, amount: u64) -> Result<()> {
+    pub fn withdraw<'info>(
+        context: Context<'_, '_, 'info, 'info, Withdraw<'info>>,
+        amount: u64,
+    ) -> Result<()> {
         instructions::withdraw::process_withdraw(context, amount)
     }
-    pub fn borrow(context: Context<Borrow>, amount: u64) -> Result<()> {
+    pub fn borrow<'info>(
+        context: Context<'_, '_, 'info, 'info, Borrow<'info>>,
+        amount: u64,
+    ) -> Result<()> {
         instructions::borrow::process_borrow(context, amount)
     }
-    pub fn repay(context: Context<Repay>, amount: u64) -> Result<()> {
+    pub fn deposit_sol<'info>(
+        context: Context<'_, '_, 'info, 'info, DepositSol<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::native_sol::process_deposit_sol(context, amount)
+    }
+    pub fn withdraw_sol<'info>(
+        context: Context<'_, '_, 'info, 'info, WithdrawSol<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::native_sol::process_withdraw_sol(context, amount)
+    }
+    pub fn borrow_sol<'info>(
+        context: Context<'_, '_, 'info, 'info, BorrowSol<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::native_sol::process_borrow_sol(context, amount)
+    }
+    pub fn set_user_emode<'info>(
+        context: Context<'_, '_, 'info, 'info, SetUserEmode<'info>>,
+        emode_category: u8,
+    ) -> Result<()> {
+        instructions::emode::process_set_user_emode(context, emode_category)
+    }
+    pub fn set_collateral_enabled<'info>(
+        context: Context<'_, '_, 'info, 'info, SetCollateralEnabled<'info>>,
+        enabled: bool,
+    ) -> Result<()> {
+        instructions::collateral::process_set_collateral_enabled(context, enabled)
+    }
+    pub fn repay<'info>(
+        context: Context<'_, '_, 'info, 'info, Repay<'info>>,
+        amount: u64,
+    ) -> Result<()> {
         instructions::repay::process_repay(context, amount)
     }
-    pub fn liquidate(context: Context<Liquidate>) -> Result<()> {
+    pub fn liquidate<'info>(
+        context: Context<'_, '_, 'info, 'info, Liquidate<'info>>,
+    ) -> Result<()> {
         instructions::liquidate::process_liquidate(context)
     }
+    pub fn repay_with_collateral<'info>(
+        context: Context<'_, '_, 'info, 'info, RepayWithCollateral<'info>>,
+        collateral_amount: u64,
+        min_out: u64,
+        swap_data: Vec<u8>,
+        swap_accounts_len: u8,
+    ) -> Result<()> {
+        instructions::repay_with_collateral::process_repay_with_collateral(
+            context,
+            collateral_amount,
+            min_out,
+            swap_data,
+            swap_accounts_len,
+        )
+    }
+    pub fn leverage<'info>(
+        context: Context<'_, '_, 'info, 'info, Leverage<'info>>,
+        collateral_amount: u64,
+        target_leverage_bps: u64,
+        max_slippage_bps: u16,
+        swap_data: Vec<u8>,
+        swap_accounts_len: u8,
+    ) -> Result<()> {
+        instructions::leverage::process_leverage(
+            context,
+            collateral_amount,
+            target_leverage_bps,
+            max_slippage_bps,
+            swap_data,
+            swap_accounts_len,
+        )
+    }
+    pub fn swap_collateral<'info>(
+        context: Context<'_, '_, 'info, 'info, SwapCollateral<'info>>,
+        amount: u64,
+        min_out: u64,
+        swap_data: Vec<u8>,
+        swap_accounts_len: u8,
+    ) -> Result<()> {
+        instructions::swap_collateral::process_swap_collateral(
+            context,
+            amount,
+            min_out,
+            swap_data,
+            swap_accounts_len,
+        )
+    }
+    pub fn flash_borrow<'info>(
+        context: Context<'_, '_, 'info, 'info, FlashBorrow<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::flash_loan::process_flash_borrow(context, amount)
+    }
+    pub fn flash_repay<'info>(
+        context: Context<'_, '_, 'info, 'info, FlashRepay<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::flash_loan::process_flash_repay(context, amount)
+    }
+    pub fn init_receipt_mint(context: Context<InitReceiptMint>) -> Result<()> {
+        instructions::receipt::process_init_receipt_mint(context)
+    }
+    pub fn mint_receipt<'info>(
+        context: Context<'_, '_, 'info, 'info, MintReceipt<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::receipt::process_mint_receipt(context, amount)
+    }
+    pub fn redeem<'info>(
+        context: Context<'_, '_, 'info, 'info, Redeem<'info>>,
+        shares: u64,
+    ) -> Result<()> {
+        instructions::receipt::process_redeem(context, shares)
+    }
+    pub fn deposit_receipt_as_collateral(
+        context: Context<DepositReceiptAsCollateral>,
+        shares: u64,
+    ) -> Result<()> {
+        instructions::receipt::process_deposit_receipt_as_collateral(context, shares)
+    }
+    pub fn withdraw_collateral_as_receipt<'info>(
+        context: Context<'_, '_, 'info, 'info, WithdrawCollateralAsReceipt<'info>>,
+        shares: u64,
+    ) -> Result<()> {
+        instructions::receipt::process_withdraw_collateral_as_receipt(context, shares)
+    }
+    pub fn refresh_bank(context: Context<RefreshBank>) -> Result<()> {
+        instructions::refresh::process_refresh_bank(context)
+    }
+    pub fn get_user_health<'info>(
+        context: Context<'_, '_, 'info, 'info, GetUserHealth<'info>>,
+    ) -> Result<UserHealth> {
+        instructions::view::process_get_user_health(context)
+    }
+    pub fn get_max_borrowable<'info>(
+        context: Context<'_, '_, 'info, 'info, GetUserBankLimit<'info>>,
+    ) -> Result<u64> {
+        instructions::view::process_get_max_borrowable(context)
+    }
+    pub fn get_max_withdrawable<'info>(
+        context: Context<'_, '_, 'info, 'info, GetUserBankLimit<'info>>,
+    ) -> Result<u64> {
+        instructions::view::process_get_max_withdrawable(context)
+    }
+    pub fn get_bank_rates(context: Context<GetBankRates>) -> Result<BankRates> {
+        instructions::view::process_get_bank_rates(context)
+    }
 }
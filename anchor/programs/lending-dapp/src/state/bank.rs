@@ -1,34 +1,355 @@
+use std::f64::consts::E;
+
 use anchor_lang::prelude::*;
 
+use crate::error::ErrorCode;
+use crate::{ISOLATED_DEBT_DECIMALS, MAX_TICKER_LEN, SHARE_PRICE_SCALE, VIRTUAL_DEPOSIT_OFFSET};
+
 #[account]
-#[derive(InitSpace)]
+#[derive(InitSpace, Default)]
 pub struct Bank {
     pub authority: Pubkey,
     pub token_mint_address: Pubkey,
+    pub mint_decimals: u8,
     pub total_deposits: u64,
     pub total_deposits_shares: u64,
     pub total_borrowed: u64,
     pub total_borrowed_shares: u64,
+    /// Maintenance asset weight: share of the collateral value that counts
+    /// when checking for liquidation.
     pub liquidation_threshold: f64,
+    /// Extra collateral paid to liquidators on top of the repaid value, in bps.
     pub liquidation_bonus: u64,
+    /// Share of a borrower's debt one liquidation may repay, in bps.
     pub close_factor: u64,
+    /// Initial asset weight: share of the collateral value that can back new
+    /// borrows.
     pub max_ltv: f64,
+    /// Initial liability weight: debt value is scaled by it when checking new
+    /// borrows, so riskier assets use more borrowing power. At least one.
+    pub liability_weight_init: f64,
+    /// Maintenance liability weight, applied to debt value when checking for
+    /// liquidation. At least one and at most `liability_weight_init`.
+    pub liability_weight_maint: f64,
     pub last_updated_borrow: i64,
     pub interest_rate: f64,
+    /// Principal of the flash loan currently outstanding, zero outside of one.
+    pub flash_loan_amount: u64,
+    /// Mint of the transferable deposit receipts, or the default key while
+    /// the bank does not issue them. Each receipt token is one deposit share.
+    pub receipt_mint: Pubkey,
+    /// Debt borrowed against this bank's collateral while it is isolated,
+    /// in `ISOLATED_DEBT_DECIMALS`. Capped by `config.debt_ceiling`.
+    pub isolated_debt: u64,
     pub config: BankConfig,
 }
 
-#[derive(AnchorSerialize, InitSpace, AnchorDeserialize, Clone)]
+impl Bank {
+    /// Assets per deposit share, scaled by `SHARE_PRICE_SCALE`. Includes the
+    /// virtual offset, matching the rate deposits and withdrawals convert at.
+    pub fn deposit_share_price(&self) -> u128 {
+        self.virtual_deposits() * SHARE_PRICE_SCALE / self.virtual_deposit_shares()
+    }
+
+    /// Debt per borrow share, scaled by `SHARE_PRICE_SCALE`.
+    pub fn borrow_share_price(&self) -> u128 {
+        if self.total_borrowed_shares == 0 {
+            return SHARE_PRICE_SCALE;
+        }
+        self.total_borrowed as u128 * SHARE_PRICE_SCALE / self.total_borrowed_shares as u128
+    }
+
+    /// Accrues interest on outstanding borrows up to `now` and returns the
+    /// interest added. The interest is owed to depositors, so it grows the
+    /// deposit side by the same amount.
+    pub fn accrue(&mut self, now: i64) -> Result<u64> {
+        if now <= self.last_updated_borrow {
+            return Ok(0);
+        }
+        let total_borrowed = calculate_accrued_interest(
+            self.total_borrowed,
+            self.interest_rate,
+            now - self.last_updated_borrow,
+        );
+        let interest = total_borrowed.saturating_sub(self.total_borrowed);
+        self.total_borrowed = total_borrowed;
+        self.total_deposits = self
+            .total_deposits
+            .checked_add(interest)
+            .ok_or(ErrorCode::Overflow)?;
+        self.last_updated_borrow = now;
+        Ok(interest)
+    }
+
+    /// `total_deposits` plus the virtual assets backing the virtual shares.
+    fn virtual_deposits(&self) -> u128 {
+        self.total_deposits as u128 + VIRTUAL_DEPOSIT_OFFSET as u128
+    }
+
+    /// `total_deposits_shares` plus the virtual shares nobody can redeem.
+    fn virtual_deposit_shares(&self) -> u128 {
+        self.total_deposits_shares as u128 + VIRTUAL_DEPOSIT_OFFSET as u128
+    }
+
+    /// Deposit shares worth `amount` tokens. An empty pool mints one share
+    /// per token. The virtual offset means that pushing the share price up,
+    /// for instance through flash loan fees, mostly benefits the virtual
+    /// shares, so inflating it to round later deposits down costs an
+    /// attacker far more than it can take.
+    pub fn deposit_amount_to_shares(&self, amount: u64, rounding: Rounding) -> Result<u64> {
+        mul_div(
+            amount,
+            self.virtual_deposit_shares(),
+            self.virtual_deposits(),
+            rounding,
+        )
+    }
+
+    /// Tokens backing `shares` deposit shares.
+    pub fn deposit_shares_to_amount(&self, shares: u64, rounding: Rounding) -> Result<u64> {
+        mul_div(
+            shares,
+            self.virtual_deposits(),
+            self.virtual_deposit_shares(),
+            rounding,
+        )
+    }
+
+    /// Borrow shares representing `amount` tokens of debt. An empty pool
+    /// mints one share per token.
+    pub fn borrow_amount_to_shares(&self, amount: u64, rounding: Rounding) -> Result<u64> {
+        if self.total_borrowed == 0 || self.total_borrowed_shares == 0 {
+            return Ok(amount);
+        }
+        mul_div(
+            amount,
+            self.total_borrowed_shares as u128,
+            self.total_borrowed as u128,
+            rounding,
+        )
+    }
+
+    /// Debt owed for `shares` borrow shares.
+    pub fn borrow_shares_to_amount(&self, shares: u64, rounding: Rounding) -> Result<u64> {
+        if self.total_borrowed_shares == 0 {
+            return Ok(0);
+        }
+        mul_div(
+            shares,
+            self.total_borrowed as u128,
+            self.total_borrowed_shares as u128,
+            rounding,
+        )
+    }
+
+    /// Books a deposit of `amount` tokens. Shares are rounded down so a
+    /// deposit never dilutes existing depositors, and a deposit too small to
+    /// mint a single share is rejected rather than donated to the pool.
+    pub fn deposit(&mut self, amount: u64) -> Result<ShareChange> {
+        let shares = self.deposit_amount_to_shares(amount, Rounding::Down)?;
+        require!(shares > 0, ErrorCode::ZeroShares);
+        self.total_deposits = self
+            .total_deposits
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        self.total_deposits_shares = self
+            .total_deposits_shares
+            .checked_add(shares)
+            .ok_or(ErrorCode::Overflow)?;
+        Ok(ShareChange { amount, shares })
+    }
+
+    /// Books a withdrawal of `amount` tokens against a position holding
+    /// `position_shares`. The burn is rounded up so a withdrawal never takes
+    /// more than its shares are worth; withdrawing everything the position
+    /// is worth burns all of its shares.
+    pub fn withdraw(&mut self, amount: u64, position_shares: u64) -> Result<ShareChange> {
+        let available = self.deposit_shares_to_amount(position_shares, Rounding::Down)?;
+        require!(amount <= available, ErrorCode::InsufficientFunds);
+        let shares = if amount == available {
+            position_shares
+        } else {
+            self.deposit_amount_to_shares(amount, Rounding::Up)?
+        };
+        self.total_deposits = self
+            .total_deposits
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientFunds)?;
+        self.total_deposits_shares = self
+            .total_deposits_shares
+            .checked_sub(shares)
+            .ok_or(ErrorCode::InsufficientFunds)?;
+        Ok(ShareChange { amount, shares })
+    }
+
+    /// Books a new borrow of `amount` tokens. Shares are rounded up so the
+    /// borrower never owes less than they received.
+    pub fn borrow(&mut self, amount: u64) -> Result<ShareChange> {
+        let shares = self.borrow_amount_to_shares(amount, Rounding::Up)?;
+        self.total_borrowed = self
+            .total_borrowed
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        self.total_borrowed_shares = self
+            .total_borrowed_shares
+            .checked_add(shares)
+            .ok_or(ErrorCode::Overflow)?;
+        Ok(ShareChange { amount, shares })
+    }
+
+    /// Books a repayment of `amount` tokens against a position holding
+    /// `position_shares`. Repaying the full debt clears every share;
+    /// otherwise the burn is rounded down so any unpaid dust stays on the
+    /// borrower's books. A partial repayment too small to burn a single
+    /// share is rejected.
+    pub fn repay(&mut self, amount: u64, position_shares: u64) -> Result<ShareChange> {
+        let owed = self.borrow_shares_to_amount(position_shares, Rounding::Up)?;
+        require!(amount <= owed, ErrorCode::OverRepayableAmount);
+        let shares = if amount == owed {
+            position_shares
+        } else {
+            self.borrow_amount_to_shares(amount, Rounding::Down)?
+        };
+        require!(shares > 0 || amount == 0, ErrorCode::ZeroShares);
+        // `owed` is rounded up, so a full repayment can exceed the bank's
+        // total by the rounding dust of other positions.
+        self.total_borrowed = self.total_borrowed.saturating_sub(amount);
+        self.total_borrowed_shares = self
+            .total_borrowed_shares
+            .checked_sub(shares)
+            .ok_or(ErrorCode::OverRepayableAmount)?;
+        Ok(ShareChange { amount, shares })
+    }
+
+    /// Books a borrow of `amount` tokens of `borrow_bank` against this
+    /// bank's collateral. Isolated collateral only backs stablecoins the
+    /// market owner allowed, up to the debt ceiling; other banks are not
+    /// limited.
+    pub fn add_isolated_debt(&mut self, borrow_bank: &Bank, amount: u64) -> Result<()> {
+        if !self.config.isolated {
+            return Ok(());
+        }
+        require!(
+            borrow_bank.config.borrowable_in_isolation,
+            ErrorCode::NotBorrowableInIsolation
+        );
+        let debt = to_isolated_debt(amount, borrow_bank.mint_decimals, Rounding::Up)?;
+        let isolated_debt = self
+            .isolated_debt
+            .checked_add(debt)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(
+            isolated_debt <= self.config.debt_ceiling,
+            ErrorCode::DebtCeilingExceeded
+        );
+        self.isolated_debt = isolated_debt;
+        Ok(())
+    }
+
+    /// Releases `amount` tokens of `repaid_bank` debt from the ceiling. The
+    /// repayment may include interest, which was never counted, so the
+    /// total only saturates at zero.
+    pub fn remove_isolated_debt(&mut self, repaid_bank: &Bank, amount: u64) -> Result<()> {
+        if !self.config.isolated || !repaid_bank.config.borrowable_in_isolation {
+            return Ok(());
+        }
+        let debt = to_isolated_debt(amount, repaid_bank.mint_decimals, Rounding::Down)?;
+        self.isolated_debt = self.isolated_debt.saturating_sub(debt);
+        Ok(())
+    }
+}
+
+/// Converts `amount` of a stablecoin with `decimals` into isolated debt
+/// units.
+fn to_isolated_debt(amount: u64, decimals: u8, rounding: Rounding) -> Result<u64> {
+    if decimals >= ISOLATED_DEBT_DECIMALS {
+        let scale = 10u128.pow((decimals - ISOLATED_DEBT_DECIMALS) as u32);
+        mul_div(amount, 1, scale, rounding)
+    } else {
+        let scale = 10u64.pow((ISOLATED_DEBT_DECIMALS - decimals) as u32);
+        amount.checked_mul(scale).ok_or(ErrorCode::Overflow.into())
+    }
+}
+
+/// Which way a token/share conversion rounds. Every conversion rounds in the
+/// bank's favour, so the caller picks the direction that protects the other
+/// users of the pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+/// Tokens and shares moved by one accounting operation on a bank.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShareChange {
+    pub amount: u64,
+    pub shares: u64,
+}
+
+/// Grows `amount` by continuously compounding `interest_rate` per hour over
+/// `time_elapsed` seconds.
+pub fn calculate_accrued_interest(amount: u64, interest_rate: f64, time_elapsed: i64) -> u64 {
+    let seconds_per_hour = 60.0 * 60.0;
+    let per_second_rate = interest_rate / seconds_per_hour;
+    (amount as f64 * E.powf(per_second_rate * time_elapsed as f64)) as u64
+}
+
+fn mul_div(value: u64, numerator: u128, denominator: u128, rounding: Rounding) -> Result<u64> {
+    require!(denominator != 0, ErrorCode::DivisionByZero);
+    let product = (value as u128)
+        .checked_mul(numerator)
+        .ok_or(ErrorCode::Overflow)?;
+    let result = match rounding {
+        Rounding::Down => product / denominator,
+        Rounding::Up => product.div_ceil(denominator),
+    };
+    u64::try_from(result).map_err(|_| ErrorCode::Overflow.into())
+}
+
+#[derive(AnchorSerialize, InitSpace, AnchorDeserialize, Clone, Default)]
 pub struct BankConfig {
     pub oracle_feed_id: [u8; 32],
-    #[max_len(5)]
+    #[max_len(MAX_TICKER_LEN)]
     pub ticker_symbol: String,
+    pub flash_loan_fee_bps: u16,
+    /// Blocks deposits, borrows and flash loans while set. Withdrawals,
+    /// repayments and liquidations stay open.
+    pub paused: bool,
+    /// Issuer-controlled mint features the market owner accepted when the
+    /// bank was created.
+    pub mint_extensions: MintExtensions,
+    /// Collateral in this bank cannot be combined with any other collateral
+    /// and only backs stablecoins, up to `debt_ceiling` in total.
+    pub isolated: bool,
+    /// Total debt all users may borrow against this bank while it is
+    /// isolated, in `ISOLATED_DEBT_DECIMALS`.
+    pub debt_ceiling: u64,
+    /// The bank holds a stablecoin that may be borrowed against isolated
+    /// collateral.
+    pub borrowable_in_isolation: bool,
+    /// E-mode category the bank belongs to, zero for none.
+    pub emode_category: u8,
+    /// Deposits count towards borrowing power. A bank without it only earns
+    /// yield, its deposits carry zero collateral weight.
+    pub can_be_collateral: bool,
+    /// The bank's asset may be borrowed.
+    pub borrowing_enabled: bool,
 }
-impl Default for BankConfig {
-    fn default() -> Self {
-        Self {
-            oracle_feed_id: [0; 32],
-            ticker_symbol: String::default(),
-        }
-    }
+
+/// Mint features that let the issuer move, freeze or lock tokens held by the
+/// treasury. A bank is only created for a mint using one of these if the
+/// market owner allowlists it.
+#[derive(AnchorSerialize, InitSpace, AnchorDeserialize, Clone, Copy, Default, PartialEq, Debug)]
+pub struct MintExtensions {
+    /// The mint has a freeze authority that can freeze the treasury.
+    pub freeze_authority: bool,
+    /// A permanent delegate can transfer or burn tokens out of the treasury.
+    pub permanent_delegate: bool,
+    /// Every transfer runs a program chosen by the issuer, which can fail it.
+    pub transfer_hook: bool,
+    /// Tokens can never leave the account they were minted to.
+    pub non_transferable: bool,
+    /// New token accounts, including the treasury, may start out frozen.
+    pub default_account_state: bool,
 }
@@ -0,0 +1,62 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    User, MAX_EMODE_CATEGORIES, MAX_EMODE_NAME_LEN, MAX_SUPPORTED_ASSETS, MAX_SWAP_PROGRAMS,
+    MAX_TICKER_LEN,
+};
+
+#[derive(AnchorSerialize, InitSpace, AnchorDeserialize, Clone, Default)]
+pub struct SupportedAsset {
+    #[max_len(MAX_TICKER_LEN)]
+    pub ticker_symbol: String,
+    pub oracle_feed_id: [u8; 32],
+    pub decimals: u8,
+}
+
+/// Correlated assets, such as stablecoins, valued with a higher LTV and
+/// liquidation threshold than their banks' own while a user's collateral
+/// and debt all belong to the category.
+#[derive(AnchorSerialize, InitSpace, AnchorDeserialize, Clone, Default, Debug)]
+pub struct EmodeCategory {
+    /// Non-zero id banks and users refer to the category by.
+    pub id: u8,
+    #[max_len(MAX_EMODE_NAME_LEN)]
+    pub name: String,
+    pub max_ltv: f64,
+    pub liquidation_threshold: f64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Market {
+    pub owner: Pubkey,
+    #[max_len(MAX_SUPPORTED_ASSETS)]
+    pub supported_assets: Vec<SupportedAsset>,
+    /// Swap programs the lending instructions are allowed to CPI into.
+    #[max_len(MAX_SWAP_PROGRAMS)]
+    pub swap_programs: Vec<Pubkey>,
+    #[max_len(MAX_EMODE_CATEGORIES)]
+    pub emode_categories: Vec<EmodeCategory>,
+}
+
+impl Market {
+    pub fn get_supported_asset(&self, ticker_symbol: &str) -> Option<&SupportedAsset> {
+        self.supported_assets
+            .iter()
+            .find(|asset| asset.ticker_symbol == ticker_symbol)
+    }
+
+    pub fn get_emode_category(&self, id: u8) -> Option<&EmodeCategory> {
+        self.emode_categories
+            .iter()
+            .find(|category| category.id == id)
+    }
+
+    /// The category `user` opted into, if it still exists.
+    pub fn user_emode(&self, user: &User) -> Option<&EmodeCategory> {
+        if user.emode_category == 0 {
+            return None;
+        }
+        self.get_emode_category(user.emode_category)
+    }
+}
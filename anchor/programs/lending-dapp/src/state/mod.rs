@@ -1,5 +1,8 @@
 pub mod bank;
 pub use bank::*;
 
+pub mod market;
+pub use market::*;
+
 pub mod user;
 pub use user::*;
@@ -9,6 +9,9 @@ pub struct Balance {
     pub borrowed_shares: u64,
     pub last_updated_deposit: i64,
     pub last_updated_borrow: i64,
+    /// The owner excluded this deposit from collateral: it adds no borrowing
+    /// power and cannot be seized in a liquidation.
+    pub collateral_disabled: bool,
 }
 pub const MAX_TOKEN_BALANCES: usize = 3;
 #[account]
@@ -17,6 +20,11 @@ pub struct User {
     pub owner: Pubkey,
 
     pub balances: [Balance; MAX_TOKEN_BALANCES],
+    /// Isolated bank holding the user's only collateral, or the default key
+    /// while the user is not in isolation mode.
+    pub isolated_bank: Pubkey,
+    /// E-mode category the user opted into, zero for none.
+    pub emode_category: u8,
 }
 impl User {
     pub fn get_balance(&mut self, bank_address: &Pubkey) -> Option<&mut Balance> {
@@ -38,6 +46,50 @@ impl User {
         balance.bank_address = *bank_address;
         Ok(balance)
     }
+    /// Checks that collateral may go into `bank_address`. Isolated
+    /// collateral cannot sit next to other deposits, and entering isolation
+    /// with debt already outstanding would keep that debt off the ceiling.
+    pub fn enter_collateral(&mut self, bank_address: &Pubkey, isolated: bool) -> Result<()> {
+        if !isolated {
+            require!(
+                self.isolated_bank == Pubkey::default(),
+                ErrorCode::IsolatedCollateralMixed
+            );
+            return Ok(());
+        }
+        if self.isolated_bank == *bank_address {
+            return Ok(());
+        }
+        let mixed = self.balances.iter().any(|balance| {
+            balance.borrowed_shares > 0
+                || (balance.bank_address != *bank_address && balance.deposited_shares > 0)
+        });
+        require!(
+            self.isolated_bank == Pubkey::default() && !mixed,
+            ErrorCode::IsolatedCollateralMixed
+        );
+        self.isolated_bank = *bank_address;
+        Ok(())
+    }
+    /// Leaves isolation mode once the isolated collateral and all debt are
+    /// gone.
+    pub fn release_isolation(&mut self) {
+        let isolated_bank = self.isolated_bank;
+        let in_use = self.balances.iter().any(|balance| {
+            balance.borrowed_shares > 0
+                || (balance.bank_address == isolated_bank && balance.deposited_shares > 0)
+        });
+        if !in_use {
+            self.isolated_bank = Pubkey::default();
+        }
+    }
+    /// Whether the deposit in `bank_address` counts as collateral.
+    pub fn is_collateral(&self, bank_address: &Pubkey) -> bool {
+        self.balances
+            .iter()
+            .find(|balance| balance.bank_address == *bank_address)
+            .is_none_or(|balance| !balance.collateral_disabled)
+    }
     pub fn get_first_empty_balance(&mut self) -> Option<&mut Balance> {
         self.balances
             .iter_mut()
@@ -46,7 +98,7 @@ impl User {
 }
 impl Balance {
     pub fn change_deposited_shares(&mut self, delta: u64) -> Result<()> {
-        let deposited_shares: u64 = self.deposited_shares.into();
+        let deposited_shares: u64 = self.deposited_shares;
         self.deposited_shares = deposited_shares
             .checked_add(delta)
             .ok_or(ErrorCode::Overflow)?;
@@ -61,6 +113,7 @@ impl Balance {
             borrowed_shares: 0,
             last_updated_borrow: 0,
             last_updated_deposit: 0,
+            collateral_disabled: false,
         }
     }
     pub fn clear(&mut self) {
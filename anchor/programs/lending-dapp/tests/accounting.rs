@@ -0,0 +1,220 @@
+//! Unit tests for the clock-independent share accounting on `Bank`.
+
+use anchor_lang::error::Error;
+use lending_dapp::error::ErrorCode;
+use lending_dapp::{Bank, Rounding, ShareChange, VIRTUAL_DEPOSIT_OFFSET as OFFSET};
+
+fn bank(total_deposits: u64, deposit_shares: u64, total_borrowed: u64, borrow_shares: u64) -> Bank {
+    Bank {
+        total_deposits,
+        total_deposits_shares: deposit_shares,
+        total_borrowed,
+        total_borrowed_shares: borrow_shares,
+        interest_rate: 0.05,
+        ..Default::default()
+    }
+}
+
+/// A bank whose deposit shares are worth 1.5 tokens each once the virtual
+/// offset is counted: (2 * OFFSET + OFFSET) / (OFFSET + OFFSET).
+fn deposit_pool() -> Bank {
+    bank(2 * OFFSET, OFFSET, 0, 0)
+}
+
+fn error(code: ErrorCode) -> Error {
+    code.into()
+}
+
+#[test]
+fn empty_bank_mints_one_share_per_token() {
+    let mut bank = bank(0, 0, 0, 0);
+    assert_eq!(
+        bank.deposit(1_000).unwrap(),
+        ShareChange {
+            amount: 1_000,
+            shares: 1_000
+        }
+    );
+    assert_eq!(
+        bank.borrow(400).unwrap(),
+        ShareChange {
+            amount: 400,
+            shares: 400
+        }
+    );
+    assert_eq!(bank.total_deposits, 1_000);
+    assert_eq!(bank.total_borrowed_shares, 400);
+}
+
+#[test]
+fn conversions_round_in_the_requested_direction() {
+    let bank = Bank {
+        total_borrowed: 300,
+        total_borrowed_shares: 200,
+        ..deposit_pool()
+    };
+    assert_eq!(bank.deposit_amount_to_shares(1, Rounding::Down).unwrap(), 0);
+    assert_eq!(bank.deposit_amount_to_shares(1, Rounding::Up).unwrap(), 1);
+    assert_eq!(bank.deposit_shares_to_amount(1, Rounding::Down).unwrap(), 1);
+    assert_eq!(bank.deposit_shares_to_amount(1, Rounding::Up).unwrap(), 2);
+    assert_eq!(bank.borrow_amount_to_shares(2, Rounding::Down).unwrap(), 1);
+    assert_eq!(bank.borrow_amount_to_shares(2, Rounding::Up).unwrap(), 2);
+    assert_eq!(bank.borrow_shares_to_amount(1, Rounding::Up).unwrap(), 2);
+}
+
+#[test]
+fn conversions_do_not_overflow_at_full_supply() {
+    let bank = bank(u64::MAX - OFFSET, u64::MAX / 2 - OFFSET, 0, 0);
+    assert_eq!(
+        bank.deposit_amount_to_shares(u64::MAX, Rounding::Down)
+            .unwrap(),
+        u64::MAX / 2
+    );
+    assert_eq!(
+        bank.deposit_shares_to_amount(u64::MAX / 2, Rounding::Down)
+            .unwrap(),
+        u64::MAX
+    );
+    assert_eq!(
+        bank.deposit_shares_to_amount(u64::MAX, Rounding::Down)
+            .unwrap_err(),
+        error(ErrorCode::Overflow)
+    );
+}
+
+#[test]
+fn deposit_rounds_shares_down() {
+    let mut bank = deposit_pool();
+    let change = bank.deposit(100).unwrap();
+    assert_eq!(change.shares, 66);
+    assert_eq!(bank.total_deposits, 2 * OFFSET + 100);
+    assert_eq!(bank.total_deposits_shares, OFFSET + 66);
+}
+
+#[test]
+fn deposit_too_small_for_a_share_fails() {
+    let mut bank = deposit_pool();
+    assert_eq!(bank.deposit(1).unwrap_err(), error(ErrorCode::ZeroShares));
+    assert_eq!(bank.total_deposits, 2 * OFFSET);
+    assert_eq!(bank.deposit(2).unwrap().shares, 1);
+}
+
+#[test]
+fn withdraw_rounds_burn_up() {
+    let mut bank = deposit_pool();
+    let change = bank.withdraw(100, 150).unwrap();
+    assert_eq!(change.shares, 67);
+    assert_eq!(bank.total_deposits, 2 * OFFSET - 100);
+    assert_eq!(bank.total_deposits_shares, OFFSET - 67);
+}
+
+#[test]
+fn withdrawing_the_whole_position_burns_every_share() {
+    let mut bank = deposit_pool();
+    // 101 shares are worth 151.5 tokens, of which 151 can be withdrawn.
+    let change = bank.withdraw(151, 101).unwrap();
+    assert_eq!(change.shares, 101);
+    assert_eq!(bank.total_deposits_shares, OFFSET - 101);
+}
+
+#[test]
+fn withdraw_beyond_position_fails() {
+    let mut bank = deposit_pool();
+    assert_eq!(
+        bank.withdraw(152, 101).unwrap_err(),
+        error(ErrorCode::InsufficientFunds)
+    );
+    assert_eq!(bank.total_deposits, 2 * OFFSET);
+}
+
+#[test]
+fn borrow_rounds_shares_up() {
+    let mut bank = bank(1_000, 1_000, 300, 200);
+    let change = bank.borrow(100).unwrap();
+    assert_eq!(change.shares, 67);
+    assert_eq!(bank.total_borrowed, 400);
+    assert_eq!(bank.total_borrowed_shares, 267);
+}
+
+#[test]
+fn partial_repay_rounds_burn_down() {
+    let mut bank = bank(1_000, 1_000, 300, 200);
+    let change = bank.repay(100, 150).unwrap();
+    assert_eq!(change.shares, 66);
+    assert_eq!(bank.total_borrowed, 200);
+    assert_eq!(bank.total_borrowed_shares, 134);
+}
+
+#[test]
+fn repay_too_small_for_a_share_fails() {
+    let mut bank = bank(1_000, 1_000, 300, 200);
+    assert_eq!(
+        bank.repay(1, 150).unwrap_err(),
+        error(ErrorCode::ZeroShares)
+    );
+    assert_eq!(bank.total_borrowed, 300);
+}
+
+#[test]
+fn full_repay_clears_every_share() {
+    let mut bank = bank(1_000, 1_000, 300, 200);
+    // 101 shares owe 151.5 tokens, rounded up to 152.
+    let change = bank.repay(152, 101).unwrap();
+    assert_eq!(change.shares, 101);
+    assert_eq!(bank.total_borrowed, 148);
+    assert_eq!(bank.total_borrowed_shares, 99);
+}
+
+#[test]
+fn repay_beyond_debt_fails() {
+    let mut bank = bank(1_000, 1_000, 300, 200);
+    assert_eq!(
+        bank.repay(153, 101).unwrap_err(),
+        error(ErrorCode::OverRepayableAmount)
+    );
+    assert_eq!(bank.total_borrowed, 300);
+}
+
+#[test]
+fn accrue_credits_interest_to_depositors() {
+    let mut bank = bank(1_000, 1_000, 500, 500);
+    bank.last_updated_borrow = 100;
+    let interest = bank.accrue(100 + 3_600).unwrap();
+    assert!(interest > 0);
+    assert_eq!(bank.total_borrowed, 500 + interest);
+    assert_eq!(bank.total_deposits, 1_000 + interest);
+    assert_eq!(bank.last_updated_borrow, 3_700);
+    assert!(bank.deposit_share_price() > lending_dapp::SHARE_PRICE_SCALE);
+}
+
+#[test]
+fn accrue_ignores_past_timestamps() {
+    let mut bank = bank(1_000, 1_000, 500, 500);
+    bank.last_updated_borrow = 100;
+    assert_eq!(bank.accrue(50).unwrap(), 0);
+    assert_eq!(bank.last_updated_borrow, 100);
+    assert_eq!(bank.total_borrowed, 500);
+}
+
+#[test]
+fn inflating_the_share_price_does_not_pay() {
+    // An attacker holding the only share pushes a large fee into the pool,
+    // hoping the next deposit rounds down in their favour.
+    let mut bank = bank(0, 0, 0, 0);
+    let attacker = bank.deposit(1).unwrap().shares;
+    let inflation = 1_000_000_000;
+    bank.total_deposits += inflation;
+
+    let victim_deposit = 1_000_000_000;
+    let victim = bank.deposit(victim_deposit).unwrap().shares;
+    let attacker_value = bank
+        .deposit_shares_to_amount(attacker, Rounding::Down)
+        .unwrap();
+    let victim_value = bank
+        .deposit_shares_to_amount(victim, Rounding::Down)
+        .unwrap();
+    // The virtual shares soak up almost all of the inflation, and the victim
+    // loses less than a basis point to rounding.
+    assert!(attacker_value < (1 + inflation) / 1_000);
+    assert!(victim_deposit - victim_value < victim_deposit / 10_000);
+}
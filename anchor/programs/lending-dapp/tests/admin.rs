@@ -0,0 +1,299 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use lending_client::instructions as ix;
+use lending_client::pda::find_market_address;
+use lending_dapp::error::ErrorCode;
+use lending_dapp::{
+    BankConfigParams, Market, MintExtensions, SOL_USD_FEED_ID, USDC_USD_FEED_ID, USDT_USD_FEED_ID,
+};
+use solana_sdk::signature::{Keypair, Signer};
+
+#[tokio::test]
+async fn init_bank_sets_defaults() {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.authority, env.admin.pubkey());
+    assert_eq!(bank.mint_decimals, USDC_DECIMALS);
+    assert_eq!(bank.config.ticker_symbol, "USDC");
+    assert_eq!(bank.config.oracle_feed_id, usdc.feed_id);
+    assert_eq!(bank.max_ltv, MAX_LTV);
+    assert!(bank.close_factor > 0);
+    assert!(!bank.config.paused);
+}
+
+#[tokio::test]
+async fn init_bank_rejects_long_ticker() {
+    let mut env = TestEnv::new().await;
+    let admin = env.admin.pubkey();
+    let mint = Pubkey::new_unique();
+    env.create_mint(mint, 6).await;
+    let result = env
+        .send(
+            &[ix::init_bank(
+                admin,
+                ix::BankKeys::new(mint, env.usdc.keys().token_program),
+                0.8,
+                0.75,
+                USDC_USD_FEED_ID.to_string(),
+                "TOOLONG".to_string(),
+                MintExtensions::default(),
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidTicker);
+}
+
+#[tokio::test]
+async fn init_bank_validates_against_registry() {
+    let mut env = TestEnv::new().await;
+    let admin = env.admin.pubkey();
+    let usdt = env.add_asset("USDT", USDT_USD_FEED_ID, 6).await;
+    let mint = Pubkey::new_unique();
+    env.create_mint(mint, 6).await;
+    let keys = ix::BankKeys::new(mint, usdt.keys().token_program);
+
+    let result = env
+        .send(
+            &[ix::init_bank(
+                admin,
+                keys,
+                0.8,
+                0.75,
+                USDC_USD_FEED_ID.to_string(),
+                "BONK".to_string(),
+                MintExtensions::default(),
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidTicker);
+
+    let result = env
+        .send(
+            &[ix::init_bank(
+                admin,
+                keys,
+                0.8,
+                0.75,
+                SOL_USD_FEED_ID.to_string(),
+                "USDT".to_string(),
+                MintExtensions::default(),
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidOracleFeed);
+
+    let mint = Pubkey::new_unique();
+    env.create_mint(mint, 9).await;
+    let keys = ix::BankKeys::new(mint, usdt.keys().token_program);
+    let result = env
+        .send(
+            &[ix::init_bank(
+                admin,
+                keys,
+                0.8,
+                0.75,
+                USDT_USD_FEED_ID.to_string(),
+                "USDT".to_string(),
+                MintExtensions::default(),
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidMintDecimals);
+}
+
+#[tokio::test]
+async fn supported_assets_are_capped() {
+    let mut env = TestEnv::new().await;
+    let admin = env.admin.pubkey();
+    let feed = "0x".to_string() + &"11".repeat(32);
+    let mut result = Ok(());
+    for index in 0..lending_dapp::MAX_SUPPORTED_ASSETS {
+        result = env
+            .send(
+                &[ix::set_supported_asset(
+                    admin,
+                    format!("A{index}"),
+                    feed.clone(),
+                    6,
+                )],
+                &[],
+            )
+            .await;
+        if result.is_err() {
+            break;
+        }
+    }
+    assert_error(result, ErrorCode::SupportedAssetsFull);
+}
+
+#[tokio::test]
+async fn swap_programs_are_capped() {
+    let mut env = TestEnv::new().await;
+    let admin = env.admin.pubkey();
+    for _ in 0..lending_dapp::MAX_SWAP_PROGRAMS {
+        env.send(&[ix::add_swap_program(admin, Pubkey::new_unique())], &[])
+            .await
+            .unwrap();
+    }
+    let result = env
+        .send(&[ix::add_swap_program(admin, Pubkey::new_unique())], &[])
+        .await;
+    assert_error(result, ErrorCode::SwapProgramsFull);
+}
+
+#[tokio::test]
+async fn only_the_upgrade_authority_creates_the_market() {
+    let mut env = TestEnv::without_market().await;
+    // Funded, so the market account could be paid for.
+    let intruder = env.create_user(0, 0).await;
+    let result = env
+        .send(&[ix::init_market(intruder.pubkey())], &[&intruder])
+        .await;
+    assert_error(result, ErrorCode::Unauthorized);
+
+    let admin = env.admin.pubkey();
+    env.send(&[ix::init_market(admin)], &[]).await.unwrap();
+    let market: Market = env.anchor_account(find_market_address().0).await;
+    assert_eq!(market.owner, admin);
+}
+
+#[tokio::test]
+async fn only_the_owner_updates_the_market_and_banks() {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let intruder = Keypair::new();
+
+    let result = env
+        .send(
+            &[ix::add_swap_program(
+                intruder.pubkey(),
+                Pubkey::new_unique(),
+            )],
+            &[&intruder],
+        )
+        .await;
+    assert_error(result, ErrorCode::Unauthorized);
+
+    let params = BankConfigParams {
+        max_ltv: Some(0.99),
+        ..Default::default()
+    };
+    let result = env
+        .send(
+            &[ix::update_bank_config(intruder.pubkey(), usdc.mint, params)],
+            &[&intruder],
+        )
+        .await;
+    assert_error(result, ErrorCode::Unauthorized);
+}
+
+#[tokio::test]
+async fn update_bank_config_validates_fees() {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let admin = env.admin.pubkey();
+
+    let params = BankConfigParams {
+        flash_loan_fee_bps: Some(10_001),
+        ..Default::default()
+    };
+    let result = env
+        .send(&[ix::update_bank_config(admin, usdc.mint, params)], &[])
+        .await;
+    assert_error(result, ErrorCode::InvalidBankConfig);
+
+    let params = BankConfigParams {
+        flash_loan_fee_bps: Some(30),
+        close_factor_bps: Some(10_000),
+        ..Default::default()
+    };
+    env.send(&[ix::update_bank_config(admin, usdc.mint, params)], &[])
+        .await
+        .unwrap();
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.config.flash_loan_fee_bps, 30);
+    assert_eq!(bank.close_factor, 10_000);
+}
+
+#[tokio::test]
+async fn collateral_weights_are_validated() {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let admin = env.admin.pubkey();
+    let mint = Pubkey::new_unique();
+    env.create_mint(mint, 6).await;
+    let result = env
+        .send(
+            &[ix::init_bank(
+                admin,
+                ix::BankKeys::new(mint, usdc.keys().token_program),
+                1.2,
+                0.8,
+                USDC_USD_FEED_ID.to_string(),
+                "USDC".to_string(),
+                MintExtensions::default(),
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidBankConfig);
+
+    for (max_ltv, liquidation_threshold) in
+        [(Some(0.0), None), (Some(0.85), None), (None, Some(1.1))]
+    {
+        let params = BankConfigParams {
+            max_ltv,
+            liquidation_threshold,
+            ..Default::default()
+        };
+        let result = env
+            .send(&[ix::update_bank_config(admin, usdc.mint, params)], &[])
+            .await;
+        assert_error(result, ErrorCode::InvalidBankConfig);
+    }
+    let params = BankConfigParams {
+        max_ltv: Some(0.9),
+        liquidation_threshold: Some(1.0),
+        ..Default::default()
+    };
+    env.send(&[ix::update_bank_config(admin, usdc.mint, params)], &[])
+        .await
+        .unwrap();
+    assert_eq!(env.bank(&usdc).await.max_ltv, 0.9);
+}
+
+#[tokio::test]
+async fn paused_bank_blocks_deposits_but_not_withdrawals() {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let admin = env.admin.pubkey();
+    let user = env.create_user(100 * USDC, 0).await;
+    env.deposit(&user, &usdc, 50 * USDC).await.unwrap();
+
+    let pause = BankConfigParams {
+        paused: Some(true),
+        ..Default::default()
+    };
+    env.send(&[ix::update_bank_config(admin, usdc.mint, pause)], &[])
+        .await
+        .unwrap();
+    let result = env.deposit(&user, &usdc, 10 * USDC).await;
+    assert_error(result, ErrorCode::BankPaused);
+    env.withdraw(&user, &usdc, 10 * USDC).await.unwrap();
+
+    let unpause = BankConfigParams {
+        paused: Some(false),
+        ..Default::default()
+    };
+    env.send(&[ix::update_bank_config(admin, usdc.mint, unpause)], &[])
+        .await
+        .unwrap();
+    env.deposit(&user, &usdc, 10 * USDC).await.unwrap();
+}
@@ -0,0 +1,248 @@
+mod common;
+
+use common::*;
+use lending_client::instructions as ix;
+use lending_dapp::error::ErrorCode;
+use lending_dapp::{BankConfigParams, MAX_AGE, USDT_USD_FEED_ID};
+use pyth_solana_receiver_sdk::error::GetPriceError;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Seeds the USDC bank with liquidity and returns a borrower holding 1 SOL
+/// of collateral, worth $100 at the default price.
+async fn setup() -> (TestEnv, Keypair) {
+    let mut env = TestEnv::new().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    let borrower = env.create_user(0, SOL).await;
+    env.deposit(&borrower, &sol, SOL).await.unwrap();
+    (env, borrower)
+}
+
+#[tokio::test]
+async fn borrow_within_ltv_transfers_tokens() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+
+    env.borrow(&borrower, &sol, &usdc, 70 * USDC).await.unwrap();
+    assert_eq!(
+        env.token_balance(&borrower.pubkey(), &usdc).await,
+        70 * USDC
+    );
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.total_borrowed, 70 * USDC);
+    let account = env.user(&borrower.pubkey()).await;
+    let balance = account
+        .balances
+        .iter()
+        .find(|balance| balance.bank_address == usdc.bank())
+        .unwrap();
+    assert_eq!(balance.borrowed, 70 * USDC);
+    assert_eq!(balance.borrowed_shares, bank.total_borrowed_shares);
+}
+
+#[tokio::test]
+async fn borrow_over_ltv_fails() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+
+    let result = env.borrow(&borrower, &sol, &usdc, 76 * USDC).await;
+    assert_error(result, ErrorCode::OverBorrowableAmount);
+}
+
+#[tokio::test]
+async fn borrow_up_to_exactly_the_max_ltv() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    let admin = env.admin.pubkey();
+    let params = BankConfigParams {
+        max_ltv: Some(0.69),
+        ..Default::default()
+    };
+    env.send(&[ix::update_bank_config(admin, sol.mint, params)], &[])
+        .await
+        .unwrap();
+
+    // 0.69 has no exact binary form, but the weighted collateral is still $69.
+    env.borrow(&borrower, &sol, &usdc, 69 * USDC).await.unwrap();
+    let result = env.borrow(&borrower, &sol, &usdc, 1).await;
+    assert_error(result, ErrorCode::OverBorrowableAmount);
+}
+
+#[tokio::test]
+async fn borrow_against_the_same_bank_fails() {
+    let (mut env, _) = setup().await;
+    let usdc = env.usdc;
+    let borrower = env.create_user(100 * USDC, 0).await;
+    env.deposit(&borrower, &usdc, 100 * USDC).await.unwrap();
+
+    let result = env.borrow(&borrower, &usdc, &usdc, 50 * USDC).await;
+    assert_error(result, ErrorCode::InvalidCollateralMint);
+}
+
+#[tokio::test]
+async fn collateral_backs_borrows_across_banks_once() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    let usdt = env.add_asset("USDT", USDT_USD_FEED_ID, 6).await;
+    env.seed_liquidity(&usdt, 1_000 * USDC).await;
+
+    // The USDC debt uses up most of the SOL's $75 of borrowing power.
+    env.borrow(&borrower, &sol, &usdc, 70 * USDC).await.unwrap();
+    let result = env.borrow(&borrower, &sol, &usdt, 70 * USDC).await;
+    assert_error(result, ErrorCode::OverBorrowableAmount);
+    env.borrow(&borrower, &sol, &usdt, 4 * USDC).await.unwrap();
+
+    // Leaving out the USDC bank cannot hide its debt.
+    let result = env
+        .send(
+            &[ix::borrow(
+                borrower.pubkey(),
+                sol.priced(),
+                usdt.priced(),
+                USDC,
+                &[],
+            )],
+            &[&borrower],
+        )
+        .await;
+    assert_error(result, ErrorCode::MissingHealthAccount);
+}
+
+#[tokio::test]
+async fn borrow_follows_collateral_price() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+
+    env.set_price(&sol, SOL_PRICE / 2).await;
+    let result = env.borrow(&borrower, &sol, &usdc, 40 * USDC).await;
+    assert_error(result, ErrorCode::OverBorrowableAmount);
+    env.borrow(&borrower, &sol, &usdc, 37 * USDC).await.unwrap();
+}
+
+#[tokio::test]
+async fn borrow_at_a_zero_price_fails() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+
+    env.set_price(&sol, 0).await;
+    let result = env.borrow(&borrower, &sol, &usdc, USDC).await;
+    assert_error(result, ErrorCode::InvalidPrice);
+}
+
+#[tokio::test]
+async fn borrow_without_collateral_fails() {
+    let (mut env, _) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    let user = env.create_user(0, SOL).await;
+
+    let result = env.borrow(&user, &sol, &usdc, USDC).await;
+    assert_error(result, ErrorCode::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn borrow_with_stale_price_fails() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+
+    env.warp(MAX_AGE as i64 + 1).await;
+    env.set_price(&usdc, USDC_PRICE).await;
+    let result = env.borrow(&borrower, &sol, &usdc, USDC).await;
+    assert_error(result, GetPriceError::PriceTooOld);
+}
+
+#[tokio::test]
+async fn borrow_with_wrong_price_feed_fails() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+
+    let mut collateral = sol.priced();
+    collateral.price_update = usdc.price_update;
+    let result = env
+        .send(
+            &[ix::borrow(
+                borrower.pubkey(),
+                collateral,
+                usdc.priced(),
+                USDC,
+                &[],
+            )],
+            &[&borrower],
+        )
+        .await;
+    assert_error(result, GetPriceError::MismatchedFeedId);
+}
+
+#[tokio::test]
+async fn borrow_from_fourth_bank_fails() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    let usdt = env.add_asset("USDT", USDT_USD_FEED_ID, 6).await;
+    let pyusd = env
+        .add_asset(
+            "PYUSD",
+            "c1da1b73d7f01e7ddd54b3766cf7fcd644395ad14f70aa706ec5384c59e76692",
+            6,
+        )
+        .await;
+    env.seed_liquidity(&pyusd, 100 * USDC).await;
+
+    env.borrow(&borrower, &sol, &usdc, 10 * USDC).await.unwrap();
+    env.fund(&borrower.pubkey(), &usdt, 10 * USDC).await;
+    env.deposit(&borrower, &usdt, 10 * USDC).await.unwrap();
+    let result = env.borrow(&borrower, &sol, &pyusd, USDC).await;
+    assert_error(result, ErrorCode::NoEmptyBalance);
+}
+
+#[tokio::test]
+async fn repay_reduces_debt() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.borrow(&borrower, &sol, &usdc, 50 * USDC).await.unwrap();
+
+    env.repay(&borrower, &usdc, 20 * USDC).await.unwrap();
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.total_borrowed, 30 * USDC);
+    assert_eq!(bank.total_borrowed_shares, 30 * USDC);
+    assert_eq!(
+        env.token_balance(&borrower.pubkey(), &usdc).await,
+        30 * USDC
+    );
+}
+
+#[tokio::test]
+async fn repay_more_than_borrowed_fails() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.borrow(&borrower, &sol, &usdc, 50 * USDC).await.unwrap();
+    env.fund(&borrower.pubkey(), &usdc, 10 * USDC).await;
+
+    let result = env.repay(&borrower, &usdc, 51 * USDC).await;
+    assert_error(result, ErrorCode::OverRepayableAmount);
+}
+
+#[tokio::test]
+async fn interest_accrues_on_refresh() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.borrow(&borrower, &sol, &usdc, 50 * USDC).await.unwrap();
+    let before = env.bank(&usdc).await;
+
+    env.warp(SECONDS_PER_DAY).await;
+    env.send(&[ix::refresh_bank(usdc.bank())], &[])
+        .await
+        .unwrap();
+    let after = env.bank(&usdc).await;
+    assert!(after.total_borrowed > before.total_borrowed);
+    assert_eq!(after.total_borrowed_shares, before.total_borrowed_shares);
+    assert_eq!(
+        after.total_deposits - before.total_deposits,
+        after.total_borrowed - before.total_borrowed
+    );
+    assert!(after.borrow_share_price() > before.borrow_share_price());
+    assert_eq!(
+        after.last_updated_borrow,
+        before.last_updated_borrow + SECONDS_PER_DAY
+    );
+}
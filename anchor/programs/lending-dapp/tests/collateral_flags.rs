@@ -0,0 +1,166 @@
+mod common;
+
+use common::*;
+use lending_client::instructions as ix;
+use lending_dapp::error::ErrorCode;
+use lending_dapp::{BankConfigParams, USDT_USD_FEED_ID};
+use solana_sdk::signature::{Keypair, Signer};
+
+/// Seeds the USDC bank and adds USDT. Returns USDT and a user holding
+/// 1 SOL and 100 USDT.
+async fn setup() -> (TestEnv, Asset, Keypair) {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let usdt = env.add_asset("USDT", USDT_USD_FEED_ID, 6).await;
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    let user = env.create_user(0, SOL).await;
+    env.fund(&user.pubkey(), &usdt, 100 * USDC).await;
+    (env, usdt, user)
+}
+
+async fn update_config(env: &mut TestEnv, asset: &Asset, params: BankConfigParams) {
+    let admin = env.admin.pubkey();
+    env.send(&[ix::update_bank_config(admin, asset.mint, params)], &[])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn yield_only_deposits_carry_no_borrowing_power() {
+    let (mut env, usdt, user) = setup().await;
+    let usdc = env.usdc;
+    let params = BankConfigParams {
+        can_be_collateral: Some(false),
+        ..Default::default()
+    };
+    update_config(&mut env, &usdt, params).await;
+    env.deposit(&user, &usdt, 100 * USDC).await.unwrap();
+
+    let result = env.borrow(&user, &usdt, &usdc, USDC).await;
+    assert_error(result, ErrorCode::OverBorrowableAmount);
+    assert!(!env.bank(&usdt).await.config.can_be_collateral);
+}
+
+#[tokio::test]
+async fn borrowing_can_be_disabled() {
+    let (mut env, _, user) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    let params = BankConfigParams {
+        borrowing_enabled: Some(false),
+        ..Default::default()
+    };
+    update_config(&mut env, &usdc, params).await;
+    env.deposit(&user, &sol, SOL).await.unwrap();
+
+    let result = env.borrow(&user, &sol, &usdc, 10 * USDC).await;
+    assert_error(result, ErrorCode::BorrowingDisabled);
+
+    let params = BankConfigParams {
+        borrowing_enabled: Some(true),
+        ..Default::default()
+    };
+    update_config(&mut env, &usdc, params).await;
+    env.borrow(&user, &sol, &usdc, 10 * USDC).await.unwrap();
+}
+
+#[tokio::test]
+async fn excluded_deposits_cannot_be_seized() {
+    let (mut env, usdt, user) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.deposit(&user, &sol, SOL).await.unwrap();
+    env.deposit(&user, &usdt, 100 * USDC).await.unwrap();
+    let accounts = [sol.health_account(), usdt.health_account()];
+    env.send(
+        &[ix::set_collateral_enabled(
+            user.pubkey(),
+            usdt.bank(),
+            false,
+            &accounts,
+        )],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    env.borrow(&user, &sol, &usdc, 70 * USDC).await.unwrap();
+
+    // At $80 the SOL alone backs $64, below the $70 of debt.
+    env.set_price(&sol, 80 * 100_000_000).await;
+    let liquidator = env.create_user(100 * USDC, 0).await;
+    let result = env
+        .send(
+            &[ix::liquidate(
+                liquidator.pubkey(),
+                user.pubkey(),
+                usdt.priced(),
+                usdc.priced(),
+                &[sol.health_account()],
+            )],
+            &[&liquidator],
+        )
+        .await;
+    assert_error(result, ErrorCode::CollateralDisabled);
+    env.send(
+        &[ix::liquidate(
+            liquidator.pubkey(),
+            user.pubkey(),
+            sol.priced(),
+            usdc.priced(),
+            &[usdt.health_account()],
+        )],
+        &[&liquidator],
+    )
+    .await
+    .unwrap();
+    assert!(env.user(&user.pubkey()).await.balances[1].collateral_disabled);
+}
+
+#[tokio::test]
+async fn excluding_collateral_requires_a_healthy_position() {
+    let (mut env, usdt, user) = setup().await;
+    let usdc = env.usdc;
+    env.deposit(&user, &usdt, 100 * USDC).await.unwrap();
+    env.borrow(&user, &usdt, &usdc, 50 * USDC).await.unwrap();
+
+    let accounts = [usdt.health_account(), usdc.health_account()];
+    let result = env
+        .send(
+            &[ix::set_collateral_enabled(
+                user.pubkey(),
+                usdt.bank(),
+                false,
+                &accounts,
+            )],
+            &[&user],
+        )
+        .await;
+    assert_error(result, ErrorCode::UnhealthyPosition);
+
+    env.repay(&user, &usdc, 50 * USDC).await.unwrap();
+    env.send(
+        &[ix::set_collateral_enabled(
+            user.pubkey(),
+            usdt.bank(),
+            false,
+            &accounts,
+        )],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    let result = env.borrow(&user, &usdt, &usdc, USDC).await;
+    assert_error(result, ErrorCode::OverBorrowableAmount);
+
+    // Including it again needs no health accounts.
+    env.send(
+        &[ix::set_collateral_enabled(
+            user.pubkey(),
+            usdt.bank(),
+            true,
+            &[],
+        )],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    env.borrow(&user, &usdt, &usdc, USDC).await.unwrap();
+}
@@ -0,0 +1,911 @@
+#![allow(dead_code)]
+
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{
+    AccountDeserialize, AccountSerialize, AnchorDeserialize, Discriminator, InstructionData,
+    ToAccountMetas,
+};
+use anchor_spl::associated_token::{
+    get_associated_token_address_with_program_id,
+    spl_associated_token_account::instruction as ata_instruction,
+};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        transfer_fee::TransferFeeConfig, transfer_hook::TransferHook, BaseStateWithExtensionsMut,
+        ExtensionType, StateWithExtensions, StateWithExtensionsMut,
+    },
+};
+use lending_client::instructions::{self as ix, BankKeys, HealthAccount, PricedBank};
+use lending_client::pda::{
+    find_bank_address, find_extra_account_metas_address, find_market_address,
+    find_program_data_address, find_user_address,
+};
+use lending_dapp::{Bank, Market, MintExtensions, User, SOL_USD_FEED_ID, USDC_USD_FEED_ID};
+use pyth_solana_receiver_sdk::price_update::{
+    get_feed_id_from_hex, PriceFeedMessage, PriceUpdateV2, VerificationLevel,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::{Account, AccountSharedData};
+use solana_sdk::account_info::AccountInfo;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::clock::Clock;
+use solana_sdk::entrypoint::ProgramResult;
+use solana_sdk::instruction::{AccountMeta, Instruction, InstructionError};
+use solana_sdk::program::invoke;
+use solana_sdk::program_error::ProgramError;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, state::ExtraAccountMetaList};
+use spl_transfer_hook_interface::instruction::{ExecuteInstruction, TransferHookInstruction};
+
+pub const USDC_DECIMALS: u8 = 6;
+pub const SOL_DECIMALS: u8 = 9;
+/// Pyth prices in these tests use an exponent of -8.
+pub const PRICE_EXPONENT: i32 = -8;
+pub const USDC_PRICE: i64 = 100_000_000;
+pub const SOL_PRICE: i64 = 100 * 100_000_000;
+pub const LIQUIDATION_THRESHOLD: f64 = 0.8;
+pub const MAX_LTV: f64 = 0.75;
+
+pub const USDC: u64 = 1_000_000;
+pub const SOL: u64 = 1_000_000_000;
+
+// `entry` ties the account slice to the `'info` lifetime of its contents,
+// which the native processor signature cannot express.
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    lending_dapp::entry(program_id, accounts, data)
+}
+
+fn process_mock_swap(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    mock_swap::entry(program_id, accounts, data)
+}
+
+/// Transfer hook that lets every transfer through once Token-2022 has passed
+/// it the accounts its extra-account-metas list resolves to.
+pub const TRANSFER_HOOK_PROGRAM_ID: Pubkey = Pubkey::new_from_array([0x48; 32]);
+/// The single extra account the test hook asks for on every transfer.
+pub const TRANSFER_HOOK_EXTRA_ACCOUNT: Pubkey = Pubkey::new_from_array([0x45; 32]);
+
+fn process_transfer_hook(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let TransferHookInstruction::Execute { .. } = TransferHookInstruction::unpack(data)? else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+    let validation_data = accounts[4].try_borrow_data()?;
+    ExtraAccountMetaList::check_account_infos::<ExecuteInstruction>(
+        accounts,
+        data,
+        program_id,
+        &validation_data,
+    )
+}
+
+/// Program that calls the program in its first account through CPI, with its
+/// own instruction data and the rest of its accounts.
+pub const CPI_PROXY_PROGRAM_ID: Pubkey = Pubkey::new_from_array([0x43; 32]);
+
+fn process_cpi_proxy(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let (program, forwarded) = accounts
+        .split_first()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let instruction = Instruction {
+        program_id: *program.key,
+        accounts: forwarded
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: *account.key,
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data: data.to_vec(),
+    };
+    invoke(&instruction, accounts)
+}
+
+/// Wraps `instruction` so it reaches its program through CPI.
+pub fn through_cpi(instruction: Instruction) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(instruction.program_id, false)];
+    accounts.extend(instruction.accounts);
+    Instruction {
+        program_id: CPI_PROXY_PROGRAM_ID,
+        accounts,
+        data: instruction.data,
+    }
+}
+
+/// One asset of the test market: its mint, bank and mocked price account.
+#[derive(Clone, Copy)]
+pub struct Asset {
+    pub mint: Pubkey,
+    pub price_update: Pubkey,
+    pub feed_id: [u8; 32],
+    pub token_program: Pubkey,
+}
+
+impl Asset {
+    /// Remaining accounts the test transfer hook needs to move this asset.
+    pub fn hook_metas(&self) -> Vec<AccountMeta> {
+        ix::transfer_hook_metas(
+            &self.mint,
+            &TRANSFER_HOOK_PROGRAM_ID,
+            &[AccountMeta::new_readonly(
+                TRANSFER_HOOK_EXTRA_ACCOUNT,
+                false,
+            )],
+        )
+    }
+
+    pub fn keys(&self) -> BankKeys {
+        BankKeys::new(self.mint, self.token_program)
+    }
+
+    pub fn priced(&self) -> PricedBank {
+        PricedBank {
+            keys: self.keys(),
+            price_update: self.price_update,
+        }
+    }
+
+    pub fn bank(&self) -> Pubkey {
+        find_bank_address(&self.mint).0
+    }
+
+    /// The `[bank, price_update]` pair health checks value this asset with.
+    pub fn health_account(&self) -> HealthAccount {
+        HealthAccount {
+            bank: self.bank(),
+            price_update: self.price_update,
+        }
+    }
+
+    pub fn token_account(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, &self.mint, &self.token_program)
+    }
+}
+
+/// A market with a USDC and a SOL bank, both funded by the admin, and
+/// synthetic Pyth price accounts the tests can move around.
+pub struct TestEnv {
+    pub context: ProgramTestContext,
+    pub admin: Keypair,
+    pub usdc: Asset,
+    pub sol: Asset,
+    /// Every asset with a bank, to find a user's health accounts.
+    pub assets: Vec<Asset>,
+}
+
+impl TestEnv {
+    pub async fn new() -> Self {
+        let mut env = Self::without_market().await;
+        let (usdc, sol) = (env.usdc, env.sol);
+        let admin = env.admin.pubkey();
+        env.send(&[ix::init_market(admin)], &[]).await.unwrap();
+        for (asset, feed, ticker) in [
+            (usdc, USDC_USD_FEED_ID, "USDC"),
+            (sol, SOL_USD_FEED_ID, "SOL"),
+        ] {
+            env.send(
+                &[ix::init_bank(
+                    admin,
+                    asset.keys(),
+                    LIQUIDATION_THRESHOLD,
+                    MAX_LTV,
+                    feed.to_string(),
+                    ticker.to_string(),
+                    MintExtensions::default(),
+                )],
+                &[],
+            )
+            .await
+            .unwrap();
+            env.assets.push(asset);
+        }
+        env
+    }
+
+    /// The mints and prices of `new`, before the market exists. The admin is
+    /// the program's upgrade authority.
+    pub async fn without_market() -> Self {
+        let mut program_test = ProgramTest::new(
+            "lending_dapp",
+            lending_dapp::ID,
+            processor!(process_instruction),
+        );
+        program_test.prefer_bpf(false);
+        program_test.add_program(
+            "transfer_hook",
+            TRANSFER_HOOK_PROGRAM_ID,
+            processor!(process_transfer_hook),
+        );
+        program_test.add_program("mock_swap", mock_swap::ID, processor!(process_mock_swap));
+        program_test.add_program(
+            "cpi_proxy",
+            CPI_PROXY_PROGRAM_ID,
+            processor!(process_cpi_proxy),
+        );
+        let mut context = program_test.start_with_context().await;
+        let admin = clone_keypair(&context.payer);
+
+        let usdc = Asset {
+            mint: Pubkey::new_unique(),
+            price_update: Pubkey::new_unique(),
+            feed_id: get_feed_id_from_hex(USDC_USD_FEED_ID).unwrap(),
+            token_program: spl_token::ID,
+        };
+        let sol = Asset {
+            mint: Pubkey::new_unique(),
+            price_update: Pubkey::new_unique(),
+            feed_id: get_feed_id_from_hex(SOL_USD_FEED_ID).unwrap(),
+            token_program: spl_token::ID,
+        };
+        create_mint(&mut context, &admin, usdc.mint, USDC_DECIMALS).await;
+        create_mint(&mut context, &admin, sol.mint, SOL_DECIMALS).await;
+
+        let mut env = Self {
+            context,
+            admin,
+            usdc,
+            sol,
+            assets: Vec::new(),
+        };
+        env.set_price(&usdc, USDC_PRICE).await;
+        env.set_price(&sol, SOL_PRICE).await;
+
+        let program_data = UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: Some(env.admin.pubkey()),
+        };
+        env.set_program_account(
+            find_program_data_address().0,
+            bpf_loader_upgradeable::ID,
+            bincode::serialize(&program_data).unwrap(),
+        );
+        env
+    }
+
+    /// Registers `ticker` with the market when it is not one of the seeded
+    /// assets, then creates a mint, price account and bank for it.
+    pub async fn add_asset(&mut self, ticker: &str, feed_hex: &str, decimals: u8) -> Asset {
+        let asset = Asset {
+            mint: Pubkey::new_unique(),
+            price_update: Pubkey::new_unique(),
+            feed_id: get_feed_id_from_hex(feed_hex).unwrap(),
+            token_program: spl_token::ID,
+        };
+        self.create_mint(asset.mint, decimals).await;
+        self.init_asset_bank(
+            &asset,
+            ticker,
+            feed_hex,
+            decimals,
+            MintExtensions::default(),
+        )
+        .await
+        .unwrap();
+        asset
+    }
+
+    /// Like `add_asset`, but backed by a Token-2022 mint charging
+    /// `transfer_fee_bps` on every transfer, with no maximum fee.
+    pub async fn add_token_2022_asset(
+        &mut self,
+        ticker: &str,
+        feed_hex: &str,
+        decimals: u8,
+        transfer_fee_bps: u16,
+    ) -> Asset {
+        let asset = Asset {
+            mint: Pubkey::new_unique(),
+            price_update: Pubkey::new_unique(),
+            feed_id: get_feed_id_from_hex(feed_hex).unwrap(),
+            token_program: spl_token_2022::ID,
+        };
+        let extensions = [ExtensionType::TransferFeeConfig];
+        self.create_token_2022_mint(asset.mint, decimals, &extensions, |state| {
+            let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+            config.newer_transfer_fee.transfer_fee_basis_points = transfer_fee_bps.into();
+            config.newer_transfer_fee.maximum_fee = u64::MAX.into();
+            config.older_transfer_fee = config.newer_transfer_fee;
+        });
+        self.init_asset_bank(
+            &asset,
+            ticker,
+            feed_hex,
+            decimals,
+            MintExtensions::default(),
+        )
+        .await
+        .unwrap();
+        asset
+    }
+
+    /// Like `add_asset`, but backed by a Token-2022 mint whose transfers run
+    /// the test transfer hook. Every transfer needs `hook_metas`.
+    pub async fn add_transfer_hook_asset(
+        &mut self,
+        ticker: &str,
+        feed_hex: &str,
+        decimals: u8,
+    ) -> Asset {
+        let asset = Asset {
+            mint: Pubkey::new_unique(),
+            price_update: Pubkey::new_unique(),
+            feed_id: get_feed_id_from_hex(feed_hex).unwrap(),
+            token_program: spl_token_2022::ID,
+        };
+        let extensions = [ExtensionType::TransferHook];
+        self.create_token_2022_mint(asset.mint, decimals, &extensions, |state| {
+            let hook = state.init_extension::<TransferHook>(true).unwrap();
+            hook.program_id = Some(TRANSFER_HOOK_PROGRAM_ID).try_into().unwrap();
+        });
+        let extra_metas =
+            [
+                ExtraAccountMeta::new_with_pubkey(&TRANSFER_HOOK_EXTRA_ACCOUNT, false, false)
+                    .unwrap(),
+            ];
+        let mut data = vec![0; ExtraAccountMetaList::size_of(extra_metas.len()).unwrap()];
+        ExtraAccountMetaList::init::<ExecuteInstruction>(&mut data, &extra_metas).unwrap();
+        let validation = find_extra_account_metas_address(&asset.mint, &TRANSFER_HOOK_PROGRAM_ID).0;
+        self.set_program_account(validation, TRANSFER_HOOK_PROGRAM_ID, data);
+
+        let allowed = MintExtensions {
+            transfer_hook: true,
+            ..Default::default()
+        };
+        self.init_asset_bank(&asset, ticker, feed_hex, decimals, allowed)
+            .await
+            .unwrap();
+        asset
+    }
+
+    /// Creates the native mint and a bank for it priced like the SOL bank,
+    /// for the native SOL instructions.
+    pub async fn add_wrapped_sol_asset(&mut self) -> Asset {
+        let asset = Asset {
+            mint: spl_token::native_mint::ID,
+            price_update: Pubkey::new_unique(),
+            feed_id: get_feed_id_from_hex(SOL_USD_FEED_ID).unwrap(),
+            token_program: spl_token::ID,
+        };
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint {
+            mint_authority: None.into(),
+            supply: 0,
+            decimals: SOL_DECIMALS,
+            is_initialized: true,
+            freeze_authority: None.into(),
+        }
+        .pack_into_slice(&mut data);
+        self.set_program_account(asset.mint, spl_token::ID, data);
+        self.init_asset_bank(
+            &asset,
+            "SOL",
+            SOL_USD_FEED_ID,
+            SOL_DECIMALS,
+            MintExtensions::default(),
+        )
+        .await
+        .unwrap();
+        self.set_price(&asset, SOL_PRICE).await;
+        asset
+    }
+
+    /// Registers `ticker` with the market if needed, prices `asset` at $1
+    /// and creates its bank, allowing `allowed_mint_extensions`. The mint
+    /// must already exist.
+    pub async fn init_asset_bank(
+        &mut self,
+        asset: &Asset,
+        ticker: &str,
+        feed_hex: &str,
+        decimals: u8,
+        allowed_mint_extensions: MintExtensions,
+    ) -> Result<(), BanksClientError> {
+        let admin = self.admin.pubkey();
+        let market: Market = self.anchor_account(find_market_address().0).await;
+        if market.get_supported_asset(ticker).is_none() {
+            self.send(
+                &[ix::set_supported_asset(
+                    admin,
+                    ticker.to_string(),
+                    feed_hex.to_string(),
+                    decimals,
+                )],
+                &[],
+            )
+            .await
+            .unwrap();
+        }
+        self.set_price(asset, USDC_PRICE).await;
+        self.assets.push(*asset);
+        self.send(
+            &[ix::init_bank(
+                admin,
+                asset.keys(),
+                LIQUIDATION_THRESHOLD,
+                MAX_LTV,
+                feed_hex.to_string(),
+                ticker.to_string(),
+                allowed_mint_extensions,
+            )],
+            &[],
+        )
+        .await
+    }
+
+    pub async fn create_mint(&mut self, mint: Pubkey, decimals: u8) {
+        let admin = clone_keypair(&self.admin);
+        create_mint(&mut self.context, &admin, mint, decimals).await;
+    }
+
+    /// Writes an initialized Token-2022 mint at `mint` with room for
+    /// `extensions`, which `init` must initialize. `init` may also adjust the
+    /// base mint, whose mint authority is the admin.
+    pub fn create_token_2022_mint(
+        &mut self,
+        mint: Pubkey,
+        decimals: u8,
+        extensions: &[ExtensionType],
+        init: impl FnOnce(&mut StateWithExtensionsMut<spl_token_2022::state::Mint>),
+    ) {
+        let len =
+            ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(extensions)
+                .unwrap();
+        let mut data = vec![0; len];
+        let mut state =
+            StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(&mut data)
+                .unwrap();
+        state.base = spl_token_2022::state::Mint {
+            mint_authority: Some(self.admin.pubkey()).into(),
+            supply: 0,
+            decimals,
+            is_initialized: true,
+            freeze_authority: None.into(),
+        };
+        init(&mut state);
+        state.pack_base();
+        state.init_account_type().unwrap();
+        self.set_program_account(mint, spl_token_2022::ID, data);
+    }
+
+    /// Sends `instructions` paid for by the admin, with `signers` added.
+    pub async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let transaction = self.transaction(instructions, signers).await?;
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    /// Sends `instructions` and returns the data of the events the program
+    /// emitted through `emit_cpi!`, read from the inner instructions of a
+    /// simulation of the same transaction. Decode them with `decode_events`.
+    pub async fn send_with_events(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Vec<Vec<u8>>, BanksClientError> {
+        let transaction = self.transaction(instructions, signers).await?;
+        let simulation = self
+            .context
+            .banks_client
+            .simulate_transaction(transaction.clone())
+            .await?;
+        let account_keys = transaction.message.account_keys.clone();
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await?;
+        let inner_instructions = simulation
+            .simulation_details
+            .and_then(|details| details.inner_instructions)
+            .unwrap_or_default();
+        Ok(inner_instructions
+            .into_iter()
+            .flatten()
+            .map(|inner| inner.instruction)
+            .filter(|instruction| {
+                account_keys[instruction.program_id_index as usize] == lending_dapp::ID
+            })
+            .filter_map(|instruction| {
+                instruction
+                    .data
+                    .strip_prefix(EVENT_IX_TAG_LE)
+                    .map(<[u8]>::to_vec)
+            })
+            .collect())
+    }
+
+    async fn transaction(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Transaction, BanksClientError> {
+        let blockhash = self.context.get_new_latest_blockhash().await?;
+        let mut all_signers = vec![&self.admin];
+        all_signers.extend_from_slice(signers);
+        Ok(Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.admin.pubkey()),
+            &all_signers,
+            blockhash,
+        ))
+    }
+
+    /// Writes a fully verified price update for `asset`, published now.
+    pub async fn set_price(&mut self, asset: &Asset, price: i64) {
+        let now = self.clock().await.unix_timestamp;
+        self.set_price_at(asset, price, now).await;
+    }
+
+    pub async fn set_price_at(&mut self, asset: &Asset, price: i64, publish_time: i64) {
+        let update = PriceUpdateV2 {
+            write_authority: Pubkey::new_unique(),
+            verification_level: VerificationLevel::Full,
+            price_message: PriceFeedMessage {
+                feed_id: asset.feed_id,
+                price,
+                conf: 0,
+                exponent: PRICE_EXPONENT,
+                publish_time,
+                prev_publish_time: publish_time,
+                ema_price: price,
+                ema_conf: 0,
+            },
+            posted_slot: 0,
+        };
+        let mut data = Vec::new();
+        update.try_serialize(&mut data).unwrap();
+        self.set_program_account(asset.price_update, pyth_solana_receiver_sdk::ID, data);
+    }
+
+    pub fn set_program_account(&mut self, address: Pubkey, owner: Pubkey, data: Vec<u8>) {
+        let account = Account {
+            lamports: 1_000_000_000,
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.context
+            .set_account(&address, &AccountSharedData::from(account));
+    }
+
+    pub async fn clock(&mut self) -> Clock {
+        self.context.banks_client.get_sysvar().await.unwrap()
+    }
+
+    /// Moves the clock forward by `seconds`, on a new slot so transactions
+    /// are not deduplicated.
+    pub async fn warp(&mut self, seconds: i64) {
+        let before = self.clock().await;
+        self.context.warp_to_slot(before.slot + 1).unwrap();
+        let mut clock = self.clock().await;
+        clock.unix_timestamp = before.unix_timestamp + seconds;
+        self.context.set_sysvar(&clock);
+    }
+
+    /// Creates a wallet with a user account and `amount` of each asset.
+    pub async fn create_user(&mut self, usdc_amount: u64, sol_amount: u64) -> Keypair {
+        let user = Keypair::new();
+        let admin = self.admin.pubkey();
+        self.send(
+            &[system_instruction::transfer(
+                &admin,
+                &user.pubkey(),
+                1_000_000_000,
+            )],
+            &[],
+        )
+        .await
+        .unwrap();
+        self.send(&[ix::init_user(user.pubkey())], &[&user])
+            .await
+            .unwrap();
+        let (usdc, sol) = (self.usdc, self.sol);
+        self.fund(&user.pubkey(), &usdc, usdc_amount).await;
+        self.fund(&user.pubkey(), &sol, sol_amount).await;
+        user
+    }
+
+    /// Mints `amount` of the asset into the owner's associated token account.
+    pub async fn fund(&mut self, owner: &Pubkey, asset: &Asset, amount: u64) {
+        let admin = self.admin.pubkey();
+        let token_account = asset.token_account(owner);
+        let mut instructions = vec![ata_instruction::create_associated_token_account_idempotent(
+            &admin,
+            owner,
+            &asset.mint,
+            &asset.token_program,
+        )];
+        if amount > 0 {
+            instructions.push(
+                spl_token_2022::instruction::mint_to(
+                    &asset.token_program,
+                    &asset.mint,
+                    &token_account,
+                    &admin,
+                    &[],
+                    amount,
+                )
+                .unwrap(),
+            );
+        }
+        self.send(&instructions, &[]).await.unwrap();
+    }
+
+    /// Whitelists the mock swap program and gives its pool `amount` of
+    /// `asset` to pay swaps out of.
+    pub async fn fund_swap_pool(&mut self, asset: &Asset, amount: u64) {
+        let admin = self.admin.pubkey();
+        let market: Market = self.anchor_account(find_market_address().0).await;
+        if !market.swap_programs.contains(&mock_swap::ID) {
+            self.send(&[ix::add_swap_program(admin, mock_swap::ID)], &[])
+                .await
+                .unwrap();
+        }
+        self.fund(&swap_pool_authority(), asset, amount).await;
+    }
+
+    /// Deposits `amount` of the asset from a fresh lender, so the bank has
+    /// liquidity to lend.
+    pub async fn seed_liquidity(&mut self, asset: &Asset, amount: u64) {
+        let lender = self.create_user(0, 0).await;
+        self.fund(&lender.pubkey(), asset, amount).await;
+        self.deposit(&lender, asset, amount).await.unwrap();
+    }
+
+    pub async fn deposit(
+        &mut self,
+        user: &Keypair,
+        asset: &Asset,
+        amount: u64,
+    ) -> Result<(), BanksClientError> {
+        self.send(
+            &[ix::deposit(user.pubkey(), asset.keys(), amount, &[])],
+            &[user],
+        )
+        .await
+    }
+
+    pub async fn withdraw(
+        &mut self,
+        user: &Keypair,
+        asset: &Asset,
+        amount: u64,
+    ) -> Result<(), BanksClientError> {
+        let health_accounts = self.health_accounts(&user.pubkey()).await;
+        self.send(
+            &[ix::withdraw(
+                user.pubkey(),
+                asset.keys(),
+                amount,
+                &health_accounts,
+            )],
+            &[user],
+        )
+        .await
+    }
+
+    pub async fn borrow(
+        &mut self,
+        user: &Keypair,
+        collateral: &Asset,
+        borrowed: &Asset,
+        amount: u64,
+    ) -> Result<(), BanksClientError> {
+        let health_accounts = self.health_accounts(&user.pubkey()).await;
+        self.send(
+            &[ix::borrow(
+                user.pubkey(),
+                collateral.priced(),
+                borrowed.priced(),
+                amount,
+                &health_accounts,
+            )],
+            &[user],
+        )
+        .await
+    }
+
+    /// The health accounts of every bank `owner` has a position in.
+    pub async fn health_accounts(&mut self, owner: &Pubkey) -> Vec<HealthAccount> {
+        let user = self.user(owner).await;
+        self.assets
+            .iter()
+            .filter(|asset| user.balances.iter().any(|b| b.bank_address == asset.bank()))
+            .map(Asset::health_account)
+            .collect()
+    }
+
+    pub async fn repay(
+        &mut self,
+        user: &Keypair,
+        asset: &Asset,
+        amount: u64,
+    ) -> Result<(), BanksClientError> {
+        self.send(
+            &[ix::repay(user.pubkey(), asset.keys(), amount, None, &[])],
+            &[user],
+        )
+        .await
+    }
+
+    pub async fn bank(&mut self, asset: &Asset) -> Bank {
+        self.anchor_account(asset.bank()).await
+    }
+
+    pub async fn user(&mut self, owner: &Pubkey) -> User {
+        self.anchor_account(find_user_address(owner).0).await
+    }
+
+    pub async fn anchor_account<T: AccountDeserialize>(&mut self, address: Pubkey) -> T {
+        let account = self
+            .context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .expect("account not found");
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    pub async fn lamports(&mut self, address: &Pubkey) -> u64 {
+        self.context
+            .banks_client
+            .get_balance(*address)
+            .await
+            .unwrap()
+    }
+
+    pub async fn token_balance(&mut self, owner: &Pubkey, asset: &Asset) -> u64 {
+        self.token_account_balance(asset.token_account(owner)).await
+    }
+
+    pub async fn treasury_balance(&mut self, asset: &Asset) -> u64 {
+        self.token_account_balance(asset.keys().treasury()).await
+    }
+
+    pub async fn token_account_balance(&mut self, address: Pubkey) -> u64 {
+        let account = self
+            .context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .expect("token account not found");
+        // Token-2022 accounts may carry extensions after the base state.
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+            .unwrap()
+            .base
+            .amount
+    }
+}
+
+async fn create_mint(
+    context: &mut ProgramTestContext,
+    admin: &Keypair,
+    mint: Pubkey,
+    decimals: u8,
+) {
+    // Mints live at fixed addresses so the banks can be derived up front.
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        mint_authority: Some(admin.pubkey()).into(),
+        supply: 0,
+        decimals,
+        is_initialized: true,
+        freeze_authority: None.into(),
+    }
+    .pack_into_slice(&mut data);
+    let account = Account {
+        lamports: rent.minimum_balance(data.len()),
+        data,
+        owner: spl_token::ID,
+        executable: false,
+        rent_epoch: 0,
+    };
+    context.set_account(&mint, &AccountSharedData::from(account));
+}
+
+fn clone_keypair(keypair: &Keypair) -> Keypair {
+    Keypair::from_bytes(&keypair.to_bytes()).unwrap()
+}
+
+/// Asserts that the transaction failed with the given program error, either
+/// one of ours or one raised by a dependency such as the Pyth SDK.
+pub fn assert_error<E: Into<u32> + std::fmt::Debug>(
+    result: Result<(), BanksClientError>,
+    error: E,
+) {
+    let name = format!("{error:?}");
+    let expected = error.into();
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(code),
+        ))) => assert_eq!(code, expected, "expected {name}, got error code {code}"),
+        other => panic!("expected {name}, got {other:?}"),
+    }
+}
+
+/// Asserts that the transaction failed, for paths that abort without a
+/// program error code.
+pub fn assert_failed(result: Result<(), BanksClientError>) {
+    assert!(result.is_err(), "expected the transaction to fail");
+}
+
+fn swap_pool_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"pool"], &mock_swap::ID).0
+}
+
+/// A mock swap of exactly `amount_in` of `source` for `amount_out` of
+/// `destination`, between `user`'s wallet and the pool `fund_swap_pool`
+/// filled. The pool must hold a `source` account too.
+pub fn mock_swap(
+    user: &Pubkey,
+    source: &Asset,
+    destination: &Asset,
+    amount_in: u64,
+    amount_out: u64,
+) -> Instruction {
+    let pool = swap_pool_authority();
+    Instruction {
+        program_id: mock_swap::ID,
+        accounts: mock_swap::accounts::Swap {
+            user: *user,
+            pool_authority: pool,
+            source_mint: source.mint,
+            destination_mint: destination.mint,
+            user_source: source.token_account(user),
+            user_destination: destination.token_account(user),
+            pool_source: source.token_account(&pool),
+            pool_destination: destination.token_account(&pool),
+            source_token_program: source.token_program,
+            destination_token_program: destination.token_program,
+        }
+        .to_account_metas(None),
+        data: mock_swap::instruction::Swap {
+            amount_in,
+            amount_out,
+        }
+        .data(),
+    }
+}
+
+/// Decodes the events of type `T` among the event data returned by
+/// `TestEnv::send_with_events`, in emission order.
+pub fn decode_events<T: AnchorDeserialize + Discriminator>(events: &[Vec<u8>]) -> Vec<T> {
+    events
+        .iter()
+        .filter_map(|data| data.strip_prefix(T::DISCRIMINATOR))
+        .map(|mut data| T::deserialize(&mut data).expect("event data decodes"))
+        .collect()
+}
+
+/// The single event of type `T` among `events`.
+pub fn decode_event<T: AnchorDeserialize + Discriminator>(events: &[Vec<u8>]) -> T {
+    let mut decoded = decode_events(events);
+    assert_eq!(decoded.len(), 1, "expected exactly one such event");
+    decoded.remove(0)
+}
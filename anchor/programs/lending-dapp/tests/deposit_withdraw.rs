@@ -0,0 +1,156 @@
+mod common;
+
+use common::*;
+use lending_client::instructions as ix;
+use lending_client::math::deposited_amount;
+use lending_dapp::error::ErrorCode;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Lends 1,000 USDC to a SOL-backed borrower and lets a day of interest
+/// accrue, so the USDC deposit share price is above one.
+async fn accrued_pool() -> TestEnv {
+    let mut env = TestEnv::new().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    let borrower = env.create_user(0, 10 * SOL).await;
+    env.deposit(&borrower, &sol, 10 * SOL).await.unwrap();
+    env.borrow(&borrower, &sol, &usdc, 500 * USDC)
+        .await
+        .unwrap();
+
+    env.warp(SECONDS_PER_DAY).await;
+    env.send(&[ix::refresh_bank(usdc.bank())], &[])
+        .await
+        .unwrap();
+    let bank = env.bank(&usdc).await;
+    assert!(bank.total_deposits > bank.total_deposits_shares);
+    env
+}
+
+async fn deposited_shares(env: &mut TestEnv, user: &Keypair, asset: &Asset) -> u64 {
+    env.user(&user.pubkey())
+        .await
+        .balances
+        .iter()
+        .find(|balance| balance.bank_address == asset.bank())
+        .map_or(0, |balance| balance.deposited_shares)
+}
+
+#[tokio::test]
+async fn deposit_then_withdraw_round_trips() {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let user = env.create_user(1_000 * USDC, 0).await;
+
+    env.deposit(&user, &usdc, 400 * USDC).await.unwrap();
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.total_deposits, 400 * USDC);
+    assert_eq!(bank.total_deposits_shares, 400 * USDC);
+    assert_eq!(env.token_balance(&user.pubkey(), &usdc).await, 600 * USDC);
+
+    env.withdraw(&user, &usdc, 150 * USDC).await.unwrap();
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.total_deposits, 250 * USDC);
+    let account = env.user(&user.pubkey()).await;
+    let balance = account
+        .balances
+        .iter()
+        .find(|balance| balance.bank_address == usdc.bank())
+        .unwrap();
+    assert_eq!(balance.deposited_shares, bank.total_deposits_shares);
+    assert_eq!(env.token_balance(&user.pubkey(), &usdc).await, 750 * USDC);
+}
+
+#[tokio::test]
+async fn withdraw_more_than_deposited_fails() {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let user = env.create_user(1_000 * USDC, 0).await;
+    env.deposit(&user, &usdc, 100 * USDC).await.unwrap();
+
+    let result = env.withdraw(&user, &usdc, 101 * USDC).await;
+    assert_error(result, ErrorCode::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn deposit_valuation_needs_complete_health_accounts() {
+    let mut env = TestEnv::new().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    let user = env.create_user(100 * USDC, SOL).await;
+    env.deposit(&user, &usdc, 100 * USDC).await.unwrap();
+
+    // The pairs leave out the bank deposited into.
+    let deposit = ix::deposit(user.pubkey(), sol.keys(), SOL, &[usdc.health_account()]);
+    let result = env.send(&[deposit], &[&user]).await;
+    assert_error(result, ErrorCode::MissingHealthAccount);
+
+    // A bank without its price account is not a pair.
+    let mut deposit = ix::deposit(user.pubkey(), sol.keys(), SOL, &[]);
+    deposit
+        .accounts
+        .push(AccountMeta::new_readonly(sol.bank(), false));
+    let result = env.send(&[deposit], &[&user]).await;
+    assert_error(result, ErrorCode::MissingHealthAccount);
+}
+
+#[tokio::test]
+async fn withdraw_keeps_a_borrower_healthy() {
+    let mut env = TestEnv::new().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    let borrower = env.create_user(0, SOL).await;
+    env.deposit(&borrower, &sol, SOL).await.unwrap();
+    env.borrow(&borrower, &sol, &usdc, 50 * USDC).await.unwrap();
+
+    // Half a SOL backs $37.50, short of the $50 of debt.
+    let result = env.withdraw(&borrower, &sol, SOL / 2).await;
+    assert_error(result, ErrorCode::UnhealthyPosition);
+    let withdraw = ix::withdraw(borrower.pubkey(), sol.keys(), SOL / 10, &[]);
+    let result = env.send(&[withdraw], &[&borrower]).await;
+    assert_error(result, ErrorCode::MissingHealthAccount);
+    env.withdraw(&borrower, &sol, 3 * SOL / 10).await.unwrap();
+
+    // Without debt no health accounts are needed.
+    env.repay(&borrower, &usdc, 50 * USDC).await.unwrap();
+    let withdraw = ix::withdraw(borrower.pubkey(), sol.keys(), 7 * SOL / 10, &[]);
+    env.send(&[withdraw], &[&borrower]).await.unwrap();
+}
+
+#[tokio::test]
+async fn late_depositor_round_trip_loses_less_than_one_share() {
+    let mut env = accrued_pool().await;
+    let usdc = env.usdc;
+    let user = env.create_user(1_000 * USDC, 0).await;
+    let amount = 333_333_333;
+
+    env.deposit(&user, &usdc, amount).await.unwrap();
+    let shares = deposited_shares(&mut env, &user, &usdc).await;
+    let bank = env.bank(&usdc).await;
+    let value = deposited_amount(&bank, shares).unwrap();
+    let share_value = deposited_amount(&bank, 1).unwrap() + 1;
+    assert!(value <= amount && amount - value < share_value);
+
+    env.withdraw(&user, &usdc, value).await.unwrap();
+    assert_eq!(deposited_shares(&mut env, &user, &usdc).await, 0);
+    assert_eq!(
+        env.token_balance(&user.pubkey(), &usdc).await,
+        1_000 * USDC - amount + value
+    );
+}
+
+#[tokio::test]
+async fn deposit_too_small_for_a_share_fails() {
+    let mut env = accrued_pool().await;
+    let usdc = env.usdc;
+    let user = env.create_user(1_000 * USDC, 0).await;
+
+    let result = env.deposit(&user, &usdc, 1).await;
+    assert_error(result, ErrorCode::ZeroShares);
+    let bank = env.bank(&usdc).await;
+    let one_share = bank.total_deposits.div_ceil(bank.total_deposits_shares);
+    env.deposit(&user, &usdc, one_share).await.unwrap();
+    assert_eq!(deposited_shares(&mut env, &user, &usdc).await, 1);
+}
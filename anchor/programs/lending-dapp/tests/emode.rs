@@ -0,0 +1,207 @@
+mod common;
+
+use common::*;
+use lending_client::instructions as ix;
+use lending_client::pda::find_market_address;
+use lending_dapp::error::ErrorCode;
+use lending_dapp::{BankConfigParams, Market, MAX_EMODE_CATEGORIES, USDT_USD_FEED_ID};
+use solana_sdk::signature::{Keypair, Signer};
+
+const STABLECOINS: u8 = 1;
+
+/// Puts USDC and a seeded USDT bank in a 90% LTV stablecoin category.
+/// Returns USDT and a user holding 100 USDC next to 1 SOL.
+async fn setup() -> (TestEnv, Asset, Keypair) {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let usdt = env.add_asset("USDT", USDT_USD_FEED_ID, 6).await;
+    let admin = env.admin.pubkey();
+    let category = BankConfigParams {
+        emode_category: Some(STABLECOINS),
+        ..Default::default()
+    };
+    env.send(
+        &[
+            ix::set_emode_category(admin, STABLECOINS, "stablecoins".to_string(), 0.9, 0.95),
+            ix::update_bank_config(admin, usdc.mint, category.clone()),
+            ix::update_bank_config(admin, usdt.mint, category),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    env.seed_liquidity(&usdt, 1_000 * USDC).await;
+    let user = env.create_user(100 * USDC, SOL).await;
+    (env, usdt, user)
+}
+
+#[tokio::test]
+async fn emode_raises_borrowing_power_within_the_category() {
+    let (mut env, usdt, user) = setup().await;
+    let usdc = env.usdc;
+    env.deposit(&user, &usdc, 100 * USDC).await.unwrap();
+
+    let result = env.borrow(&user, &usdc, &usdt, 85 * USDC).await;
+    assert_error(result, ErrorCode::OverBorrowableAmount);
+
+    env.send(
+        &[ix::set_user_emode(
+            user.pubkey(),
+            STABLECOINS,
+            &[usdc.health_account()],
+        )],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.user(&user.pubkey()).await.emode_category, STABLECOINS);
+    env.borrow(&user, &usdc, &usdt, 85 * USDC).await.unwrap();
+    let result = env.borrow(&user, &usdc, &usdt, 10 * USDC).await;
+    assert_error(result, ErrorCode::OverBorrowableAmount);
+}
+
+#[tokio::test]
+async fn collateral_outside_the_category_keeps_its_own_weights() {
+    let (mut env, usdt, user) = setup().await;
+    let sol = env.sol;
+    env.send(
+        &[ix::set_user_emode(user.pubkey(), STABLECOINS, &[])],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    env.deposit(&user, &sol, SOL).await.unwrap();
+
+    // 1 SOL is worth $100, so 75% LTV rather than the category's 90%.
+    let result = env.borrow(&user, &sol, &usdt, 85 * USDC).await;
+    assert_error(result, ErrorCode::OverBorrowableAmount);
+    env.borrow(&user, &sol, &usdt, 70 * USDC).await.unwrap();
+}
+
+#[tokio::test]
+async fn leaving_emode_requires_a_healthy_position() {
+    let (mut env, usdt, user) = setup().await;
+    let usdc = env.usdc;
+    env.deposit(&user, &usdc, 100 * USDC).await.unwrap();
+    env.send(
+        &[ix::set_user_emode(
+            user.pubkey(),
+            STABLECOINS,
+            &[usdc.health_account()],
+        )],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    env.borrow(&user, &usdc, &usdt, 85 * USDC).await.unwrap();
+
+    let accounts = [usdc.health_account(), usdt.health_account()];
+    let result = env
+        .send(&[ix::set_user_emode(user.pubkey(), 0, &accounts)], &[&user])
+        .await;
+    assert_error(result, ErrorCode::UnhealthyPosition);
+
+    env.repay(&user, &usdt, 20 * USDC).await.unwrap();
+    env.send(&[ix::set_user_emode(user.pubkey(), 0, &accounts)], &[&user])
+        .await
+        .unwrap();
+    assert_eq!(env.user(&user.pubkey()).await.emode_category, 0);
+}
+
+#[tokio::test]
+async fn users_can_only_join_existing_categories() {
+    let (mut env, _, user) = setup().await;
+    let result = env
+        .send(&[ix::set_user_emode(user.pubkey(), 2, &[])], &[&user])
+        .await;
+    assert_error(result, ErrorCode::InvalidEmodeCategory);
+
+    let admin = env.admin.pubkey();
+    env.send(&[ix::remove_emode_category(admin, STABLECOINS)], &[])
+        .await
+        .unwrap();
+    let result = env
+        .send(
+            &[ix::set_user_emode(user.pubkey(), STABLECOINS, &[])],
+            &[&user],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidEmodeCategory);
+}
+
+#[tokio::test]
+async fn emode_categories_are_validated() {
+    let (mut env, _, _) = setup().await;
+    let admin = env.admin.pubkey();
+    for (id, max_ltv, liquidation_threshold) in [(0, 0.9, 0.95), (2, 0.96, 0.95), (2, 0.9, 1.0)] {
+        let result = env
+            .send(
+                &[ix::set_emode_category(
+                    admin,
+                    id,
+                    "invalid".to_string(),
+                    max_ltv,
+                    liquidation_threshold,
+                )],
+                &[],
+            )
+            .await;
+        assert_error(result, ErrorCode::InvalidEmodeCategory);
+    }
+
+    env.send(
+        &[ix::set_emode_category(
+            admin,
+            STABLECOINS,
+            "stables".to_string(),
+            0.85,
+            0.9,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let market: Market = env.anchor_account(find_market_address().0).await;
+    assert_eq!(market.emode_categories.len(), 1);
+    assert_eq!(market.emode_categories[0].name, "stables");
+    assert_eq!(market.emode_categories[0].max_ltv, 0.85);
+}
+
+#[tokio::test]
+async fn banks_can_only_join_existing_categories() {
+    let (mut env, _, _) = setup().await;
+    let sol = env.sol;
+    let admin = env.admin.pubkey();
+    let join = |emode_category| {
+        let params = BankConfigParams {
+            emode_category: Some(emode_category),
+            ..Default::default()
+        };
+        ix::update_bank_config(admin, sol.mint, params)
+    };
+
+    let result = env.send(&[join(2)], &[]).await;
+    assert_error(result, ErrorCode::InvalidBankConfig);
+    env.send(&[join(STABLECOINS)], &[]).await.unwrap();
+    env.send(&[join(0)], &[]).await.unwrap();
+    assert_eq!(env.bank(&sol).await.config.emode_category, 0);
+}
+
+#[tokio::test]
+async fn emode_categories_are_capped() {
+    let (mut env, _, _) = setup().await;
+    let admin = env.admin.pubkey();
+    let set_category =
+        |id: u8| ix::set_emode_category(admin, id, format!("category {id}"), 0.9, 0.95);
+    for id in 2..=MAX_EMODE_CATEGORIES as u8 {
+        env.send(&[set_category(id)], &[]).await.unwrap();
+    }
+
+    let result = env
+        .send(&[set_category(MAX_EMODE_CATEGORIES as u8 + 1)], &[])
+        .await;
+    assert_error(result, ErrorCode::EmodeCategoriesFull);
+    // Existing categories can still be updated.
+    env.send(&[set_category(STABLECOINS)], &[]).await.unwrap();
+}
@@ -0,0 +1,187 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use lending_client::instructions as ix;
+use lending_dapp::{events, DEFAULT_FLASH_LOAN_FEE_BPS, USDT_USD_FEED_ID};
+use pyth_solana_receiver_sdk::price_update::get_feed_id_from_hex;
+use solana_sdk::signature::Signer;
+
+fn assert_close(actual: Option<f64>, expected: f64) {
+    let actual = actual.expect("the event carries a health factor");
+    assert!(
+        (actual - expected).abs() < 1e-6,
+        "expected a health factor of {expected}, got {actual}"
+    );
+}
+
+#[tokio::test]
+async fn deposit_event_is_valued_when_given_the_health_accounts() {
+    let mut env = TestEnv::new().await;
+    let sol = env.sol;
+    let user = env.create_user(0, SOL).await;
+    let owner = user.pubkey();
+
+    let instruction = ix::deposit(owner, sol.keys(), SOL / 2, &[]);
+    let emitted = env
+        .send_with_events(&[instruction], &[&user])
+        .await
+        .unwrap();
+    let deposit: events::Deposit = decode_event(&emitted);
+    assert_eq!((deposit.user, deposit.bank), (owner, sol.bank()));
+    assert_eq!(deposit.amount, SOL / 2);
+    assert_eq!((deposit.price, deposit.health_factor), (None, None));
+
+    let instruction = ix::deposit(owner, sol.keys(), SOL / 2, &[sol.health_account()]);
+    let emitted = env
+        .send_with_events(&[instruction], &[&user])
+        .await
+        .unwrap();
+    let deposit: events::Deposit = decode_event(&emitted);
+    assert_eq!(deposit.price, Some(SOL_PRICE));
+    assert_eq!(deposit.health_factor, Some(f64::MAX));
+}
+
+#[tokio::test]
+async fn withdraw_and_repay_events_report_the_health_they_leave() {
+    let mut env = TestEnv::new().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    let user = env.create_user(0, SOL).await;
+    let owner = user.pubkey();
+    env.deposit(&user, &sol, SOL).await.unwrap();
+    env.borrow(&user, &sol, &usdc, 50 * USDC).await.unwrap();
+    let health_accounts = env.health_accounts(&owner).await;
+
+    // $75 of SOL left at an 80% threshold against $50 of debt.
+    let instruction = ix::withdraw(owner, sol.keys(), SOL / 4, &health_accounts);
+    let emitted = env
+        .send_with_events(&[instruction], &[&user])
+        .await
+        .unwrap();
+    let withdraw: events::Withdraw = decode_event(&emitted);
+    assert_eq!(withdraw.amount, SOL / 4);
+    assert_eq!(withdraw.price, Some(SOL_PRICE));
+    assert_close(withdraw.health_factor, 60.0 / 50.0);
+
+    // Repaying $10 leaves $40 of debt against the same $60.
+    let instruction = ix::repay(owner, usdc.keys(), 10 * USDC, None, &health_accounts);
+    let emitted = env
+        .send_with_events(&[instruction], &[&user])
+        .await
+        .unwrap();
+    let repay: events::Repay = decode_event(&emitted);
+    assert_eq!((repay.bank, repay.amount), (usdc.bank(), 10 * USDC));
+    assert_eq!(repay.price, Some(USDC_PRICE));
+    assert_close(repay.health_factor, 60.0 / 40.0);
+}
+
+#[tokio::test]
+async fn liquidate_event_reports_the_health_after_the_seizure() {
+    let mut env = TestEnv::new().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    let borrower = env.create_user(0, SOL).await;
+    env.deposit(&borrower, &sol, SOL).await.unwrap();
+    env.borrow(&borrower, &sol, &usdc, 70 * USDC).await.unwrap();
+    let liquidator = env.create_user(100 * USDC, 0).await;
+    env.set_price(&sol, 80 * 100_000_000).await;
+
+    let instruction = ix::liquidate(
+        liquidator.pubkey(),
+        borrower.pubkey(),
+        sol.priced(),
+        usdc.priced(),
+        &[],
+    );
+    let emitted = env
+        .send_with_events(&[instruction], &[&liquidator])
+        .await
+        .unwrap();
+    let liquidate: events::Liquidate = decode_event(&emitted);
+    assert_eq!(liquidate.repaid_amount, 35 * USDC);
+    assert_eq!(liquidate.seized_amount, 459_375_000);
+    assert_eq!(liquidate.collateral_price, 80 * 100_000_000);
+    // 0.540625 SOL at $80 and an 80% threshold against the $35 left, up
+    // from $64 against $70 before the liquidation.
+    assert_close(Some(liquidate.health_factor), 34.6 / 35.0);
+}
+
+#[tokio::test]
+async fn flash_loan_events_report_the_loan_and_its_fee() {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    let user = env.create_user(10 * USDC, 0).await;
+    let owner = user.pubkey();
+
+    let emitted = env
+        .send_with_events(
+            &[
+                ix::flash_borrow(owner, usdc.keys(), 500 * USDC),
+                ix::flash_repay(owner, usdc.keys(), 500 * USDC),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    let borrow: events::FlashBorrow = decode_event(&emitted);
+    assert_eq!(
+        (borrow.user, borrow.bank, borrow.amount),
+        (owner, usdc.bank(), 500 * USDC)
+    );
+    let repay: events::FlashRepay = decode_event(&emitted);
+    assert_eq!(repay.amount, 500 * USDC);
+    assert_eq!(
+        repay.fee,
+        500 * USDC * DEFAULT_FLASH_LOAN_FEE_BPS as u64 / 10_000
+    );
+}
+
+#[tokio::test]
+async fn market_updates_emit_events() {
+    let mut env = TestEnv::new().await;
+    let admin = env.admin.pubkey();
+    let swap_program = Pubkey::new_unique();
+
+    let emitted = env
+        .send_with_events(
+            &[
+                ix::set_supported_asset(admin, "BONK".to_string(), USDT_USD_FEED_ID.to_string(), 5),
+                ix::remove_supported_asset(admin, "BONK".to_string()),
+                ix::set_emode_category(admin, 1, "stablecoins".to_string(), 0.9, 0.95),
+                ix::remove_emode_category(admin, 1),
+                ix::add_swap_program(admin, swap_program),
+                ix::remove_swap_program(admin, swap_program),
+            ],
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let asset: events::SupportedAssetSet = decode_event(&emitted);
+    assert_eq!(asset.ticker_symbol, "BONK");
+    assert_eq!(
+        asset.oracle_feed_id,
+        get_feed_id_from_hex(USDT_USD_FEED_ID).unwrap()
+    );
+    assert_eq!(asset.decimals, 5);
+    let removed: events::SupportedAssetRemoved = decode_event(&emitted);
+    assert_eq!(removed.ticker_symbol, "BONK");
+
+    let category: events::EmodeCategorySet = decode_event(&emitted);
+    assert_eq!((category.id, category.name.as_str()), (1, "stablecoins"));
+    assert_eq!(
+        (category.max_ltv, category.liquidation_threshold),
+        (0.9, 0.95)
+    );
+    let removed: events::EmodeCategoryRemoved = decode_event(&emitted);
+    assert_eq!(removed.id, 1);
+
+    let swap_programs: Vec<events::SwapProgramSet> = decode_events(&emitted);
+    let changes: Vec<_> = swap_programs
+        .iter()
+        .map(|event| (event.program_id, event.allowed))
+        .collect();
+    assert_eq!(changes, [(swap_program, true), (swap_program, false)]);
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use common::*;
+use lending_client::instructions as ix;
+use lending_client::math::deposited_amount;
+use lending_dapp::error::ErrorCode;
+use lending_dapp::{BankConfigParams, DEFAULT_FLASH_LOAN_FEE_BPS};
+use solana_sdk::signature::{Keypair, Signer};
+
+/// A USDC bank with 1,000 USDC of liquidity and a user holding 10 USDC to
+/// pay flash loan fees with.
+async fn setup() -> (TestEnv, Keypair) {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    let user = env.create_user(10 * USDC, 0).await;
+    (env, user)
+}
+
+#[tokio::test]
+async fn flash_loan_charges_fee_to_depositors() {
+    let (mut env, user) = setup().await;
+    let usdc = env.usdc;
+    let owner = user.pubkey();
+
+    env.send(
+        &[
+            ix::flash_borrow(owner, usdc.keys(), 500 * USDC),
+            ix::flash_repay(owner, usdc.keys(), 500 * USDC),
+        ],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    let fee = 500 * USDC * DEFAULT_FLASH_LOAN_FEE_BPS as u64 / 10_000;
+    assert_eq!(env.token_balance(&owner, &usdc).await, 10 * USDC - fee);
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.total_deposits, 1_000 * USDC + fee);
+    assert_eq!(bank.flash_loan_amount, 0);
+}
+
+#[tokio::test]
+async fn flash_borrow_without_repay_fails() {
+    let (mut env, user) = setup().await;
+    let usdc = env.usdc;
+
+    let result = env
+        .send(
+            &[ix::flash_borrow(user.pubkey(), usdc.keys(), 500 * USDC)],
+            &[&user],
+        )
+        .await;
+    assert_error(result, ErrorCode::MissingFlashRepay);
+}
+
+#[tokio::test]
+async fn flash_repay_must_match_the_loan() {
+    let (mut env, user) = setup().await;
+    let usdc = env.usdc;
+    let owner = user.pubkey();
+
+    let result = env
+        .send(
+            &[
+                ix::flash_borrow(owner, usdc.keys(), 500 * USDC),
+                ix::flash_repay(owner, usdc.keys(), 400 * USDC),
+            ],
+            &[&user],
+        )
+        .await;
+    assert_error(result, ErrorCode::InvalidFlashRepay);
+
+    let result = env
+        .send(&[ix::flash_repay(owner, usdc.keys(), 400 * USDC)], &[&user])
+        .await;
+    assert_error(result, ErrorCode::InvalidFlashRepay);
+}
+
+#[tokio::test]
+async fn flash_borrow_through_cpi_fails() {
+    let (mut env, user) = setup().await;
+    let usdc = env.usdc;
+    let owner = user.pubkey();
+
+    let result = env
+        .send(
+            &[
+                through_cpi(ix::flash_borrow(owner, usdc.keys(), 100 * USDC)),
+                ix::flash_repay(owner, usdc.keys(), 100 * USDC),
+            ],
+            &[&user],
+        )
+        .await;
+    assert_error(result, ErrorCode::FlashLoanCpiNotAllowed);
+}
+
+#[tokio::test]
+async fn nested_flash_loans_fail() {
+    let (mut env, user) = setup().await;
+    let usdc = env.usdc;
+    let owner = user.pubkey();
+
+    let result = env
+        .send(
+            &[
+                ix::flash_borrow(owner, usdc.keys(), 100 * USDC),
+                ix::flash_borrow(owner, usdc.keys(), 100 * USDC),
+                ix::flash_repay(owner, usdc.keys(), 100 * USDC),
+                ix::flash_repay(owner, usdc.keys(), 100 * USDC),
+            ],
+            &[&user],
+        )
+        .await;
+    assert_error(result, ErrorCode::FlashLoanInProgress);
+}
+
+#[tokio::test]
+async fn paused_bank_blocks_flash_loans() {
+    let (mut env, user) = setup().await;
+    let usdc = env.usdc;
+    let owner = user.pubkey();
+    let admin = env.admin.pubkey();
+    let params = BankConfigParams {
+        paused: Some(true),
+        ..Default::default()
+    };
+    env.send(&[ix::update_bank_config(admin, usdc.mint, params)], &[])
+        .await
+        .unwrap();
+
+    let result = env
+        .send(
+            &[
+                ix::flash_borrow(owner, usdc.keys(), 100 * USDC),
+                ix::flash_repay(owner, usdc.keys(), 100 * USDC),
+            ],
+            &[&user],
+        )
+        .await;
+    assert_error(result, ErrorCode::BankPaused);
+}
+
+#[tokio::test]
+async fn first_depositor_cannot_inflate_share_price() {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let attacker = env.create_user(100_000 * USDC, 0).await;
+    let victim = env.create_user(1_000 * USDC, 0).await;
+    let owner = attacker.pubkey();
+
+    // Take the only share, then donate to the treasury so there is enough
+    // liquidity to push large flash loan fees into `total_deposits`.
+    env.deposit(&attacker, &usdc, 1).await.unwrap();
+    let donation = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &get_associated_token_address(&owner, &usdc.mint),
+        &usdc.keys().treasury(),
+        &owner,
+        &[],
+        50_000 * USDC,
+    )
+    .unwrap();
+    env.send(&[donation], &[&attacker]).await.unwrap();
+    for _ in 0..4 {
+        env.send(
+            &[
+                ix::flash_borrow(owner, usdc.keys(), 50_000 * USDC),
+                ix::flash_repay(owner, usdc.keys(), 50_000 * USDC),
+            ],
+            &[&attacker],
+        )
+        .await
+        .unwrap();
+    }
+    let fees = env.bank(&usdc).await.total_deposits - 1;
+    assert!(fees >= 100 * USDC);
+
+    // Without the virtual offset each share would be worth the whole 180 USDC
+    // of fees, and the victim's deposit would round down by up to one of them.
+    env.deposit(&victim, &usdc, 1_000 * USDC).await.unwrap();
+    let bank = env.bank(&usdc).await;
+    let account = env.user(&victim.pubkey()).await;
+    let victim_value = deposited_amount(&bank, account.balances[0].deposited_shares).unwrap();
+    assert!(1_000 * USDC - victim_value < USDC / 10);
+
+    let account = env.user(&owner).await;
+    let attacker_value = deposited_amount(&bank, account.balances[0].deposited_shares).unwrap();
+    assert!(attacker_value < fees / 1_000);
+}
@@ -0,0 +1,338 @@
+//! Drives random sequences of user actions against the program and checks
+//! the share accounting invariants after every step:
+//!
+//! - user deposit and borrow shares add up to the bank totals,
+//! - the treasury holds exactly the deposits that are not lent out, with
+//!   interest and flash loan fees credited to `total_deposits`,
+//! - the deposit share price never goes down.
+//!
+//! Transactions are allowed to fail; a rejected action must simply leave the
+//! books consistent. Set `PROPTEST_CASES` to run more cases than the default.
+
+mod common;
+
+use common::*;
+use lending_client::instructions as ix;
+use lending_client::math::{
+    borrowed_amount, calculate_token_amount, calculate_usd_value, deposited_amount,
+};
+use lending_dapp::{Balance, User};
+use proptest::prelude::*;
+use solana_sdk::signature::{Keypair, Signer};
+
+const USERS: usize = 3;
+const DEFAULT_CASES: u32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Side {
+    Usdc,
+    Sol,
+}
+
+impl Side {
+    fn other(self) -> Self {
+        match self {
+            Side::Usdc => Side::Sol,
+            Side::Sol => Side::Usdc,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Action {
+    /// Deposits `tenths` of a whole token.
+    Deposit {
+        user: usize,
+        side: Side,
+        tenths: u64,
+    },
+    /// Withdraws `bps` of the user's deposit.
+    Withdraw {
+        user: usize,
+        side: Side,
+        bps: u64,
+    },
+    /// Borrows the other asset against `bps` of the user's borrowing power
+    /// in `collateral`.
+    Borrow {
+        user: usize,
+        collateral: Side,
+        bps: u64,
+    },
+    /// Repays `bps` of the user's debt.
+    Repay {
+        user: usize,
+        side: Side,
+        bps: u64,
+    },
+    /// Moves the clock forward and refreshes both banks.
+    Accrue {
+        seconds: i64,
+    },
+    SetSolPrice {
+        dollars: i64,
+    },
+    Liquidate {
+        borrower: usize,
+        collateral: Side,
+    },
+}
+
+fn side() -> impl Strategy<Value = Side> {
+    prop_oneof![Just(Side::Usdc), Just(Side::Sol)]
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        3 => (0..USERS, side(), 1..=1_000u64)
+            .prop_map(|(user, side, tenths)| Action::Deposit { user, side, tenths }),
+        2 => (0..USERS, side(), 1..=10_000u64)
+            .prop_map(|(user, side, bps)| Action::Withdraw { user, side, bps }),
+        3 => (0..USERS, side(), 1..=10_000u64)
+            .prop_map(|(user, collateral, bps)| Action::Borrow { user, collateral, bps }),
+        2 => (0..USERS, side(), 1..=10_000u64)
+            .prop_map(|(user, side, bps)| Action::Repay { user, side, bps }),
+        1 => (1..=86_400i64).prop_map(|seconds| Action::Accrue { seconds }),
+        1 => (20..=200i64).prop_map(|dollars| Action::SetSolPrice { dollars }),
+        2 => (0..USERS, side())
+            .prop_map(|(borrower, collateral)| Action::Liquidate { borrower, collateral }),
+    ]
+}
+
+struct Harness {
+    env: TestEnv,
+    users: Vec<Keypair>,
+    liquidator: Keypair,
+    sol_price: i64,
+    /// Last deposit share price seen per bank, reset whenever the bank has
+    /// no depositors left.
+    share_prices: [u128; 2],
+}
+
+impl Harness {
+    async fn new() -> Self {
+        let mut env = TestEnv::new().await;
+        let mut users = Vec::new();
+        let (usdc, sol) = (env.usdc, env.sol);
+        for _ in 0..USERS {
+            let user = env.create_user(10_000 * USDC, 100 * SOL).await;
+            env.deposit(&user, &usdc, 1_000 * USDC).await.unwrap();
+            env.deposit(&user, &sol, 10 * SOL).await.unwrap();
+            users.push(user);
+        }
+        let liquidator = env.create_user(1_000_000 * USDC, 10_000 * SOL).await;
+        Self {
+            env,
+            users,
+            liquidator,
+            sol_price: SOL_PRICE,
+            share_prices: [0; 2],
+        }
+    }
+
+    fn asset(&self, side: Side) -> Asset {
+        match side {
+            Side::Usdc => self.env.usdc,
+            Side::Sol => self.env.sol,
+        }
+    }
+
+    fn price(&self, side: Side) -> i64 {
+        match side {
+            Side::Usdc => USDC_PRICE,
+            Side::Sol => self.sol_price,
+        }
+    }
+
+    async fn balance(&mut self, user: usize, side: Side) -> Balance {
+        let owner = self.users[user].pubkey();
+        let bank = self.asset(side).bank();
+        let account = self.env.user(&owner).await;
+        account
+            .balances
+            .into_iter()
+            .find(|balance| balance.bank_address == bank)
+            .unwrap_or_default()
+    }
+
+    async fn apply(&mut self, action: &Action) {
+        // Rejected actions are expected; only the resulting state matters.
+        let _ = match *action {
+            Action::Deposit { user, side, tenths } => {
+                let asset = self.asset(side);
+                let unit = match side {
+                    Side::Usdc => USDC,
+                    Side::Sol => SOL,
+                };
+                let user = &self.users[user];
+                let instruction = ix::deposit(user.pubkey(), asset.keys(), tenths * unit / 10, &[]);
+                self.env.send(&[instruction], &[user]).await
+            }
+            Action::Withdraw { user, side, bps } => {
+                let asset = self.asset(side);
+                let shares = self.balance(user, side).await.deposited_shares;
+                let bank = self.env.bank(&asset).await;
+                let amount = deposited_amount(&bank, shares).unwrap() * bps / 10_000;
+                let user = &self.users[user];
+                let health_accounts = self.env.health_accounts(&user.pubkey()).await;
+                let instruction =
+                    ix::withdraw(user.pubkey(), asset.keys(), amount, &health_accounts);
+                self.env.send(&[instruction], &[user]).await
+            }
+            Action::Borrow {
+                user,
+                collateral,
+                bps,
+            } => {
+                let borrowed = collateral.other();
+                let (collateral_asset, borrowed_asset) =
+                    (self.asset(collateral), self.asset(borrowed));
+                let shares = self.balance(user, collateral).await.deposited_shares;
+                let bank = self.env.bank(&collateral_asset).await;
+                let value = calculate_usd_value(
+                    deposited_amount(&bank, shares).unwrap(),
+                    self.price(collateral),
+                    bank.mint_decimals,
+                )
+                .unwrap();
+                let budget = (value as f64 * MAX_LTV) as u128 * bps as u128 / 10_000;
+                let decimals = self.env.bank(&borrowed_asset).await.mint_decimals;
+                let amount =
+                    calculate_token_amount(budget, self.price(borrowed), decimals).unwrap();
+                let user = &self.users[user];
+                let instruction = ix::borrow(
+                    user.pubkey(),
+                    collateral_asset.priced(),
+                    borrowed_asset.priced(),
+                    amount,
+                    &[],
+                );
+                self.env.send(&[instruction], &[user]).await
+            }
+            Action::Repay { user, side, bps } => {
+                let asset = self.asset(side);
+                let shares = self.balance(user, side).await.borrowed_shares;
+                let bank = self.env.bank(&asset).await;
+                let amount = borrowed_amount(&bank, shares).unwrap() * bps / 10_000;
+                let user = &self.users[user];
+                let instruction = ix::repay(user.pubkey(), asset.keys(), amount, None, &[]);
+                self.env.send(&[instruction], &[user]).await
+            }
+            Action::Accrue { seconds } => {
+                let (usdc, sol) = (self.env.usdc, self.env.sol);
+                self.env.warp(seconds).await;
+                self.env.set_price(&usdc, USDC_PRICE).await;
+                self.env.set_price(&sol, self.sol_price).await;
+                let instructions = [ix::refresh_bank(usdc.bank()), ix::refresh_bank(sol.bank())];
+                self.env.send(&instructions, &[]).await
+            }
+            Action::SetSolPrice { dollars } => {
+                let sol = self.env.sol;
+                self.sol_price = dollars * 100_000_000;
+                self.env.set_price(&sol, self.sol_price).await;
+                Ok(())
+            }
+            Action::Liquidate {
+                borrower,
+                collateral,
+            } => {
+                let instruction = ix::liquidate(
+                    self.liquidator.pubkey(),
+                    self.users[borrower].pubkey(),
+                    self.asset(collateral).priced(),
+                    self.asset(collateral.other()).priced(),
+                    &[],
+                );
+                let liquidator = &self.liquidator;
+                self.env.send(&[instruction], &[liquidator]).await
+            }
+        };
+    }
+
+    async fn check_invariants(&mut self, step: usize) {
+        let mut accounts: Vec<User> = Vec::new();
+        for owner in self
+            .users
+            .iter()
+            .chain([&self.liquidator])
+            .map(Signer::pubkey)
+            .collect::<Vec<_>>()
+        {
+            accounts.push(self.env.user(&owner).await);
+        }
+        for side in [Side::Usdc, Side::Sol] {
+            let asset = self.asset(side);
+            let bank = self.env.bank(&asset).await;
+            let balances = accounts
+                .iter()
+                .flat_map(|account| account.balances.iter())
+                .filter(|balance| balance.bank_address == asset.bank());
+            let (deposited_shares, borrowed_shares) =
+                balances.fold((0u64, 0u64), |(deposited, borrowed), balance| {
+                    (
+                        deposited + balance.deposited_shares,
+                        borrowed + balance.borrowed_shares,
+                    )
+                });
+            assert_eq!(
+                deposited_shares, bank.total_deposits_shares,
+                "step {step}: {side:?} deposit shares"
+            );
+            assert_eq!(
+                borrowed_shares, bank.total_borrowed_shares,
+                "step {step}: {side:?} borrow shares"
+            );
+
+            let treasury = self.env.treasury_balance(&asset).await;
+            assert_eq!(
+                treasury as u128 + bank.total_borrowed as u128,
+                bank.total_deposits as u128,
+                "step {step}: {side:?} treasury"
+            );
+
+            let last = &mut self.share_prices[side.index()];
+            if bank.total_deposits_shares == 0 {
+                *last = 0;
+            } else {
+                let price = bank.deposit_share_price();
+                assert!(
+                    price >= *last,
+                    "step {step}: {side:?} share price fell from {last} to {price}"
+                );
+                *last = price;
+            }
+        }
+    }
+}
+
+async fn run(actions: Vec<Action>) {
+    let mut harness = Harness::new().await;
+    for (step, action) in actions.iter().enumerate() {
+        harness.apply(action).await;
+        harness.check_invariants(step).await;
+    }
+}
+
+fn cases() -> u32 {
+    std::env::var("PROPTEST_CASES")
+        .ok()
+        .and_then(|cases| cases.parse().ok())
+        .unwrap_or(DEFAULT_CASES)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(cases()))]
+
+    #[test]
+    fn share_accounting_invariants_hold(actions in prop::collection::vec(action(), 1..24)) {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(run(actions));
+    }
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use common::*;
+use lending_client::instructions as ix;
+use lending_dapp::error::ErrorCode;
+use lending_dapp::{BankConfigParams, SOL_USD_FEED_ID};
+use solana_sdk::signature::{Keypair, Signer};
+
+const RISK: u64 = 1_000_000_000;
+
+/// Adds an isolated $1 asset with a $100 debt ceiling, counted in cents,
+/// lets USDC be borrowed against it and seeds the USDC bank. Returns the
+/// asset and a user holding 1,000 of it next to 1 SOL.
+async fn setup() -> (TestEnv, Asset, Keypair) {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let risk = env.add_asset("RISK", SOL_USD_FEED_ID, 9).await;
+    let admin = env.admin.pubkey();
+    let isolated = BankConfigParams {
+        isolated: Some(true),
+        debt_ceiling: Some(10_000),
+        ..Default::default()
+    };
+    let borrowable = BankConfigParams {
+        borrowable_in_isolation: Some(true),
+        ..Default::default()
+    };
+    env.send(
+        &[
+            ix::update_bank_config(admin, risk.mint, isolated),
+            ix::update_bank_config(admin, usdc.mint, borrowable),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    let user = env.create_user(0, SOL).await;
+    env.fund(&user.pubkey(), &risk, 1_000 * RISK).await;
+    (env, risk, user)
+}
+
+#[tokio::test]
+async fn borrows_against_isolated_collateral_respect_the_debt_ceiling() {
+    let (mut env, risk, user) = setup().await;
+    let usdc = env.usdc;
+    env.deposit(&user, &risk, 1_000 * RISK).await.unwrap();
+    assert_eq!(env.user(&user.pubkey()).await.isolated_bank, risk.bank());
+
+    // $750 of borrowing power, but only $100 of ceiling.
+    env.borrow(&user, &risk, &usdc, 80 * USDC).await.unwrap();
+    assert_eq!(env.bank(&risk).await.isolated_debt, 8_000);
+    let result = env.borrow(&user, &risk, &usdc, 30 * USDC).await;
+    assert_error(result, ErrorCode::DebtCeilingExceeded);
+
+    let result = env.repay(&user, &usdc, 50 * USDC).await;
+    assert_error(result, ErrorCode::MissingIsolatedBank);
+    env.send(
+        &[ix::repay(
+            user.pubkey(),
+            usdc.keys(),
+            50 * USDC,
+            Some(risk.bank()),
+            &[],
+        )],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.bank(&risk).await.isolated_debt, 3_000);
+    env.borrow(&user, &risk, &usdc, 60 * USDC).await.unwrap();
+    assert_eq!(env.bank(&risk).await.isolated_debt, 9_000);
+}
+
+#[tokio::test]
+async fn isolated_collateral_only_backs_allowed_stablecoins() {
+    let (mut env, risk, user) = setup().await;
+    let sol = env.sol;
+    env.seed_liquidity(&sol, SOL).await;
+    env.deposit(&user, &risk, 1_000 * RISK).await.unwrap();
+
+    let result = env.borrow(&user, &risk, &sol, SOL / 10).await;
+    assert_error(result, ErrorCode::NotBorrowableInIsolation);
+}
+
+#[tokio::test]
+async fn isolated_users_cannot_borrow_against_another_bank() {
+    let (mut env, risk, user) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.seed_liquidity(&sol, SOL).await;
+    env.deposit(&user, &risk, 1_000 * RISK).await.unwrap();
+    env.borrow(&user, &risk, &usdc, 80 * USDC).await.unwrap();
+
+    // The USDC bank only holds the user's debt, and naming it as collateral
+    // must not skip the ceiling or the stablecoin allowlist.
+    let result = env.borrow(&user, &usdc, &sol, SOL / 2).await;
+    assert_error(result, ErrorCode::MissingIsolatedBank);
+    assert_eq!(env.bank(&risk).await.isolated_debt, 8_000);
+}
+
+#[tokio::test]
+async fn isolated_collateral_cannot_be_combined() {
+    let (mut env, risk, user) = setup().await;
+    let sol = env.sol;
+
+    env.deposit(&user, &sol, SOL).await.unwrap();
+    let result = env.deposit(&user, &risk, 1_000 * RISK).await;
+    assert_error(result, ErrorCode::IsolatedCollateralMixed);
+
+    env.withdraw(&user, &sol, SOL).await.unwrap();
+    env.deposit(&user, &risk, 1_000 * RISK).await.unwrap();
+    let result = env.deposit(&user, &sol, SOL).await;
+    assert_error(result, ErrorCode::IsolatedCollateralMixed);
+
+    // Withdrawing the isolated collateral leaves isolation mode.
+    env.withdraw(&user, &risk, 1_000 * RISK).await.unwrap();
+    env.deposit(&user, &sol, SOL).await.unwrap();
+    assert_eq!(
+        env.user(&user.pubkey()).await.isolated_bank,
+        Default::default()
+    );
+}
+
+#[tokio::test]
+async fn isolated_bank_cannot_be_borrowable_in_isolation() {
+    let (mut env, risk, _) = setup().await;
+    let params = BankConfigParams {
+        borrowable_in_isolation: Some(true),
+        ..Default::default()
+    };
+    let admin = env.admin.pubkey();
+    let result = env
+        .send(&[ix::update_bank_config(admin, risk.mint, params)], &[])
+        .await;
+    assert_error(result, ErrorCode::InvalidBankConfig);
+}
+
+#[tokio::test]
+async fn isolation_cannot_change_while_the_bank_is_in_use() {
+    let (mut env, risk, user) = setup().await;
+    let admin = env.admin.pubkey();
+    let isolated = |isolated| {
+        let params = BankConfigParams {
+            isolated: Some(isolated),
+            ..Default::default()
+        };
+        ix::update_bank_config(admin, risk.mint, params)
+    };
+    env.deposit(&user, &risk, 1_000 * RISK).await.unwrap();
+
+    let result = env.send(&[isolated(false)], &[]).await;
+    assert_error(result, ErrorCode::InvalidBankConfig);
+    // Restating the current mode is not a change.
+    env.send(&[isolated(true)], &[]).await.unwrap();
+
+    env.withdraw(&user, &risk, 1_000 * RISK).await.unwrap();
+    env.send(&[isolated(false)], &[]).await.unwrap();
+    assert!(!env.bank(&risk).await.config.isolated);
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::*;
+use lending_client::instructions as ix;
+use lending_dapp::error::ErrorCode;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// Seeds the USDC bank, fills the swap pool with SOL and returns a user
+/// holding 1 SOL, worth $100.
+async fn setup() -> (TestEnv, Keypair) {
+    let mut env = TestEnv::new().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    env.fund_swap_pool(&usdc, 0).await;
+    env.fund_swap_pool(&sol, 10 * SOL).await;
+    let user = env.create_user(0, SOL).await;
+    (env, user)
+}
+
+/// Levers the user's SOL up to `target_leverage_bps` with USDC debt, the
+/// swap paying `amount_out` SOL for the borrowed USDC.
+fn leverage(
+    env: &TestEnv,
+    user: &Keypair,
+    target_leverage_bps: u64,
+    max_slippage_bps: u16,
+    amount_out: u64,
+) -> Instruction {
+    let (usdc, sol) = (env.usdc, env.sol);
+    let borrowed = 100 * USDC * (target_leverage_bps - 10_000) / 10_000;
+    let swap = mock_swap(&user.pubkey(), &usdc, &sol, borrowed, amount_out);
+    ix::leverage(
+        user.pubkey(),
+        sol.priced(),
+        usdc.priced(),
+        SOL,
+        target_leverage_bps,
+        max_slippage_bps,
+        &swap,
+        &[],
+    )
+}
+
+#[tokio::test]
+async fn leverage_reaches_the_target_exposure() {
+    let (mut env, user) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    let instruction = leverage(&env, &user, 20_000, 100, SOL);
+    env.send(&[instruction], &[&user]).await.unwrap();
+
+    // 2x on $100 of SOL: $100 of USDC debt bought a second SOL.
+    let mut account = env.user(&user.pubkey()).await;
+    assert_eq!(account.get_balance(&sol.bank()).unwrap().deposited, 2 * SOL);
+    assert_eq!(
+        account.get_balance(&usdc.bank()).unwrap().borrowed,
+        100 * USDC
+    );
+    assert_eq!(env.token_balance(&user.pubkey(), &sol).await, 0);
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.total_borrowed, 100 * USDC);
+    assert_eq!(bank.flash_loan_amount, 0);
+}
+
+#[tokio::test]
+async fn leverage_rejects_slippage_past_the_bound() {
+    let (mut env, user) = setup().await;
+    // 5% short of the oracle-implied SOL, with 1% allowed.
+    let instruction = leverage(&env, &user, 20_000, 100, 95 * SOL / 100);
+    let result = env.send(&[instruction], &[&user]).await;
+    assert_error(result, ErrorCode::SlippageExceeded);
+}
+
+#[tokio::test]
+async fn leverage_rejects_an_unhealthy_position() {
+    let (mut env, user) = setup().await;
+    // 5x leaves $400 of debt against $375 of borrowing power.
+    let instruction = leverage(&env, &user, 50_000, 100, 4 * SOL);
+    let result = env.send(&[instruction], &[&user]).await;
+    assert_error(result, ErrorCode::OverBorrowableAmount);
+}
+
+#[tokio::test]
+async fn leverage_rejects_invalid_parameters() {
+    let (mut env, user) = setup().await;
+    // No leverage at 1x, and slippage past 100%.
+    for (target_leverage_bps, max_slippage_bps) in [(10_000, 100), (20_000, 10_001)] {
+        let instruction = leverage(&env, &user, target_leverage_bps, max_slippage_bps, SOL);
+        let result = env.send(&[instruction], &[&user]).await;
+        assert_error(result, ErrorCode::InvalidLeverage);
+    }
+}
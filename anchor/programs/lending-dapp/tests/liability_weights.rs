@@ -0,0 +1,85 @@
+mod common;
+
+use common::*;
+use lending_client::instructions as ix;
+use lending_dapp::error::ErrorCode;
+use lending_dapp::BankConfigParams;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// Weighs USDC debt at 1.25 for new borrows and 1.2 for liquidation, seeds
+/// the USDC bank and returns a borrower with 1 SOL of collateral.
+async fn setup() -> (TestEnv, Keypair) {
+    let mut env = TestEnv::new().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    let admin = env.admin.pubkey();
+    let params = BankConfigParams {
+        liability_weight_init: Some(1.25),
+        liability_weight_maint: Some(1.2),
+        ..Default::default()
+    };
+    env.send(&[ix::update_bank_config(admin, usdc.mint, params)], &[])
+        .await
+        .unwrap();
+
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    let borrower = env.create_user(0, SOL).await;
+    env.deposit(&borrower, &sol, SOL).await.unwrap();
+    (env, borrower)
+}
+
+#[tokio::test]
+async fn init_liability_weight_limits_new_borrows() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+
+    // $75 of borrowing power covers $60 of debt weighted at 1.25.
+    let result = env.borrow(&borrower, &sol, &usdc, 61 * USDC).await;
+    assert_error(result, ErrorCode::OverBorrowableAmount);
+    env.borrow(&borrower, &sol, &usdc, 60 * USDC).await.unwrap();
+}
+
+#[tokio::test]
+async fn maintenance_liability_weight_drives_liquidation() {
+    let (mut env, borrower) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.borrow(&borrower, &sol, &usdc, 60 * USDC).await.unwrap();
+    let liquidator = env.create_user(100 * USDC, 0).await;
+    let liquidate = [ix::liquidate(
+        liquidator.pubkey(),
+        borrower.pubkey(),
+        sol.priced(),
+        usdc.priced(),
+        &[],
+    )];
+
+    // At $92 the collateral backs $73.60, above the $72 of weighted debt.
+    env.set_price(&sol, 92 * 100_000_000).await;
+    let result = env.send(&liquidate, &[&liquidator]).await;
+    assert_error(result, ErrorCode::NotUnderCollateralized);
+
+    // At $88 it backs $70.40: more than the raw debt, less than its weight.
+    env.set_price(&sol, 88 * 100_000_000).await;
+    env.send(&liquidate, &[&liquidator]).await.unwrap();
+    assert!(env.token_balance(&liquidator.pubkey(), &sol).await > 0);
+}
+
+#[tokio::test]
+async fn liability_weights_are_validated() {
+    let (mut env, _) = setup().await;
+    let usdc = env.usdc;
+    let admin = env.admin.pubkey();
+    for (init, maint) in [(Some(0.9), Some(0.9)), (Some(1.1), None), (None, Some(1.3))] {
+        let params = BankConfigParams {
+            liability_weight_init: init,
+            liability_weight_maint: maint,
+            ..Default::default()
+        };
+        let result = env
+            .send(&[ix::update_bank_config(admin, usdc.mint, params)], &[])
+            .await;
+        assert_error(result, ErrorCode::InvalidBankConfig);
+    }
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.liability_weight_init, 1.25);
+    assert_eq!(bank.liability_weight_maint, 1.2);
+}
@@ -0,0 +1,214 @@
+mod common;
+
+use common::*;
+use lending_client::instructions::{self as ix, HealthAccount};
+use lending_client::math::{borrowed_amount, calculate_liquidation_amounts, deposited_amount};
+use lending_dapp::error::ErrorCode;
+use lending_dapp::events::InterestAccrued;
+use lending_dapp::USDT_USD_FEED_ID;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// A borrower with 1 SOL of collateral and 70 USDC of debt, and a liquidator
+/// holding 100 USDC.
+async fn setup() -> (TestEnv, Keypair, Keypair) {
+    let mut env = TestEnv::new().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    let borrower = env.create_user(0, SOL).await;
+    env.deposit(&borrower, &sol, SOL).await.unwrap();
+    env.borrow(&borrower, &sol, &usdc, 70 * USDC).await.unwrap();
+    let liquidator = env.create_user(100 * USDC, 0).await;
+    (env, borrower, liquidator)
+}
+
+async fn liquidate(
+    env: &mut TestEnv,
+    liquidator: &Keypair,
+    borrower: &Keypair,
+    collateral: &Asset,
+    borrowed: &Asset,
+    health_accounts: &[HealthAccount],
+) -> Result<(), solana_program_test::BanksClientError> {
+    env.send(
+        &[ix::liquidate(
+            liquidator.pubkey(),
+            borrower.pubkey(),
+            collateral.priced(),
+            borrowed.priced(),
+            health_accounts,
+        )],
+        &[liquidator],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn healthy_position_cannot_be_liquidated() {
+    let (mut env, borrower, liquidator) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+
+    let result = liquidate(&mut env, &liquidator, &borrower, &sol, &usdc, &[]).await;
+    assert_error(result, ErrorCode::NotUnderCollateralized);
+}
+
+#[tokio::test]
+async fn price_drop_allows_liquidation() {
+    let (mut env, borrower, liquidator) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+
+    // At $80 the collateral is worth $64 after the liquidation threshold,
+    // below the $70 of debt.
+    env.set_price(&sol, 80 * 100_000_000).await;
+    liquidate(&mut env, &liquidator, &borrower, &sol, &usdc, &[])
+        .await
+        .unwrap();
+
+    // Half the debt is repaid and the liquidator receives its value plus a
+    // 5% bonus in SOL: 35 * 1.05 / 80.
+    assert_eq!(
+        env.token_balance(&liquidator.pubkey(), &usdc).await,
+        65 * USDC
+    );
+    assert_eq!(
+        env.token_balance(&liquidator.pubkey(), &sol).await,
+        459_375_000
+    );
+    let usdc_bank = env.bank(&usdc).await;
+    assert_eq!(usdc_bank.total_borrowed, 35 * USDC);
+    let sol_bank = env.bank(&sol).await;
+    assert_eq!(sol_bank.total_deposits, SOL - 459_375_000);
+    let account = env.user(&borrower.pubkey()).await;
+    let collateral = account
+        .balances
+        .iter()
+        .find(|balance| balance.bank_address == sol.bank())
+        .unwrap();
+    assert_eq!(collateral.deposited_shares, sol_bank.total_deposits_shares);
+}
+
+#[tokio::test]
+async fn seizure_is_capped_by_collateral() {
+    let (mut env, borrower, liquidator) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+
+    // Half the debt plus the bonus is worth more than the whole $30 of
+    // collateral, so the liquidator only repays what the collateral covers.
+    env.set_price(&sol, 30 * 100_000_000).await;
+    liquidate(&mut env, &liquidator, &borrower, &sol, &usdc, &[])
+        .await
+        .unwrap();
+    assert_eq!(env.token_balance(&liquidator.pubkey(), &sol).await, SOL);
+    let repaid = 100 * USDC - env.token_balance(&liquidator.pubkey(), &usdc).await;
+    assert_eq!(repaid, 28_571_428);
+    assert_eq!(env.bank(&sol).await.total_deposits, 0);
+}
+
+#[tokio::test]
+async fn client_sizing_matches_the_liquidation() {
+    // At $30 the collateral cannot cover half the debt plus the bonus.
+    for price in [80, 30] {
+        let (mut env, borrower, liquidator) = setup().await;
+        let (usdc, sol) = (env.usdc, env.sol);
+        env.set_price(&sol, price * 100_000_000).await;
+        let (sol_bank, usdc_bank) = (env.bank(&sol).await, env.bank(&usdc).await);
+        let account = env.user(&borrower.pubkey()).await;
+        let balance = |asset: &Asset| {
+            *account
+                .balances
+                .iter()
+                .find(|balance| balance.bank_address == asset.bank())
+                .unwrap()
+        };
+        let collateral = deposited_amount(&sol_bank, balance(&sol).deposited_shares).unwrap();
+        let debt = borrowed_amount(&usdc_bank, balance(&usdc).borrowed_shares).unwrap();
+        let expected = calculate_liquidation_amounts(
+            &sol_bank,
+            price * 100_000_000,
+            collateral,
+            &usdc_bank,
+            USDC_PRICE,
+            debt,
+        )
+        .unwrap();
+
+        liquidate(&mut env, &liquidator, &borrower, &sol, &usdc, &[])
+            .await
+            .unwrap();
+        let repaid = 100 * USDC - env.token_balance(&liquidator.pubkey(), &usdc).await;
+        let seized = env.token_balance(&liquidator.pubkey(), &sol).await;
+        assert_eq!((repaid, seized), expected);
+    }
+}
+
+#[tokio::test]
+async fn liquidation_accrues_interest_on_the_collateral_bank() {
+    let (mut env, borrower, liquidator) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    // SOL borrowed by someone else earns the borrower's collateral interest.
+    let sol_borrower = env.create_user(1_000 * USDC, 0).await;
+    env.deposit(&sol_borrower, &usdc, 1_000 * USDC)
+        .await
+        .unwrap();
+    env.borrow(&sol_borrower, &usdc, &sol, SOL / 2)
+        .await
+        .unwrap();
+    let before = env.bank(&sol).await;
+
+    env.warp(60 * 60).await;
+    env.set_price(&usdc, USDC_PRICE).await;
+    env.set_price(&sol, 80 * 100_000_000).await;
+    let emitted = env
+        .send_with_events(
+            &[ix::liquidate(
+                liquidator.pubkey(),
+                borrower.pubkey(),
+                sol.priced(),
+                usdc.priced(),
+                &[],
+            )],
+            &[&liquidator],
+        )
+        .await
+        .unwrap();
+    let after = env.bank(&sol).await;
+    assert_eq!(
+        after.last_updated_borrow,
+        before.last_updated_borrow + 60 * 60
+    );
+    assert!(after.total_borrowed > before.total_borrowed);
+    let accrued: Vec<InterestAccrued> = decode_events(&emitted);
+    let collateral_interest = accrued
+        .iter()
+        .find(|event| event.bank == sol.bank())
+        .unwrap();
+    assert_eq!(
+        collateral_interest.interest,
+        after.total_borrowed - before.total_borrowed
+    );
+}
+
+#[tokio::test]
+async fn same_bank_cannot_be_liquidated_against_itself() {
+    let (mut env, borrower, liquidator) = setup().await;
+    let usdc = env.usdc;
+
+    let result = liquidate(&mut env, &liquidator, &borrower, &usdc, &usdc, &[]).await;
+    assert_error(result, ErrorCode::InvalidCollateralMint);
+}
+
+#[tokio::test]
+async fn liquidation_requires_every_position_priced() {
+    let (mut env, borrower, liquidator) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    let usdt = env.add_asset("USDT", USDT_USD_FEED_ID, 6).await;
+    env.set_price(&sol, 80 * 100_000_000).await;
+
+    // Seizing from a bank the borrower has no deposit in leaves the SOL
+    // position out of the health check unless it is passed explicitly.
+    let result = liquidate(&mut env, &liquidator, &borrower, &usdt, &usdc, &[]).await;
+    assert_error(result, ErrorCode::MissingHealthAccount);
+
+    let health = [sol.health_account()];
+    let result = liquidate(&mut env, &liquidator, &borrower, &usdt, &usdc, &health).await;
+    assert_error(result, ErrorCode::NothingToLiquidate);
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use common::*;
+use lending_client::instructions as ix;
+use lending_client::pda::find_wrapped_sol_address;
+use lending_dapp::error::ErrorCode;
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn deposit_and_withdraw_sol_move_lamports() {
+    let mut env = TestEnv::new().await;
+    let wsol = env.add_wrapped_sol_asset().await;
+    let user = env.create_user(0, 0).await;
+    let owner = user.pubkey();
+    let wrapped = find_wrapped_sol_address(&owner).0;
+    let lamports = env.lamports(&owner).await;
+
+    env.send(
+        &[ix::deposit_sol(owner, wsol.keys(), SOL / 2, &[])],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.lamports(&owner).await, lamports - SOL / 2);
+    assert_eq!(env.lamports(&wrapped).await, 0);
+    assert_eq!(env.treasury_balance(&wsol).await, SOL / 2);
+    let bank = env.bank(&wsol).await;
+    assert_eq!(bank.total_deposits, SOL / 2);
+    let account = env.user(&owner).await;
+    let balance = account
+        .balances
+        .iter()
+        .find(|balance| balance.bank_address == wsol.bank())
+        .unwrap();
+    assert_eq!(balance.deposited, SOL / 2);
+
+    env.send(
+        &[ix::withdraw_sol(owner, wsol.keys(), SOL / 5, &[])],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.lamports(&owner).await, lamports - SOL / 2 + SOL / 5);
+    assert_eq!(env.lamports(&wrapped).await, 0);
+    assert_eq!(env.treasury_balance(&wsol).await, SOL / 2 - SOL / 5);
+    assert_eq!(env.bank(&wsol).await.total_deposits, SOL / 2 - SOL / 5);
+}
+
+#[tokio::test]
+async fn borrow_sol_pays_out_lamports() {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let wsol = env.add_wrapped_sol_asset().await;
+    let lender = env.create_user(0, 0).await;
+    env.send(
+        &[ix::deposit_sol(
+            lender.pubkey(),
+            wsol.keys(),
+            9 * SOL / 10,
+            &[],
+        )],
+        &[&lender],
+    )
+    .await
+    .unwrap();
+    let borrower = env.create_user(1_000 * USDC, 0).await;
+    env.deposit(&borrower, &usdc, 100 * USDC).await.unwrap();
+    let owner = borrower.pubkey();
+    let lamports = env.lamports(&owner).await;
+
+    // $100 of USDC at a 75% max LTV covers at most 0.75 SOL at $100.
+    let result = env
+        .send(
+            &[ix::borrow_sol(
+                owner,
+                usdc.priced(),
+                wsol.priced(),
+                8 * SOL / 10,
+                &[],
+            )],
+            &[&borrower],
+        )
+        .await;
+    assert_error(result, ErrorCode::OverBorrowableAmount);
+
+    env.send(
+        &[ix::borrow_sol(
+            owner,
+            usdc.priced(),
+            wsol.priced(),
+            SOL / 4,
+            &[],
+        )],
+        &[&borrower],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.lamports(&owner).await, lamports + SOL / 4);
+    assert_eq!(env.lamports(&find_wrapped_sol_address(&owner).0).await, 0);
+    assert_eq!(env.bank(&wsol).await.total_borrowed, SOL / 4);
+}
+
+#[tokio::test]
+async fn native_sol_instructions_reject_other_banks() {
+    let mut env = TestEnv::new().await;
+    let sol = env.sol;
+    let user = env.create_user(0, 0).await;
+
+    let result = env
+        .send(
+            &[ix::deposit_sol(user.pubkey(), sol.keys(), SOL / 2, &[])],
+            &[&user],
+        )
+        .await;
+    assert_error(result, ErrorCode::NotNativeMint);
+}
@@ -0,0 +1,344 @@
+mod common;
+
+use anchor_lang::AccountSerialize;
+use anchor_spl::associated_token::spl_associated_token_account::instruction as ata_instruction;
+use anchor_spl::token::spl_token;
+use common::*;
+use lending_client::instructions as ix;
+use lending_dapp::error::ErrorCode;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// Enables receipts on the USDC bank and returns a user holding 1,000 USDC.
+async fn setup() -> (TestEnv, Keypair) {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let admin = env.admin.pubkey();
+    env.send(&[ix::init_receipt_mint(admin, usdc.keys())], &[])
+        .await
+        .unwrap();
+    let user = env.create_user(1_000 * USDC, 0).await;
+    (env, user)
+}
+
+async fn receipt_balance(env: &mut TestEnv, owner: &Keypair, asset: &Asset) -> u64 {
+    env.token_account_balance(asset.keys().user_receipt_account(&owner.pubkey()))
+        .await
+}
+
+#[tokio::test]
+async fn init_receipt_mint_records_the_mint() {
+    let (mut env, _) = setup().await;
+    let usdc = env.usdc;
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.receipt_mint, usdc.keys().receipt_mint());
+}
+
+#[tokio::test]
+async fn only_the_bank_authority_can_enable_receipts() {
+    let mut env = TestEnv::new().await;
+    let sol = env.sol;
+    let user = env.create_user(0, 0).await;
+
+    let result = env
+        .send(
+            &[ix::init_receipt_mint(user.pubkey(), sol.keys())],
+            &[&user],
+        )
+        .await;
+    assert_error(result, ErrorCode::Unauthorized);
+}
+
+#[tokio::test]
+async fn mint_receipt_then_redeem_round_trips() {
+    let (mut env, user) = setup().await;
+    let usdc = env.usdc;
+    let owner = user.pubkey();
+
+    env.send(
+        &[ix::mint_receipt(owner, usdc.keys(), 400 * USDC)],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    assert_eq!(receipt_balance(&mut env, &user, &usdc).await, 400 * USDC);
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.total_deposits, 400 * USDC);
+    assert_eq!(bank.total_deposits_shares, 400 * USDC);
+    assert_eq!(env.user(&owner).await.balances[0].deposited_shares, 0);
+
+    env.send(&[ix::redeem(owner, usdc.keys(), 150 * USDC)], &[&user])
+        .await
+        .unwrap();
+    assert_eq!(receipt_balance(&mut env, &user, &usdc).await, 250 * USDC);
+    assert_eq!(env.token_balance(&owner, &usdc).await, 750 * USDC);
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.total_deposits, 250 * USDC);
+    assert_eq!(bank.total_deposits_shares, 250 * USDC);
+}
+
+#[tokio::test]
+async fn transferred_receipts_can_back_a_borrow() {
+    let (mut env, user) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.seed_liquidity(&sol, 10 * SOL).await;
+    env.send(
+        &[ix::mint_receipt(user.pubkey(), usdc.keys(), 1_000 * USDC)],
+        &[&user],
+    )
+    .await
+    .unwrap();
+
+    // Receipts are plain SPL tokens, so they can change hands freely.
+    let holder = env.create_user(0, 0).await;
+    let receipt_mint = usdc.keys().receipt_mint();
+    let holder_receipts = usdc.keys().user_receipt_account(&holder.pubkey());
+    env.send(
+        &[
+            ata_instruction::create_associated_token_account_idempotent(
+                &env.admin.pubkey(),
+                &holder.pubkey(),
+                &receipt_mint,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::transfer(
+                &spl_token::ID,
+                &usdc.keys().user_receipt_account(&user.pubkey()),
+                &holder_receipts,
+                &user.pubkey(),
+                &[],
+                600 * USDC,
+            )
+            .unwrap(),
+        ],
+        &[&user],
+    )
+    .await
+    .unwrap();
+
+    env.send(
+        &[ix::deposit_receipt_as_collateral(
+            holder.pubkey(),
+            usdc.keys(),
+            600 * USDC,
+        )],
+        &[&holder],
+    )
+    .await
+    .unwrap();
+    assert_eq!(receipt_balance(&mut env, &holder, &usdc).await, 0);
+    let balance = env.user(&holder.pubkey()).await.balances[0];
+    assert_eq!(balance.bank_address, usdc.bank());
+    assert_eq!(balance.deposited_shares, 600 * USDC);
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.total_deposits_shares, 1_000 * USDC);
+
+    // $600 of collateral at a 75% max LTV supports $400 of SOL.
+    env.borrow(&holder, &usdc, &sol, 4 * SOL).await.unwrap();
+    assert_eq!(env.token_balance(&holder.pubkey(), &sol).await, 4 * SOL);
+}
+
+#[tokio::test]
+async fn redeem_pays_accrued_interest() {
+    let (mut env, user) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    let owner = user.pubkey();
+    env.send(
+        &[ix::mint_receipt(owner, usdc.keys(), 1_000 * USDC)],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    let borrower = env.create_user(0, 10 * SOL).await;
+    env.deposit(&borrower, &sol, 10 * SOL).await.unwrap();
+    env.borrow(&borrower, &sol, &usdc, 500 * USDC)
+        .await
+        .unwrap();
+    env.warp(60 * 60).await;
+    env.send(&[ix::refresh_bank(usdc.bank())], &[])
+        .await
+        .unwrap();
+    let bank = env.bank(&usdc).await;
+    let interest = bank.total_deposits - 1_000 * USDC;
+    assert!(interest > 0);
+
+    env.fund(&borrower.pubkey(), &usdc, interest).await;
+    env.repay(&borrower, &usdc, 500 * USDC + interest)
+        .await
+        .unwrap();
+    env.send(&[ix::redeem(owner, usdc.keys(), 1_000 * USDC)], &[&user])
+        .await
+        .unwrap();
+    let received = env.token_balance(&owner, &usdc).await;
+    assert!(received > 1_000 * USDC && received <= 1_000 * USDC + interest);
+    assert_eq!(env.bank(&usdc).await.total_deposits_shares, 0);
+}
+
+#[tokio::test]
+async fn bank_without_receipts_rejects_them() {
+    let mut env = TestEnv::new().await;
+    let usdc = env.usdc;
+    let user = env.create_user(1_000 * USDC, 0).await;
+
+    let result = env
+        .send(
+            &[ix::mint_receipt(user.pubkey(), usdc.keys(), 100 * USDC)],
+            &[&user],
+        )
+        .await;
+    assert_failed(result);
+}
+
+#[tokio::test]
+async fn paused_bank_blocks_minting_receipts() {
+    let (mut env, user) = setup().await;
+    let usdc = env.usdc;
+    let admin = env.admin.pubkey();
+    let params = lending_dapp::BankConfigParams {
+        paused: Some(true),
+        ..Default::default()
+    };
+    env.send(&[ix::update_bank_config(admin, usdc.mint, params)], &[])
+        .await
+        .unwrap();
+
+    let result = env
+        .send(
+            &[ix::mint_receipt(user.pubkey(), usdc.keys(), 100 * USDC)],
+            &[&user],
+        )
+        .await;
+    assert_error(result, ErrorCode::BankPaused);
+}
+
+#[tokio::test]
+async fn redeem_worth_no_tokens_fails() {
+    let (mut env, user) = setup().await;
+    let usdc = env.usdc;
+    let owner = user.pubkey();
+    env.send(
+        &[ix::mint_receipt(owner, usdc.keys(), 1_000 * USDC)],
+        &[&user],
+    )
+    .await
+    .unwrap();
+
+    // After a loss a share is worth less than a token, so one share rounds
+    // down to nothing and must not be burned for free.
+    let mut bank = env.bank(&usdc).await;
+    bank.total_deposits = 0;
+    let mut data = Vec::new();
+    bank.try_serialize(&mut data).unwrap();
+    env.set_program_account(usdc.bank(), lending_dapp::ID, data);
+
+    let result = env
+        .send(&[ix::redeem(owner, usdc.keys(), 1)], &[&user])
+        .await;
+    assert_error(result, ErrorCode::ZeroShares);
+    assert_eq!(receipt_balance(&mut env, &user, &usdc).await, 1_000 * USDC);
+}
+
+#[tokio::test]
+async fn collateral_can_be_withdrawn_as_receipts() {
+    let (mut env, user) = setup().await;
+    let usdc = env.usdc;
+    let owner = user.pubkey();
+    env.deposit(&user, &usdc, 1_000 * USDC).await.unwrap();
+
+    env.send(
+        &[ix::withdraw_collateral_as_receipt(
+            owner,
+            usdc.keys(),
+            400 * USDC,
+            &[],
+        )],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    assert_eq!(receipt_balance(&mut env, &user, &usdc).await, 400 * USDC);
+    let balance = env.user(&owner).await.balances[0];
+    assert_eq!(balance.deposited_shares, 600 * USDC);
+    assert_eq!(balance.deposited, 600 * USDC);
+    let bank = env.bank(&usdc).await;
+    assert_eq!(bank.total_deposits, 1_000 * USDC);
+    assert_eq!(bank.total_deposits_shares, 1_000 * USDC);
+
+    let result = env
+        .send(
+            &[ix::withdraw_collateral_as_receipt(
+                owner,
+                usdc.keys(),
+                600 * USDC + 1,
+                &[],
+            )],
+            &[&user],
+        )
+        .await;
+    assert_error(result, ErrorCode::InsufficientFunds);
+
+    env.send(
+        &[ix::deposit_receipt_as_collateral(
+            owner,
+            usdc.keys(),
+            400 * USDC,
+        )],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.user(&owner).await.balances[0].deposited_shares,
+        1_000 * USDC
+    );
+}
+
+#[tokio::test]
+async fn collateral_backing_debt_stays_in_the_position() {
+    let (mut env, user) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    let owner = user.pubkey();
+    env.seed_liquidity(&sol, 10 * SOL).await;
+    env.deposit(&user, &usdc, 1_000 * USDC).await.unwrap();
+    env.borrow(&user, &usdc, &sol, 4 * SOL).await.unwrap();
+
+    let result = env
+        .send(
+            &[ix::withdraw_collateral_as_receipt(
+                owner,
+                usdc.keys(),
+                400 * USDC,
+                &[],
+            )],
+            &[&user],
+        )
+        .await;
+    assert_error(result, ErrorCode::MissingHealthAccount);
+
+    // $400 of collateral at a 75% max LTV cannot back $400 of SOL.
+    let health_accounts = env.health_accounts(&owner).await;
+    let result = env
+        .send(
+            &[ix::withdraw_collateral_as_receipt(
+                owner,
+                usdc.keys(),
+                600 * USDC,
+                &health_accounts,
+            )],
+            &[&user],
+        )
+        .await;
+    assert_error(result, ErrorCode::UnhealthyPosition);
+
+    env.send(
+        &[ix::withdraw_collateral_as_receipt(
+            owner,
+            usdc.keys(),
+            400 * USDC,
+            &health_accounts,
+        )],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    assert_eq!(receipt_balance(&mut env, &user, &usdc).await, 400 * USDC);
+}
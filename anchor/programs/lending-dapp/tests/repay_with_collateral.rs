@@ -0,0 +1,112 @@
+mod common;
+
+use common::*;
+use lending_client::instructions as ix;
+use lending_client::instructions::HealthAccount;
+use lending_dapp::error::ErrorCode;
+use lending_dapp::USDT_USD_FEED_ID;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SECONDS_PER_HOUR: i64 = 60 * 60;
+
+/// Fills the swap pool with USDC and returns a user who borrowed 100 USDC
+/// against 2 SOL.
+async fn setup() -> (TestEnv, Keypair) {
+    let mut env = TestEnv::new().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    env.fund_swap_pool(&sol, 0).await;
+    env.fund_swap_pool(&usdc, 1_000 * USDC).await;
+    let user = env.create_user(0, 2 * SOL).await;
+    env.deposit(&user, &sol, 2 * SOL).await.unwrap();
+    env.borrow(&user, &sol, &usdc, 100 * USDC).await.unwrap();
+    (env, user)
+}
+
+/// Sells `sol_in` of the user's SOL deposit for `usdc_out` and repays USDC.
+fn repay_with_sol(
+    env: &TestEnv,
+    user: &Keypair,
+    sol_in: u64,
+    usdc_out: u64,
+    health_accounts: &[HealthAccount],
+) -> Instruction {
+    let (usdc, sol) = (env.usdc, env.sol);
+    let swap = mock_swap(&user.pubkey(), &sol, &usdc, sol_in, usdc_out);
+    ix::repay_with_collateral(
+        user.pubkey(),
+        sol.priced(),
+        usdc.priced(),
+        sol_in,
+        usdc_out,
+        &swap,
+        health_accounts,
+    )
+}
+
+#[tokio::test]
+async fn collateral_repays_debt_at_current_share_prices() {
+    let (mut env, user) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    // A SOL borrower makes the SOL deposits earn interest too.
+    env.seed_liquidity(&sol, 10 * SOL).await;
+    let sol_borrower = env.create_user(1_000 * USDC, 0).await;
+    env.deposit(&sol_borrower, &usdc, 1_000 * USDC)
+        .await
+        .unwrap();
+    env.borrow(&sol_borrower, &usdc, &sol, 5 * SOL)
+        .await
+        .unwrap();
+
+    env.warp(SECONDS_PER_HOUR).await;
+    env.set_price(&usdc, USDC_PRICE).await;
+    env.set_price(&sol, SOL_PRICE).await;
+    let instruction = repay_with_sol(&env, &user, SOL / 2, 50 * USDC, &[]);
+    env.send(&[instruction], &[&user]).await.unwrap();
+
+    // The whole swap output went to the debt, which had grown past it, and
+    // the wallet only holds the borrowed USDC.
+    assert_eq!(env.token_balance(&user.pubkey(), &usdc).await, 100 * USDC);
+    assert!(env.user(&user.pubkey()).await.balances[1].borrowed_shares > 0);
+    // The SOL bank was accrued before the withdrawal burnt its shares.
+    let sol_bank = env.bank(&sol).await;
+    env.send(&[ix::refresh_bank(sol.bank())], &[])
+        .await
+        .unwrap();
+    assert_eq!(
+        env.bank(&sol).await.deposit_share_price(),
+        sol_bank.deposit_share_price()
+    );
+}
+
+#[tokio::test]
+async fn repaying_one_debt_in_full_still_checks_the_others() {
+    let (mut env, user) = setup().await;
+    let sol = env.sol;
+    let usdt = env.add_asset("USDT", USDT_USD_FEED_ID, 6).await;
+    env.seed_liquidity(&usdt, 1_000 * USDC).await;
+    env.borrow(&user, &sol, &usdt, 40 * USDC).await.unwrap();
+    let others = [usdt.health_account()];
+
+    // The USDT debt stays behind, so its bank must be valued.
+    let instruction = repay_with_sol(&env, &user, SOL, 100 * USDC, &[]);
+    let result = env.send(&[instruction], &[&user]).await;
+    assert_error(result, ErrorCode::MissingHealthAccount);
+
+    // Half a SOL left backs $37.50, short of the $40 still owed.
+    let instruction = repay_with_sol(&env, &user, 3 * SOL / 2, 100 * USDC, &others);
+    let result = env.send(&[instruction], &[&user]).await;
+    assert_error(result, ErrorCode::UnhealthyPosition);
+
+    let instruction = repay_with_sol(&env, &user, SOL, 100 * USDC, &others);
+    env.send(&[instruction], &[&user]).await.unwrap();
+    let mut account = env.user(&user.pubkey()).await;
+    assert_eq!(
+        account
+            .get_balance(&env.usdc.bank())
+            .unwrap()
+            .borrowed_shares,
+        0
+    );
+}
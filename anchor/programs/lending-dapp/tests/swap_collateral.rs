@@ -0,0 +1,111 @@
+mod common;
+
+use common::*;
+use lending_client::instructions as ix;
+use lending_dapp::error::ErrorCode;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Lends out both banks so their deposits earn interest, fills the swap
+/// pool with SOL and returns a user with 100 USDC deposited.
+async fn setup() -> (TestEnv, Keypair) {
+    let mut env = TestEnv::new().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    env.seed_liquidity(&usdc, 1_000 * USDC).await;
+    env.seed_liquidity(&sol, 10 * SOL).await;
+    let borrower = env.create_user(1_000 * USDC, 10 * SOL).await;
+    env.deposit(&borrower, &sol, 10 * SOL).await.unwrap();
+    env.deposit(&borrower, &usdc, 1_000 * USDC).await.unwrap();
+    env.borrow(&borrower, &sol, &usdc, 500 * USDC)
+        .await
+        .unwrap();
+    env.borrow(&borrower, &usdc, &sol, 5 * SOL).await.unwrap();
+
+    env.fund_swap_pool(&usdc, 0).await;
+    env.fund_swap_pool(&sol, 10 * SOL).await;
+    let user = env.create_user(100 * USDC, 0).await;
+    env.deposit(&user, &usdc, 100 * USDC).await.unwrap();
+    (env, user)
+}
+
+/// Swaps `amount` of the user's USDC deposit for half a SOL.
+fn swap_usdc_for_sol(env: &TestEnv, user: &Keypair, amount: u64, min_out: u64) -> Instruction {
+    let (usdc, sol) = (env.usdc, env.sol);
+    let swap = mock_swap(&user.pubkey(), &usdc, &sol, amount, SOL / 2);
+    ix::swap_collateral(
+        user.pubkey(),
+        usdc.priced(),
+        sol.priced(),
+        amount,
+        min_out,
+        &swap,
+        &[],
+    )
+}
+
+#[tokio::test]
+async fn swap_accrues_interest_on_both_banks() {
+    let (mut env, user) = setup().await;
+    let (usdc, sol) = (env.usdc, env.sol);
+    let usdc_price = env.bank(&usdc).await.deposit_share_price();
+    let sol_price = env.bank(&sol).await.deposit_share_price();
+
+    env.warp(SECONDS_PER_DAY).await;
+    env.set_price(&usdc, USDC_PRICE).await;
+    env.set_price(&sol, SOL_PRICE).await;
+    let swap = swap_usdc_for_sol(&env, &user, 50 * USDC, SOL / 2);
+    env.send(&[swap], &[&user]).await.unwrap();
+
+    // Both banks were brought up to date before their shares moved, so a
+    // refresh at the same time has nothing left to accrue.
+    let usdc_bank = env.bank(&usdc).await;
+    let sol_bank = env.bank(&sol).await;
+    assert!(usdc_bank.deposit_share_price() > usdc_price);
+    assert!(sol_bank.deposit_share_price() > sol_price);
+    env.send(
+        &[ix::refresh_bank(usdc.bank()), ix::refresh_bank(sol.bank())],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.bank(&usdc).await.deposit_share_price(),
+        usdc_bank.deposit_share_price()
+    );
+    assert_eq!(
+        env.bank(&sol).await.deposit_share_price(),
+        sol_bank.deposit_share_price()
+    );
+}
+
+#[tokio::test]
+async fn swap_below_min_out_fails() {
+    let (mut env, user) = setup().await;
+    let swap = swap_usdc_for_sol(&env, &user, 50 * USDC, SOL);
+    let result = env.send(&[swap], &[&user]).await;
+    assert_error(result, ErrorCode::SlippageExceeded);
+}
+
+#[tokio::test]
+async fn swap_through_a_removed_program_fails() {
+    let (mut env, user) = setup().await;
+    let admin = env.admin.pubkey();
+    env.send(&[ix::remove_swap_program(admin, mock_swap::ID)], &[])
+        .await
+        .unwrap();
+
+    let swap = swap_usdc_for_sol(&env, &user, 50 * USDC, SOL / 2);
+    let result = env.send(&[swap], &[&user]).await;
+    assert_error(result, ErrorCode::SwapProgramNotAllowed);
+}
+
+#[tokio::test]
+async fn swap_with_missing_swap_accounts_fails() {
+    let (mut env, user) = setup().await;
+    let mut swap = swap_usdc_for_sol(&env, &user, 50 * USDC, SOL / 2);
+    swap.accounts.pop();
+    let result = env.send(&[swap], &[&user]).await;
+    assert_error(result, ErrorCode::InvalidSwapAccounts);
+}
@@ -0,0 +1,200 @@
+mod common;
+
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        interest_bearing_mint::InterestBearingConfig, permanent_delegate::PermanentDelegate,
+        BaseStateWithExtensionsMut, ExtensionType,
+    },
+};
+use common::*;
+use lending_dapp::error::ErrorCode;
+use lending_dapp::{MintExtensions, USDT_USD_FEED_ID};
+use pyth_solana_receiver_sdk::price_update::get_feed_id_from_hex;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// 1% on every transfer.
+const FEE_BPS: u16 = 100;
+const USDT: u64 = 1_000_000;
+
+/// A market with a USDT bank on a Token-2022 mint charging `FEE_BPS`, and a
+/// user holding 1,000 USDT and 10 SOL.
+async fn setup() -> (TestEnv, Asset, Keypair) {
+    let mut env = TestEnv::new().await;
+    let usdt = env
+        .add_token_2022_asset("USDT", USDT_USD_FEED_ID, 6, FEE_BPS)
+        .await;
+    let user = env.create_user(0, 10 * SOL).await;
+    env.fund(&user.pubkey(), &usdt, 1_000 * USDT).await;
+    (env, usdt, user)
+}
+
+fn after_fee(amount: u64) -> u64 {
+    amount - amount * FEE_BPS as u64 / 10_000
+}
+
+#[tokio::test]
+async fn deposit_credits_only_what_the_treasury_receives() {
+    let (mut env, usdt, user) = setup().await;
+
+    env.deposit(&user, &usdt, 100 * USDT).await.unwrap();
+    assert_eq!(env.treasury_balance(&usdt).await, after_fee(100 * USDT));
+    let bank = env.bank(&usdt).await;
+    assert_eq!(bank.total_deposits, after_fee(100 * USDT));
+    assert_eq!(
+        env.user(&user.pubkey()).await.balances[0].deposited,
+        after_fee(100 * USDT)
+    );
+    assert_eq!(env.token_balance(&user.pubkey(), &usdt).await, 900 * USDT);
+}
+
+#[tokio::test]
+async fn withdrawal_fee_is_borne_by_the_recipient() {
+    let (mut env, usdt, user) = setup().await;
+    env.deposit(&user, &usdt, 100 * USDT).await.unwrap();
+
+    let deposited = after_fee(100 * USDT);
+    env.withdraw(&user, &usdt, deposited).await.unwrap();
+    assert_eq!(env.treasury_balance(&usdt).await, 0);
+    assert_eq!(env.bank(&usdt).await.total_deposits, 0);
+    assert_eq!(
+        env.token_balance(&user.pubkey(), &usdt).await,
+        900 * USDT + after_fee(deposited)
+    );
+}
+
+#[tokio::test]
+async fn repay_reduces_debt_by_the_amount_received() {
+    let (mut env, usdt, user) = setup().await;
+    let sol = env.sol;
+    env.seed_liquidity(&usdt, 1_000 * USDT).await;
+    env.deposit(&user, &sol, 10 * SOL).await.unwrap();
+
+    env.borrow(&user, &sol, &usdt, 200 * USDT).await.unwrap();
+    env.repay(&user, &usdt, 100 * USDT).await.unwrap();
+
+    // Only 99 USDT reached the treasury, so 101 USDT of debt remains.
+    let bank = env.bank(&usdt).await;
+    let remaining = bank.total_borrowed;
+    assert!(remaining >= 200 * USDT - after_fee(100 * USDT));
+    assert!(remaining < 200 * USDT - after_fee(100 * USDT) + USDT / 100);
+    assert_eq!(
+        env.treasury_balance(&usdt).await,
+        after_fee(1_000 * USDT) - 200 * USDT + after_fee(100 * USDT)
+    );
+}
+
+/// An asset on a fresh Token-2022 mint address, for `init_asset_bank`.
+fn token_2022_asset() -> Asset {
+    Asset {
+        mint: Pubkey::new_unique(),
+        price_update: Pubkey::new_unique(),
+        feed_id: get_feed_id_from_hex(USDT_USD_FEED_ID).unwrap(),
+        token_program: spl_token_2022::ID,
+    }
+}
+
+#[tokio::test]
+async fn init_bank_rejects_a_permanent_delegate_unless_allowed() {
+    let mut env = TestEnv::new().await;
+    let asset = token_2022_asset();
+    let delegate = Pubkey::new_unique();
+    env.create_token_2022_mint(
+        asset.mint,
+        6,
+        &[ExtensionType::PermanentDelegate],
+        |state| {
+            state
+                .init_extension::<PermanentDelegate>(true)
+                .unwrap()
+                .delegate = Some(delegate).try_into().unwrap();
+        },
+    );
+
+    let result = env
+        .init_asset_bank(
+            &asset,
+            "USDT",
+            USDT_USD_FEED_ID,
+            6,
+            MintExtensions::default(),
+        )
+        .await;
+    assert_error(result, ErrorCode::MintExtensionNotAllowed);
+
+    let allowed = MintExtensions {
+        permanent_delegate: true,
+        ..Default::default()
+    };
+    env.init_asset_bank(&asset, "USDT", USDT_USD_FEED_ID, 6, allowed)
+        .await
+        .unwrap();
+    assert_eq!(env.bank(&asset).await.config.mint_extensions, allowed);
+}
+
+#[tokio::test]
+async fn init_bank_records_only_the_extensions_the_mint_uses() {
+    let mut env = TestEnv::new().await;
+    let asset = token_2022_asset();
+    let freezer = Pubkey::new_unique();
+    env.create_token_2022_mint(asset.mint, 6, &[], |state| {
+        state.base.freeze_authority = Some(freezer).into();
+    });
+
+    let result = env
+        .init_asset_bank(
+            &asset,
+            "USDT",
+            USDT_USD_FEED_ID,
+            6,
+            MintExtensions::default(),
+        )
+        .await;
+    assert_error(result, ErrorCode::MintExtensionNotAllowed);
+
+    let allowed = MintExtensions {
+        freeze_authority: true,
+        transfer_hook: true,
+        ..Default::default()
+    };
+    env.init_asset_bank(&asset, "USDT", USDT_USD_FEED_ID, 6, allowed)
+        .await
+        .unwrap();
+    let bank = env.bank(&asset).await;
+    assert_eq!(
+        bank.config.mint_extensions,
+        MintExtensions {
+            freeze_authority: true,
+            ..Default::default()
+        }
+    );
+}
+
+#[tokio::test]
+async fn init_bank_rejects_unsupported_mint_extensions() {
+    let mut env = TestEnv::new().await;
+    let asset = token_2022_asset();
+    // Interest accrues only in the displayed amount, which the oracle prices
+    // but the bank accounting never sees.
+    env.create_token_2022_mint(
+        asset.mint,
+        6,
+        &[ExtensionType::InterestBearingConfig],
+        |state| {
+            state.init_extension::<InterestBearingConfig>(true).unwrap();
+        },
+    );
+
+    let allow_all = MintExtensions {
+        freeze_authority: true,
+        permanent_delegate: true,
+        transfer_hook: true,
+        non_transferable: true,
+        default_account_state: true,
+    };
+    let result = env
+        .init_asset_bank(&asset, "USDT", USDT_USD_FEED_ID, 6, allow_all)
+        .await;
+    assert_error(result, ErrorCode::UnsupportedMintExtension);
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::*;
+use lending_client::instructions as ix;
+use lending_dapp::{USDC_USD_FEED_ID, USDT_USD_FEED_ID};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{Keypair, Signer};
+
+const USDT: u64 = 1_000_000;
+
+/// A market with a USDT bank whose mint runs the test transfer hook, and a
+/// user holding 1,000 USDT and 10 SOL.
+async fn setup() -> (TestEnv, Asset, Keypair) {
+    let mut env = TestEnv::new().await;
+    let usdt = env
+        .add_transfer_hook_asset("USDT", USDT_USD_FEED_ID, 6)
+        .await;
+    let user = env.create_user(0, 10 * SOL).await;
+    env.fund(&user.pubkey(), &usdt, 1_000 * USDT).await;
+    (env, usdt, user)
+}
+
+fn with_hook(mut instruction: Instruction, asset: &Asset) -> Instruction {
+    instruction.accounts.extend(asset.hook_metas());
+    instruction
+}
+
+#[tokio::test]
+async fn deposit_and_withdraw_resolve_the_hook_accounts() {
+    let (mut env, usdt, user) = setup().await;
+    let owner = user.pubkey();
+
+    // Token-2022 refuses the transfer when the hook accounts are missing.
+    let result = env.deposit(&user, &usdt, 100 * USDT).await;
+    assert_failed(result);
+
+    let deposit = ix::deposit(owner, usdt.keys(), 100 * USDT, &[]);
+    env.send(&[with_hook(deposit, &usdt)], &[&user])
+        .await
+        .unwrap();
+    assert_eq!(env.treasury_balance(&usdt).await, 100 * USDT);
+    assert_eq!(env.bank(&usdt).await.total_deposits, 100 * USDT);
+
+    let withdraw = ix::withdraw(owner, usdt.keys(), 40 * USDT, &[]);
+    env.send(&[with_hook(withdraw, &usdt)], &[&user])
+        .await
+        .unwrap();
+    assert_eq!(env.token_balance(&owner, &usdt).await, 940 * USDT);
+    assert_eq!(env.treasury_balance(&usdt).await, 60 * USDT);
+}
+
+#[tokio::test]
+async fn borrow_and_repay_resolve_the_hook_accounts() {
+    let (mut env, usdt, user) = setup().await;
+    let sol = env.sol;
+    let lender = env.create_user(0, 0).await;
+    env.fund(&lender.pubkey(), &usdt, 1_000 * USDT).await;
+    let deposit = ix::deposit(lender.pubkey(), usdt.keys(), 1_000 * USDT, &[]);
+    env.send(&[with_hook(deposit, &usdt)], &[&lender])
+        .await
+        .unwrap();
+    env.deposit(&user, &sol, 10 * SOL).await.unwrap();
+
+    let borrow = ix::borrow(user.pubkey(), sol.priced(), usdt.priced(), 200 * USDT, &[]);
+    env.send(&[with_hook(borrow, &usdt)], &[&user])
+        .await
+        .unwrap();
+    assert_eq!(env.token_balance(&user.pubkey(), &usdt).await, 1_200 * USDT);
+
+    let repay = ix::repay(user.pubkey(), usdt.keys(), 150 * USDT, None, &[]);
+    env.send(&[with_hook(repay, &usdt)], &[&user])
+        .await
+        .unwrap();
+    assert_eq!(env.treasury_balance(&usdt).await, 950 * USDT);
+    let bank = env.bank(&usdt).await;
+    assert!(bank.total_borrowed >= 50 * USDT && bank.total_borrowed < 51 * USDT);
+}
+
+#[tokio::test]
+async fn liquidation_finds_hook_accounts_after_health_accounts() {
+    let (mut env, usdt, _) = setup().await;
+    let sol = env.sol;
+    // Liquidations move both assets through one token program.
+    let collateral = env
+        .add_token_2022_asset("USDC", USDC_USD_FEED_ID, 6, 0)
+        .await;
+    let lender = env.create_user(0, 0).await;
+    env.fund(&lender.pubkey(), &usdt, 1_000 * USDT).await;
+    let deposit = ix::deposit(lender.pubkey(), usdt.keys(), 1_000 * USDT, &[]);
+    env.send(&[with_hook(deposit, &usdt)], &[&lender])
+        .await
+        .unwrap();
+
+    let borrower = env.create_user(0, 0).await;
+    env.fund(&borrower.pubkey(), &collateral, 100 * USDC).await;
+    env.deposit(&borrower, &collateral, 100 * USDC)
+        .await
+        .unwrap();
+    let borrow = ix::borrow(
+        borrower.pubkey(),
+        collateral.priced(),
+        usdt.priced(),
+        70 * USDT,
+        &[],
+    );
+    env.send(&[with_hook(borrow, &usdt)], &[&borrower])
+        .await
+        .unwrap();
+
+    // At $0.80 the collateral is worth $64 after the liquidation threshold,
+    // below the $70 of debt.
+    env.set_price(&collateral, 80_000_000).await;
+    let liquidator = env.create_user(0, 0).await;
+    env.fund(&liquidator.pubkey(), &usdt, 100 * USDT).await;
+    let liquidate = ix::liquidate(
+        liquidator.pubkey(),
+        borrower.pubkey(),
+        collateral.priced(),
+        usdt.priced(),
+        &[sol.health_account()],
+    );
+    env.send(&[with_hook(liquidate, &usdt)], &[&liquidator])
+        .await
+        .unwrap();
+
+    // Half the debt is repaid for its value plus a 5% bonus in collateral:
+    // 35 * 1.05 / 0.8.
+    assert_eq!(
+        env.token_balance(&liquidator.pubkey(), &usdt).await,
+        65 * USDT
+    );
+    assert_eq!(
+        env.token_balance(&liquidator.pubkey(), &collateral).await,
+        45_937_500
+    );
+}
@@ -0,0 +1,72 @@
+#![allow(unexpected_cfgs)]
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+declare_id!("FrsmKDk4rZ7R6sfC2SaiBBmuPr5Y9pyk6Vmcd7knrBX");
+
+/// Swaps `amount_in` of the source mint for exactly `amount_out` of the
+/// destination mint out of a pool vault funded by the test harness.
+#[program]
+pub mod mock_swap {
+    use super::*;
+
+    pub fn swap(context: Context<Swap>, amount_in: u64, amount_out: u64) -> Result<()> {
+        let accounts = TransferChecked {
+            from: context.accounts.user_source.to_account_info(),
+            to: context.accounts.pool_source.to_account_info(),
+            mint: context.accounts.source_mint.to_account_info(),
+            authority: context.accounts.user.to_account_info(),
+        };
+        let cpi_context = CpiContext::new(
+            context.accounts.source_token_program.to_account_info(),
+            accounts,
+        );
+        transfer_checked(cpi_context, amount_in, context.accounts.source_mint.decimals)?;
+
+        let seeds = &[b"pool".as_ref(), &[context.bumps.pool_authority]];
+        let signer_seeds = [&seeds[..]];
+        let accounts = TransferChecked {
+            from: context.accounts.pool_destination.to_account_info(),
+            to: context.accounts.user_destination.to_account_info(),
+            mint: context.accounts.destination_mint.to_account_info(),
+            authority: context.accounts.pool_authority.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            context.accounts.destination_token_program.to_account_info(),
+            accounts,
+            &signer_seeds,
+        );
+        transfer_checked(
+            cpi_context,
+            amount_out,
+            context.accounts.destination_mint.decimals,
+        )
+    }
+}
+
+#[derive(Accounts)]
+pub struct Swap<'info> {
+    pub user: Signer<'info>,
+    /// CHECK: only signs for the pool token accounts
+    #[account(seeds = [b"pool"], bump)]
+    pub pool_authority: UncheckedAccount<'info>,
+    pub source_mint: InterfaceAccount<'info, Mint>,
+    pub destination_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = source_mint)]
+    pub user_source: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = destination_mint)]
+    pub user_destination: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = source_mint)]
+    pub pool_source: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = destination_mint,
+        token::authority = pool_authority,
+    )]
+    pub pool_destination: InterfaceAccount<'info, TokenAccount>,
+    pub source_token_program: Interface<'info, TokenInterface>,
+    pub destination_token_program: Interface<'info, TokenInterface>,
+}
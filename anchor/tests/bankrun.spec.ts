@@ -2,18 +2,21 @@ import { describe, it } from 'node:test';
 import { BN, Program } from '@coral-xyz/anchor';
 import { BankrunProvider } from 'anchor-bankrun';
 import { AccountLayout } from "@solana/spl-token";
-import { TOKEN_PROGRAM_ID } from '@solana/spl-token';
+import { TOKEN_PROGRAM_ID, getAssociatedTokenAddressSync } from '@solana/spl-token';
 import { createAccount, createMint, mintTo } from 'spl-token-bankrun';
 import type { RawAccount } from "@solana/spl-token";
 import { PythSolanaReceiver } from '@pythnetwork/pyth-solana-receiver';
 import { assert } from "chai";
-import { startAnchor, BanksClient, ProgramTestContext } from 'solana-bankrun';
+import { startAnchor, BanksClient, Clock, ProgramTestContext } from 'solana-bankrun';
 
-import { PublicKey, Keypair, Connection } from '@solana/web3.js';
+import { PublicKey, Keypair, Connection, Transaction } from '@solana/web3.js';
 
 // @ts-ignore
 import IDL from '../target/idl/lending_dapp.json';
+// @ts-ignore
+import MockSwapIDL from '../target/idl/mock_swap.json';
 import { LendingDapp } from '../target/types/lending_dapp';
+import { MockSwap } from '../target/types/mock_swap';
 import { BankrunContextWrapper } from '../bankrun-utils/bankrunConnection';
 const getTokenBalance = async (
   provider:  BankrunProvider,
@@ -31,6 +34,9 @@ const getTokenBalance = async (
   const amount: BigInt = data.amount;
   return Number(amount);
 };
+const BPF_LOADER_UPGRADEABLE_ID = new PublicKey(
+  'BPFLoaderUpgradeab1e11111111111111111111111'
+);
 describe('Lending Smart Contract Tests', async () => {
   let signer: Keypair;
   let usdcBankAccount: PublicKey;
@@ -50,7 +56,10 @@ describe('Lending Smart Contract Tests', async () => {
 
   context = await startAnchor(
     '',
-    [{ name: 'lending_dapp', programId: new PublicKey(IDL.address) }],
+    [
+      { name: 'lending_dapp', programId: new PublicKey(IDL.address) },
+      { name: 'mock_swap', programId: new PublicKey(MockSwapIDL.address) },
+    ],
     [
       {
         address: pyth,
@@ -70,10 +79,18 @@ describe('Lending Smart Contract Tests', async () => {
   });
 
   const SOL_PRICE_FEED_ID =
-    '0xeaa020c61cc479712813461ce153894a96a6c00b21ed0cfc2798d1f9a9e9c94a';
+    '0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d';
     const USDC_PRICE_FEED_ID =
     '0xeaa020c61cc479712813461ce153894a96a6c00b21ed0cfc2798d1f9a9e9c94a';
 
+  const ALLOWED_MINT_EXTENSIONS = {
+    freezeAuthority: false,
+    permanentDelegate: false,
+    transferHook: false,
+    nonTransferable: false,
+    defaultAccountState: false,
+  };
+
   const solUsdPriceFeedAccount = pythSolanaReceiver
     .getPriceFeedAccountAddress(0, SOL_PRICE_FEED_ID)
     .toBase58();
@@ -95,6 +112,7 @@ describe('Lending Smart Contract Tests', async () => {
 
   console.log('Pyth Account Info:', accountInfo);
   program = new Program<LendingDapp>(IDL as LendingDapp, provider);
+  const mockSwapProgram = new Program<MockSwap>(MockSwapIDL as MockSwap, provider);
 
   banksClient = context.banksClient;
 
@@ -106,7 +124,7 @@ describe('Lending Smart Contract Tests', async () => {
     signer,
     signer.publicKey,
     null,
-    2
+    6
   );
 
   const mintSOL = await createMint(
@@ -115,7 +133,7 @@ describe('Lending Smart Contract Tests', async () => {
     signer,
     signer.publicKey,
     null,
-    2
+    9
   );
 
   [usdcBankAccount] = PublicKey.findProgramAddressSync(
@@ -177,9 +195,72 @@ describe('Lending Smart Contract Tests', async () => {
     
   });
 
+  it('Test Init Market', async () => {
+    // Only the upgrade authority may create the market, so make the signer
+    // the authority recorded in the program's ProgramData account.
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      BPF_LOADER_UPGRADEABLE_ID
+    );
+    const programDataInfo = await banksClient.getAccount(programData);
+    const data = Buffer.alloc(Math.max(programDataInfo?.data.length ?? 0, 45));
+    if (programDataInfo) {
+      data.set(programDataInfo.data);
+    }
+    data.writeUInt32LE(3, 0);
+    data.writeUInt8(1, 12);
+    signer.publicKey.toBuffer().copy(data, 13);
+    context.setAccount(programData, {
+      lamports: programDataInfo?.lamports ?? 1_000_000_000,
+      data,
+      owner: BPF_LOADER_UPGRADEABLE_ID,
+      executable: false,
+    });
+
+    const initMarketTx = await program.methods
+      .initMarket()
+      .accounts({
+        signer: signer.publicKey,
+      })
+      .rpc({ commitment: 'confirmed' });
+
+    console.log('Create Market Account', initMarketTx);
+    const [marketPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from('market')],
+      program.programId
+    );
+    const marketAccount = await program.account.market.fetch(marketPda);
+    assert.strictEqual(
+      marketAccount.owner.toBase58(),
+      signer.publicKey.toBase58(),
+      "Market owner should be the signer"
+    );
+    assert.deepEqual(
+      marketAccount.supportedAssets.map((asset: any) => asset.tickerSymbol),
+      ['SOL', 'USDC', 'USDT'],
+      "Market should be seeded with the known assets"
+    );
+  });
+
+  it('Test Init Bank Rejects Mislabeled Asset', async () => {
+    try {
+      await program.methods
+        .initBank(0.8, 0.75, SOL_PRICE_FEED_ID, "USDC", ALLOWED_MINT_EXTENSIONS)
+        .accounts({
+          signer: signer.publicKey,
+          mint: mintUSDC,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc({ commitment: 'confirmed' });
+      assert.fail("Init bank should fail with a mismatched oracle feed");
+    } catch (err) {
+      assert.include(err.toString(), 'InvalidOracleFeed');
+    }
+  });
+
   it('Test Init and Fund USDC Bank', async () => {
     const initUSDCBankTx = await program.methods
-      .initBank(1.2,0.8,USDC_PRICE_FEED_ID,"USDC")
+      .initBank(0.8, 0.75, USDC_PRICE_FEED_ID, "USDC", ALLOWED_MINT_EXTENSIONS)
       .accounts({
         signer: signer.publicKey,
         mint: mintUSDC,
@@ -229,7 +310,7 @@ describe('Lending Smart Contract Tests', async () => {
 
   it('Test Init amd Fund SOL Bank', async () => {
     const initSOLBankTx = await program.methods
-      .initBank(1.2, 0.8,SOL_PRICE_FEED_ID,"SOL")
+      .initBank(0.8, 0.75, SOL_PRICE_FEED_ID, "SOL", ALLOWED_MINT_EXTENSIONS)
       .accounts({
         signer: signer.publicKey,
         mint: mintSOL,
@@ -418,4 +499,225 @@ describe('Lending Smart Contract Tests', async () => {
 
     console.log('Withdraw USDC', withdrawUSDC);
   });
+
+  it('Test Flash Loan', async () => {
+    const [usdcBankPda] = PublicKey.findProgramAddressSync(
+      [mintUSDC.toBuffer()],
+      program.programId
+    );
+    const bankBefore = await program.account.bank.fetch(usdcBankPda);
+    const treasuryBefore = await getTokenBalance(provider, usdcBankAccount);
+
+    const flashAmount = new BN(1_000_000);
+    const flashBorrowIx = await program.methods
+      .flashBorrow(flashAmount)
+      .accounts({
+        signer: signer.publicKey,
+        mint: mintUSDC,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .instruction();
+    const flashRepayIx = await program.methods
+      .flashRepay(flashAmount)
+      .accounts({
+        signer: signer.publicKey,
+        mint: mintUSDC,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .instruction();
+    const flashLoanTx = await provider.sendAndConfirm(
+      new Transaction().add(flashBorrowIx, flashRepayIx)
+    );
+    console.log('Flash Loan USDC', flashLoanTx);
+
+    const fee = Math.ceil(
+      (flashAmount.toNumber() * bankBefore.config.flashLoanFeeBps) / 10_000
+    );
+    const bankAfter = await program.account.bank.fetch(usdcBankPda);
+    assert.strictEqual(
+      bankAfter.totalDeposits.toNumber(),
+      bankBefore.totalDeposits.toNumber() + fee,
+      "Flash loan fee should accrue to depositors"
+    );
+    assert.strictEqual(
+      await getTokenBalance(provider, usdcBankAccount),
+      treasuryBefore + fee,
+      "Treasury should receive the principal back plus the fee"
+    );
+  });
+
+  it('Test Flash Borrow Without Repay Fails', async () => {
+    try {
+      await program.methods
+        .flashBorrow(new BN(1_000_000))
+        .accounts({
+          signer: signer.publicKey,
+          mint: mintUSDC,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc({ commitment: 'confirmed' });
+      assert.fail("Flash borrow should require a flash repay");
+    } catch (err) {
+      assert.include(err.toString(), 'MissingFlashRepay');
+    }
+  });
+
+  it('Test Repay With Collateral', async () => {
+    await program.methods
+      .addSwapProgram(mockSwapProgram.programId)
+      .accounts({ signer: signer.publicKey })
+      .rpc({ commitment: 'confirmed' });
+
+    await program.methods
+      .borrow(new BN(1_000))
+      .accounts({
+        signer: signer.publicKey,
+        collateralMint: mintUSDC,
+        borrowMint: mintSOL,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        collateralPriceUpdate: usdcUsdPriceFeedAccount,
+        borrowPriceUpdate: solUsdPriceFeedAccount,
+      })
+      .rpc({ commitment: 'confirmed' });
+
+    // Fund a mock pool that pays out SOL for USDC at a fixed rate.
+    const [poolAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from('pool')],
+      mockSwapProgram.programId
+    );
+    const poolUsdc = await createAccount(
+      // @ts-ignore
+      banksClient,
+      signer,
+      mintUSDC,
+      poolAuthority,
+      Keypair.generate()
+    );
+    const poolSol = await createAccount(
+      // @ts-ignore
+      banksClient,
+      signer,
+      mintSOL,
+      poolAuthority,
+      Keypair.generate()
+    );
+    await mintTo(
+      // @ts-ignore
+      banksClient,
+      signer,
+      mintSOL,
+      poolSol,
+      signer,
+      1_000_000
+    );
+
+    const userUsdc = getAssociatedTokenAddressSync(mintUSDC, signer.publicKey);
+    const userSol = getAssociatedTokenAddressSync(mintSOL, signer.publicKey);
+    const collateralAmount = new BN(100);
+    const amountOut = new BN(1_000);
+    const swapIx = await mockSwapProgram.methods
+      .swap(collateralAmount, amountOut)
+      .accounts({
+        user: signer.publicKey,
+        sourceMint: mintUSDC,
+        destinationMint: mintSOL,
+        userSource: userUsdc,
+        userDestination: userSol,
+        poolSource: poolUsdc,
+        poolDestination: poolSol,
+        sourceTokenProgram: TOKEN_PROGRAM_ID,
+        destinationTokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .instruction();
+
+    const [solBankPda] = PublicKey.findProgramAddressSync(
+      [mintSOL.toBuffer()],
+      program.programId
+    );
+    const [userPda] = PublicKey.findProgramAddressSync(
+      [signer.publicKey.toBuffer()],
+      program.programId
+    );
+    const repayTx = await program.methods
+      .repayWithCollateral(
+        collateralAmount,
+        amountOut,
+        swapIx.data,
+        swapIx.keys.length
+      )
+      .accounts({
+        signer: signer.publicKey,
+        collateralMint: mintUSDC,
+        borrowMint: mintSOL,
+        collateralPriceUpdate: usdcUsdPriceFeedAccount,
+        borrowPriceUpdate: solUsdPriceFeedAccount,
+        swapProgram: mockSwapProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(swapIx.keys)
+      .rpc({ commitment: 'confirmed' });
+    console.log('Repay With Collateral', repayTx);
+
+    const userAccount = await program.account.user.fetch(userPda);
+    const solBalance = userAccount.balances.find(
+      (balance: any) => balance.bankAddress.toBase58() === solBankPda.toBase58()
+    );
+    assert.strictEqual(
+      solBalance.borrowedShares.toNumber(),
+      0,
+      "Swap output should have repaid the whole SOL debt"
+    );
+  });
+
+  it('Test Bank Rates View', async () => {
+    const [usdcBankPda] = PublicKey.findProgramAddressSync(
+      [mintUSDC.toBuffer()],
+      program.programId
+    );
+    const bank = await program.account.bank.fetch(usdcBankPda);
+    const rates = await program.methods
+      .getBankRates()
+      .accounts({ bank: usdcBankPda })
+      .view();
+    assert.strictEqual(
+      rates.totalDeposits.toNumber(),
+      bank.totalDeposits.toNumber(),
+      "View should report the bank's total deposits"
+    );
+    assert.strictEqual(rates.borrowRate, bank.interestRate);
+  });
+
+  it('Test Refresh Bank', async () => {
+    const [solBankPda] = PublicKey.findProgramAddressSync(
+      [mintSOL.toBuffer()],
+      program.programId
+    );
+    const before = await program.account.bank.fetch(solBankPda);
+    const clock = await banksClient.getClock();
+    context.setClock(
+      new Clock(
+        clock.slot + BigInt(1),
+        clock.epochStartTimestamp,
+        clock.epoch,
+        clock.leaderScheduleEpoch,
+        clock.unixTimestamp + BigInt(3600)
+      )
+    );
+
+    await program.methods
+      .refreshBank()
+      .accounts({ bank: solBankPda })
+      .rpc({ commitment: 'confirmed' });
+
+    const after = await program.account.bank.fetch(solBankPda);
+    assert.isAbove(
+      after.lastUpdatedBorrow.toNumber(),
+      before.lastUpdatedBorrow.toNumber(),
+      "Refresh should move the bank's accrual timestamp forward"
+    );
+    assert.isAtLeast(
+      after.totalBorrowed.toNumber(),
+      before.totalBorrowed.toNumber()
+    );
+  });
 });
\ No newline at end of file